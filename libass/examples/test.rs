@@ -52,7 +52,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
 
     let track = lib.new_track_from_file(sub_file, "UTF-8")?;
-    let frame = renderer.render_frame(track, timestamp);
+    let frame = renderer.render_frame(&track, timestamp);
     let image = frame.0.unwrap();
 
     let mut framebuffer = vec![0u8; 1920 * 1080 * 4];
@@ -0,0 +1,50 @@
+//! Feeds the input to a track the way a Matroska demuxer would, then renders
+//! it.
+//!
+//! Everything up to and including the first `Format:` line after `[Events]`
+//! is passed to `process_codec_private`, and every following line is passed
+//! to `process_chunk` as a `ReadOrder,Layer,Style,...` packet, half a second
+//! after the previous one.
+//!
+//! cargo fuzz run process_chunk
+#![no_main]
+
+use libass::{DefaultFontProvider, Library};
+use libfuzzer_sys::fuzz_target;
+
+fn split_header(data: &[u8]) -> (&[u8], &[u8]) {
+    let mut in_events = false;
+    let mut offset = 0;
+    for line in data.split_inclusive(|&b| b == b'\n') {
+        offset += line.len();
+        if line.starts_with(b"[Events]") {
+            in_events = true;
+        } else if in_events && line.starts_with(b"Format:") {
+            return data.split_at(offset);
+        }
+    }
+    (data, &[])
+}
+
+fuzz_target!(|data: &[u8]| {
+    let lib = Library::new().unwrap();
+    let mut renderer = lib.new_renderer().unwrap();
+    renderer.set_frame_size(640, 360);
+    renderer.set_fonts(None, "sans-serif", DefaultFontProvider::None, None, false);
+
+    let mut track = lib.new_track().unwrap();
+    let (header, packets) = split_header(data);
+    track.process_codec_private(&mut header.to_vec());
+
+    let mut timecode = 0;
+    for packet in packets.split(|&b| b == b'\n') {
+        track.process_chunk(&mut packet.to_vec(), timecode, 2000);
+        timecode += 500;
+    }
+
+    if let (Some(image), _) = renderer.render_frame(&track, 1000) {
+        // iterating checks the dimensions of each layer and copies its
+        // bitmap
+        image.for_each(drop);
+    }
+});
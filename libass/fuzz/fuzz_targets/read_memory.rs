@@ -0,0 +1,27 @@
+//! Loads the input as a whole script and renders it.
+//!
+//! cargo fuzz run read_memory
+#![no_main]
+
+use libass::{DefaultFontProvider, Library};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let lib = Library::new().unwrap();
+    let mut renderer = lib.new_renderer().unwrap();
+    renderer.set_frame_size(640, 360);
+    renderer.set_fonts(None, "sans-serif", DefaultFontProvider::None, None, false);
+
+    let track = match lib.new_track_from_memory(data, "UTF-8") {
+        Ok(track) => track,
+        Err(_) => return,
+    };
+
+    // render where something is on screen, if anything ever is
+    let now = track.change_points().points().first().map_or(0, |p| p.time);
+    if let (Some(image), _) = renderer.render_frame(&track, now) {
+        // iterating checks the dimensions of each layer and copies its
+        // bitmap
+        image.for_each(drop);
+    }
+});
@@ -0,0 +1,257 @@
+use crate::script::legacy_to_numpad_alignment;
+use crate::style::Style;
+use crate::tags::find_block;
+use crate::timeline::override_tags;
+use crate::track::{Event, Track};
+
+/// What an export to SRT or WebVTT had to leave out, as returned by
+/// [`Track::write_srt`] and [`Track::write_webvtt`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ExportReport {
+    pub losses: Vec<Loss>,
+}
+
+impl ExportReport {
+    pub fn is_lossless(&self) -> bool {
+        self.losses.is_empty()
+    }
+}
+
+/// Something an event had that the target format can't express.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Loss {
+    /// The id of the event.
+    pub event: i32,
+    pub kind: LossKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LossKind {
+    /// An override tag that was stripped, by name, e.g. `pos` or `1c`.
+    Tag(String),
+    /// A numpad-style alignment that was replaced by the default one.
+    Alignment(i32),
+    /// Vector drawings, which were dropped.
+    Drawing,
+    /// The event's effect, e.g. `Banner;5`, which was ignored.
+    Effect(String),
+}
+
+// An event converted to the common subset of SRT and WebVTT.
+pub(crate) struct ExportCue {
+    pub(crate) event: i32,
+    pub(crate) start: i64,
+    pub(crate) end: i64,
+    /// Lines separated by `\n`, with `<b>`, `<i>` and `<u>` tags.
+    pub(crate) text: String,
+    /// Numpad-style.
+    pub(crate) alignment: i32,
+}
+
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+struct Format {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Format {
+    fn from_style(style: &Style) -> Self {
+        Format {
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+        }
+    }
+
+    fn tags(self) -> Vec<char> {
+        let tags = [(self.bold, 'b'), (self.italic, 'i'), (self.underline, 'u')];
+        tags.iter()
+            .filter(|&&(on, _)| on)
+            .map(|&(_, tag)| tag)
+            .collect()
+    }
+}
+
+// Returns the events of `track` in time order, converted for a format that
+// only knows `<b>`, `<i>` and `<u>`, and records what got lost on the way.
+// `escape_html` escapes `&`, `<` and `>` in the text.
+pub(crate) fn export_cues(
+    track: &Track,
+    escape_html: bool,
+    report: &mut ExportReport,
+) -> Vec<ExportCue> {
+    let styles: Vec<Style> = (0..track.style_count() as i32)
+        .filter_map(|id| track.style(id))
+        .collect();
+    let mut events: Vec<Event<'_>> = track.events().filter(|e| e.end() > e.start()).collect();
+    events.sort_by_key(|e| (e.start(), e.read_order(), e.id));
+
+    let mut cues = Vec::with_capacity(events.len());
+    for event in &events {
+        let mut losses = Vec::new();
+        let cue = export_event(event, &styles, escape_html, &mut losses);
+        report.losses.extend(losses.into_iter().map(|kind| Loss {
+            event: event.id,
+            kind,
+        }));
+        if !cue.text.is_empty() {
+            cues.push(cue);
+        }
+    }
+    cues
+}
+
+fn export_event(
+    event: &Event,
+    styles: &[Style],
+    escape_html: bool,
+    losses: &mut Vec<LossKind>,
+) -> ExportCue {
+    let mut lose = |kind: LossKind| {
+        if !losses.contains(&kind) {
+            losses.push(kind);
+        }
+    };
+    let effect = event.effect();
+    if !effect.is_empty() {
+        lose(LossKind::Effect(effect.into_owned()));
+    }
+
+    let style = styles.get(event.style() as usize);
+    let base = style.map_or_else(Format::default, Format::from_style);
+    let mut format = base;
+    let mut alignment = None;
+    let mut drawing = false;
+
+    let mut out = String::new();
+    // tags open in `out`, innermost last
+    let mut open: Vec<char> = Vec::new();
+
+    let text = event.text();
+    let mut rest = &*text;
+    while !rest.is_empty() {
+        let (segment, block) = match find_block(rest) {
+            Some(open) => match rest[open..].find('}') {
+                Some(close) => {
+                    let block = &rest[open..=open + close];
+                    let segment = &rest[..open];
+                    rest = &rest[open + close + 1..];
+                    (segment, block)
+                }
+                None => (std::mem::take(&mut rest), ""),
+            },
+            None => (std::mem::take(&mut rest), ""),
+        };
+
+        if drawing {
+            if !segment.is_empty() {
+                lose(LossKind::Drawing);
+            }
+        } else if !segment.is_empty() {
+            set_format(&mut out, &mut open, format);
+            push_text(&mut out, segment, escape_html);
+        }
+
+        for (name, args) in override_tags(block) {
+            let args = args.trim();
+            let flag = |current: bool, reset: bool| match args {
+                "" => reset,
+                _ => args.parse::<i32>().map_or(current, |value| value != 0),
+            };
+            match name {
+                "b" => {
+                    format.bold = match args {
+                        "" => base.bold,
+                        // 1 or a font weight
+                        _ => args
+                            .parse::<i32>()
+                            .map_or(format.bold, |weight| weight == 1 || weight >= 600),
+                    }
+                }
+                "i" => format.italic = flag(format.italic, base.italic),
+                "u" => format.underline = flag(format.underline, base.underline),
+                "r" => {
+                    format = styles
+                        .iter()
+                        .rev()
+                        .find(|style| !args.is_empty() && style.name.as_bytes() == args.as_bytes())
+                        .map_or(base, Format::from_style)
+                }
+                "an" => {
+                    if alignment.is_none() {
+                        alignment = args.parse().ok().filter(|a| (1..=9).contains(a));
+                    }
+                }
+                "a" => {
+                    if alignment.is_none() {
+                        alignment = args
+                            .parse()
+                            .ok()
+                            .filter(|&a| a > 0)
+                            .map(legacy_to_numpad_alignment);
+                    }
+                }
+                "p" => drawing = args.parse::<f64>().is_ok_and(|scale| scale > 0.0),
+                // a stray backslash
+                "" => {}
+                _ => lose(LossKind::Tag(name.to_string())),
+            }
+        }
+    }
+    set_format(&mut out, &mut open, Format::default());
+
+    // blank lines would end the cue
+    let lines: Vec<&str> = out.split('\n').filter(|l| !l.trim().is_empty()).collect();
+
+    ExportCue {
+        event: event.id,
+        start: event.start(),
+        end: event.end(),
+        text: lines.join("\n"),
+        alignment: alignment
+            .unwrap_or_else(|| style.map_or(2, |s| legacy_to_numpad_alignment(s.alignment))),
+    }
+}
+
+// Closes and opens tags so that exactly those of `format` are open.
+fn set_format(out: &mut String, open: &mut Vec<char>, format: Format) {
+    let wanted = format.tags();
+    let keep = open
+        .iter()
+        .position(|tag| !wanted.contains(tag))
+        .unwrap_or(open.len());
+    for tag in open.drain(keep..).rev() {
+        out.push_str(&format!("</{}>", tag));
+    }
+    for tag in wanted {
+        if !open.contains(&tag) {
+            out.push_str(&format!("<{}>", tag));
+            open.push(tag);
+        }
+    }
+}
+
+fn push_text(out: &mut String, text: &str, escape_html: bool) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    out.push('\n');
+                }
+                Some('h') => {
+                    chars.next();
+                    out.push('\u{a0}');
+                }
+                Some('{') | Some('}') => out.push(chars.next().unwrap()),
+                _ => out.push('\\'),
+            },
+            '&' if escape_html => out.push_str("&amp;"),
+            '<' if escape_html => out.push_str("&lt;"),
+            '>' if escape_html => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+}
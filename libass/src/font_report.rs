@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::fonts::{faces, font_files, Face, FailedFont};
+use crate::library::Library;
+use crate::tags::{push_plain_text, EventText, Item, Part, Tag};
+use crate::track::Track;
+
+/// A font face a script asks for.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct FontRequest {
+    /// The family name, without the `@` of vertical fonts.
+    pub family: String,
+    /// 400 for regular and 700 for bold, or the weight given with `\b`.
+    pub weight: u16,
+    pub italic: bool,
+}
+
+/// Whether the fonts a track asks for are available, see
+/// [`Track::font_report`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FontReport {
+    /// Sorted by family, weight and slant.
+    pub fonts: Vec<FontCheck>,
+    /// Files and subdirectories of the fonts directory that couldn't be
+    /// read, and files that aren't fonts.
+    pub failed: Vec<FailedFont>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FontCheck {
+    pub request: FontRequest,
+    /// The events that draw text in the face, empty if only a style asks
+    /// for it.
+    pub events: Vec<i32>,
+    pub status: FontStatus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FontStatus {
+    /// A face of the family in the requested slant that is at most a
+    /// little lighter than requested, so libass doesn't emulate bold.
+    Found(FontSource),
+    /// Only other faces of the family, so libass will use the closest one
+    /// and emulate bold or italic.
+    Fallback(FontSource),
+    /// No face of the family, so libass will use the default font.
+    Missing,
+    /// No face in the requested weight and slant among the library's fonts
+    /// and the fonts directory, and the system fonts couldn't be listed
+    /// because `fc-list` failed.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FontSource {
+    /// Added with [`Library::add_font`].
+    Library,
+    /// A file in the fonts directory.
+    Directory(PathBuf),
+    /// A file found by fontconfig.
+    System(PathBuf),
+}
+
+impl FontReport {
+    pub fn missing(&self) -> impl Iterator<Item = &FontCheck> {
+        self.fonts
+            .iter()
+            .filter(|font| font.status == FontStatus::Missing)
+    }
+
+    pub fn fallbacks(&self) -> impl Iterator<Item = &FontCheck> {
+        self.fonts
+            .iter()
+            .filter(|font| matches!(font.status, FontStatus::Fallback(_)))
+    }
+
+    pub fn unknown(&self) -> impl Iterator<Item = &FontCheck> {
+        self.fonts
+            .iter()
+            .filter(|font| font.status == FontStatus::Unknown)
+    }
+}
+
+/// Text drawn in one face, see [`font_runs`].
+pub(crate) struct FontRun {
+    pub event: i32,
+    pub request: FontRequest,
+    /// The text with escapes like `\h` replaced.
+    pub text: String,
+}
+
+struct StyleFont {
+    name: String,
+    request: FontRequest,
+}
+
+pub(crate) fn family_name(name: &str) -> String {
+    let name = name.trim();
+    name.strip_prefix('@').unwrap_or(name).to_string()
+}
+
+// a style's Bold field, which libass takes as a weight unless it's 0, 1
+// or -1
+fn style_weight(bold: c_int) -> u16 {
+    match bold {
+        1 | -1 => 700,
+        bold if bold <= 0 => 400,
+        weight => weight.min(1000) as u16,
+    }
+}
+
+fn style_fonts(track: &Track) -> Vec<StyleFont> {
+    (0..track.style_count() as i32)
+        .filter_map(|id| {
+            let style = track.style(id)?;
+            // `Style::bold` only says whether it's bold
+            let bold = unsafe { (*track.as_ass_track().styles.offset(id as isize)).Bold };
+            Some(StyleFont {
+                name: style.name.to_string_lossy().into_owned(),
+                request: FontRequest {
+                    family: family_name(&style.font_name.to_string_lossy()),
+                    weight: style_weight(bold),
+                    italic: style.italic,
+                },
+            })
+        })
+        .collect()
+}
+
+/// The text of every event split where the face changes, following the
+/// event's style and its `\fn`, `\b`, `\i` and `\r` tags. Drawings are
+/// left out.
+pub(crate) fn font_runs(track: &Track) -> Vec<FontRun> {
+    let styles = style_fonts(track);
+    let mut runs: Vec<FontRun> = Vec::new();
+    for event in track.events() {
+        let style = match styles
+            .get(event.style() as usize)
+            .or_else(|| styles.first())
+        {
+            Some(style) => style,
+            None => continue,
+        };
+        // the style that tags without a value go back to
+        let mut base = &style.request;
+        let mut current = base.clone();
+        let mut drawing = false;
+        for part in EventText::parse(&event.text()).parts {
+            let items = match part {
+                Part::Text(text) => {
+                    let mut plain = String::new();
+                    push_plain_text(&mut plain, &text);
+                    match runs.last_mut() {
+                        _ if drawing || plain.is_empty() => {}
+                        Some(run) if run.event == event.id && run.request == current => {
+                            run.text.push_str(&plain)
+                        }
+                        _ => runs.push(FontRun {
+                            event: event.id,
+                            request: current.clone(),
+                            text: plain,
+                        }),
+                    }
+                    continue;
+                }
+                Part::Block(items) => items,
+            };
+            for item in items {
+                let tag = match item {
+                    Item::Tag(o) => o.tag,
+                    Item::Comment(_) => continue,
+                };
+                match tag {
+                    Tag::FontName(name) if name.trim().is_empty() => {
+                        current.family = base.family.clone()
+                    }
+                    Tag::FontName(name) => current.family = family_name(&name),
+                    Tag::Bold(weight) => {
+                        current.weight = match weight {
+                            None => base.weight,
+                            Some(0) => 400,
+                            Some(1) => 700,
+                            Some(weight) if weight >= 100 => weight.min(900) as u16,
+                            Some(_) => current.weight,
+                        }
+                    }
+                    Tag::Italic(italic) => current.italic = italic.unwrap_or(base.italic),
+                    Tag::Reset(name) => {
+                        base = name
+                            .and_then(|name| styles.iter().find(|s| s.name == name))
+                            .map_or(&style.request, |s| &s.request);
+                        current = base.clone();
+                    }
+                    Tag::Drawing(scale) => drawing = scale > 0,
+                    _ => {}
+                }
+            }
+        }
+    }
+    runs
+}
+
+impl<'library> Track<'library> {
+    /// Checks every face the track's styles and events ask for against the
+    /// fonts added to `library`, the fonts in `fonts_dir` and, if `system`
+    /// is set, the fonts fontconfig knows about. The implicit `Default`
+    /// style is only checked if an event uses it.
+    ///
+    /// Files in `fonts_dir` that can't be read are listed in
+    /// [`FontReport::failed`]. If `fc-list` can't be run, faces that
+    /// aren't found otherwise are [`FontStatus::Unknown`].
+    pub fn font_report(
+        &self,
+        library: &Library,
+        fonts_dir: Option<&Path>,
+        system: bool,
+    ) -> io::Result<FontReport> {
+        let mut requests: BTreeMap<FontRequest, Vec<i32>> = BTreeMap::new();
+        let default_used = self.events().any(|event| event.style() == 0);
+        for (id, style) in style_fonts(self).into_iter().enumerate() {
+            if id > 0 || default_used {
+                requests.entry(style.request).or_default();
+            }
+        }
+        for run in font_runs(self) {
+            let events = requests.entry(run.request).or_default();
+            if !events.contains(&run.event) {
+                events.push(run.event);
+            }
+        }
+
+        let mut sources: Vec<(FontSource, Face)> = library
+            .faces()
+            .iter()
+            .map(|face| (FontSource::Library, face.clone()))
+            .collect();
+        let mut failed = Vec::new();
+        if let Some(dir) = fonts_dir {
+            for path in font_files(dir, &mut failed)? {
+                let result = fs::read(&path).map_err(|e| e.to_string()).and_then(|data| {
+                    faces(&data).ok_or_else(|| "not a TrueType or OpenType font".to_string())
+                });
+                match result {
+                    Ok(faces) => {
+                        for face in faces {
+                            sources.push((FontSource::Directory(path.clone()), face));
+                        }
+                    }
+                    Err(error) => failed.push(FailedFont { path, error }),
+                }
+            }
+        }
+        let mut system_known = true;
+        if system {
+            match system_faces() {
+                Ok(faces) => sources.extend(faces),
+                Err(_) => system_known = false,
+            }
+        }
+
+        let fonts = requests
+            .into_iter()
+            .map(|(request, events)| {
+                let status = match status(&request, &sources) {
+                    status @ FontStatus::Found(_) => status,
+                    _ if !system_known => FontStatus::Unknown,
+                    status => status,
+                };
+                FontCheck {
+                    request,
+                    events,
+                    status,
+                }
+            })
+            .collect();
+        Ok(FontReport { fonts, failed })
+    }
+}
+
+fn status(request: &FontRequest, sources: &[(FontSource, Face)]) -> FontStatus {
+    match best_face(request, sources) {
+        Some(((source, _), true)) => FontStatus::Found(source.clone()),
+        Some(((source, _), false)) => FontStatus::Fallback(source.clone()),
+        None => FontStatus::Missing,
+    }
+}
+
+/// The face to use for `request`, and whether it has the requested slant
+/// and is heavy enough. Faces whose family or full name matches are
+/// candidates, and the closest weight wins.
+pub(crate) fn best_face<'a, T>(
+    request: &FontRequest,
+    faces: &'a [(T, Face)],
+) -> Option<(&'a (T, Face), bool)> {
+    let candidates: Vec<&(T, Face)> = faces
+        .iter()
+        .filter(|(_, face)| face_name(face, &request.family).is_some())
+        .collect();
+    let closest = candidates
+        .iter()
+        .filter(|(_, face)| face.italic == request.italic)
+        .min_by_key(|(_, face)| (face.weight as i32 - request.weight as i32).abs());
+    match closest {
+        // libass emulates bold when the face is more than 150 lighter
+        Some(face) if face.1.weight as i32 + 150 >= request.weight as i32 => Some((face, true)),
+        _ => Some((candidates.first()?, false)),
+    }
+}
+
+/// The family or full name of `face` that `family` refers to, compared
+/// without case like libass does.
+pub(crate) fn face_name<'a>(face: &'a Face, family: &str) -> Option<&'a str> {
+    face.families
+        .iter()
+        .chain(&face.full_names)
+        .find(|name| name.eq_ignore_ascii_case(family))
+        .map(String::as_str)
+}
+
+// fontconfig weights and their OpenType equivalents
+const FONTCONFIG_WEIGHTS: [(f64, f64); 12] = [
+    (0.0, 100.0),
+    (40.0, 200.0),
+    (50.0, 300.0),
+    (55.0, 350.0),
+    (75.0, 380.0),
+    (80.0, 400.0),
+    (100.0, 500.0),
+    (180.0, 600.0),
+    (200.0, 700.0),
+    (205.0, 800.0),
+    (210.0, 900.0),
+    (215.0, 1000.0),
+];
+
+fn opentype_weight(weight: f64) -> u16 {
+    let i = FONTCONFIG_WEIGHTS
+        .iter()
+        .position(|&(fc, _)| fc >= weight)
+        .unwrap_or(FONTCONFIG_WEIGHTS.len() - 1)
+        .max(1);
+    let (x0, y0) = FONTCONFIG_WEIGHTS[i - 1];
+    let (x1, y1) = FONTCONFIG_WEIGHTS[i];
+    let t = ((weight - x0) / (x1 - x0)).clamp(0.0, 1.0);
+    (y0 + t * (y1 - y0)).round() as u16
+}
+
+/// The faces fontconfig knows about, an error if `fc-list` can't be run
+/// or fails.
+fn system_faces() -> io::Result<Vec<(FontSource, Face)>> {
+    let output = Command::new("fc-list")
+        .arg("--format=%{file}\t%{family}\t%{fullname}\t%{weight}\t%{slant}\n")
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("fc-list failed: {}", output.status),
+        ));
+    }
+    let names = |list: &str| -> Vec<String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    // variable fonts give ranges like `[0 210]`
+    let number = |value: &str| -> Option<f64> {
+        value
+            .trim_start_matches('[')
+            .split_whitespace()
+            .next()?
+            .trim_end_matches(']')
+            .parse()
+            .ok()
+    };
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (file, family, full_name, weight, slant) = match fields[..] {
+                [file, family, full_name, weight, slant] => {
+                    (file, family, full_name, weight, slant)
+                }
+                _ => return None,
+            };
+            let face = Face {
+                families: names(family),
+                full_names: names(full_name),
+                postscript_name: None,
+                weight: number(weight).map_or(400, opentype_weight),
+                italic: number(slant).is_some_and(|slant| slant != 0.0),
+            };
+            Some((FontSource::System(PathBuf::from(file)), face))
+        })
+        .collect())
+}
@@ -0,0 +1,304 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::library::Library;
+
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+// far more than any font, since sizes in zip headers can't be trusted
+#[cfg(feature = "zip")]
+const MAX_FONT_SIZE: u64 = 256 << 20;
+
+/// What [`Library::add_fonts_from_dir`] or [`Library::add_fonts_from_zip`]
+/// loaded.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FontLoadReport {
+    pub loaded: Vec<LoadedFont>,
+    pub failed: Vec<FailedFont>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LoadedFont {
+    /// The file's path, or its name in the archive.
+    pub path: PathBuf,
+    /// The family names of every face in the file.
+    pub families: Vec<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FailedFont {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl FontLoadReport {
+    /// The family names of all loaded fonts, sorted and without duplicates.
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = self
+            .loaded
+            .iter()
+            .flat_map(|font| font.families.iter().map(String::as_str))
+            .collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+    }
+
+    fn add(&mut self, library: &Library, path: PathBuf, data: io::Result<Vec<u8>>) {
+        let result = data.map_err(|e| e.to_string()).and_then(|data| {
+            let faces =
+                faces(&data).ok_or_else(|| "not a TrueType or OpenType font".to_string())?;
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            library.add_font(&name, &data);
+            Ok(faces)
+        });
+        match result {
+            Ok(faces) => {
+                let mut families = Vec::new();
+                for family in faces.into_iter().flat_map(|face| face.families) {
+                    if !families.contains(&family) {
+                        families.push(family);
+                    }
+                }
+                self.loaded.push(LoadedFont { path, families });
+            }
+            Err(error) => self.failed.push(FailedFont { path, error }),
+        }
+    }
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            FONT_EXTENSIONS
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// The font files in `dir` and its subdirectories, sorted by path.
+/// Symbolic links to directories aren't followed, and subdirectories that
+/// can't be read are added to `failed`.
+pub(crate) fn font_files(dir: &Path, failed: &mut Vec<FailedFont>) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut dirs = Vec::new();
+    read_font_dir(dir, &mut paths, &mut dirs)?;
+    while let Some(dir) = dirs.pop() {
+        if let Err(error) = read_font_dir(&dir, &mut paths, &mut dirs) {
+            failed.push(FailedFont {
+                path: dir,
+                error: error.to_string(),
+            });
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_font_dir(dir: &Path, paths: &mut Vec<PathBuf>, dirs: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            dirs.push(path);
+        } else if is_font_file(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Library {
+    /// Adds every TrueType and OpenType font in `dir` and its
+    /// subdirectories, in path order. Only reading `dir` itself is an
+    /// error, files and subdirectories that can't be read are listed in the
+    /// report.
+    pub fn add_fonts_from_dir(&self, dir: impl AsRef<Path>) -> io::Result<FontLoadReport> {
+        let mut report = FontLoadReport::default();
+        for path in font_files(dir.as_ref(), &mut report.failed)? {
+            let data = fs::read(&path);
+            report.add(self, path, data);
+        }
+        Ok(report)
+    }
+
+    /// Adds every TrueType and OpenType font in a zip archive.
+    #[cfg(feature = "zip")]
+    pub fn add_fonts_from_zip(
+        &self,
+        reader: impl io::Read + io::Seek,
+    ) -> io::Result<FontLoadReport> {
+        use std::io::Read;
+
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut report = FontLoadReport::default();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let path = PathBuf::from(file.name());
+            if file.is_dir() || !is_font_file(&path) {
+                continue;
+            }
+            let mut data = Vec::new();
+            let data = (&mut file)
+                .take(MAX_FONT_SIZE + 1)
+                .read_to_end(&mut data)
+                .and_then(|size| match size as u64 {
+                    size if size > MAX_FONT_SIZE => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "larger than 256 MiB",
+                    )),
+                    _ => Ok(data),
+                });
+            report.add(self, path, data);
+        }
+        Ok(report)
+    }
+}
+
+/// A face of a font file, from its `name`, `OS/2` and `head` tables.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Face {
+    /// Family names in every language, as libass matches them.
+    pub families: Vec<String>,
+    pub full_names: Vec<String>,
+    pub postscript_name: Option<String>,
+    /// 100 to 900, 400 being regular and 700 bold.
+    pub weight: u16,
+    pub italic: bool,
+}
+
+pub(crate) fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The faces of a TrueType or OpenType font or collection, `None` if it
+/// isn't one.
+pub(crate) fn faces(data: &[u8]) -> Option<Vec<Face>> {
+    (0..face_count(data)?)
+        .map(|i| face(data, face_offset(data, i)?))
+        .collect()
+}
+
+fn face_count(data: &[u8]) -> Option<usize> {
+    if data.get(..4)? == b"ttcf" {
+        Some(u32_at(data, 8)? as usize)
+    } else {
+        Some(1)
+    }
+}
+
+/// The offset of the table directory of the face at `index`.
+pub(crate) fn face_offset(data: &[u8], index: usize) -> Option<usize> {
+    if index >= face_count(data)? {
+        None
+    } else if data.get(..4)? == b"ttcf" {
+        Some(u32_at(data, 12 + 4 * index)? as usize)
+    } else {
+        Some(0)
+    }
+}
+
+/// Decodes a string of the `name` table, `None` for encodings other than
+/// Unicode and Mac Roman.
+pub(crate) fn decode_name(platform: u16, encoding: u16, bytes: &[u8]) -> Option<String> {
+    match (platform, encoding) {
+        (0, _) | (3, 0) | (3, 1) | (3, 10) => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        // close enough for the ASCII names of most fonts
+        (1, 0) => Some(bytes.iter().map(|&b| b as char).collect()),
+        _ => None,
+    }
+}
+
+/// The offset and length of each table of the face at `offset`.
+pub(crate) fn tables(data: &[u8], offset: usize) -> Option<Vec<([u8; 4], usize, usize)>> {
+    let version = u32_at(data, offset)?;
+    if !matches!(version, 0x0001_0000 | 0x4F54_544F | 0x7472_7565) {
+        return None;
+    }
+    let count = u16_at(data, offset + 4)? as usize;
+    (0..count)
+        .map(|i| {
+            let record = offset + 12 + 16 * i;
+            let tag = data.get(record..record + 4)?;
+            let start = u32_at(data, record + 8)? as usize;
+            let length = u32_at(data, record + 12)? as usize;
+            data.get(start..start.checked_add(length)?)?;
+            Some(([tag[0], tag[1], tag[2], tag[3]], start, length))
+        })
+        .collect()
+}
+
+fn face(data: &[u8], offset: usize) -> Option<Face> {
+    let tables = tables(data, offset)?;
+    let table = |tag: &[u8; 4]| {
+        tables
+            .iter()
+            .find(|t| &t.0 == tag)
+            .map(|&(_, start, length)| &data[start..start + length])
+    };
+
+    let mut face = Face {
+        weight: 400,
+        ..Face::default()
+    };
+    if let Some(os2) = table(b"OS/2") {
+        face.weight = u16_at(os2, 4).unwrap_or(400);
+        face.italic = u16_at(os2, 62).is_some_and(|selection| selection & 1 != 0);
+    } else if let Some(head) = table(b"head") {
+        let style = u16_at(head, 44).unwrap_or(0);
+        face.weight = if style & 1 != 0 { 700 } else { 400 };
+        face.italic = style & 2 != 0;
+    }
+
+    let name = table(b"name")?;
+    let count = u16_at(name, 2)? as usize;
+    let strings = u16_at(name, 4)? as usize;
+    for i in 0..count {
+        let record = 6 + 12 * i;
+        let platform = u16_at(name, record)?;
+        let encoding = u16_at(name, record + 2)?;
+        let id = u16_at(name, record + 6)?;
+        let length = u16_at(name, record + 8)? as usize;
+        let start = strings + u16_at(name, record + 10)? as usize;
+        let bytes = match name.get(start..start + length) {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let value = match decode_name(platform, encoding, bytes) {
+            Some(value) => value,
+            None => continue,
+        };
+        let list = match id {
+            1 => &mut face.families,
+            4 => &mut face.full_names,
+            6 => {
+                face.postscript_name.get_or_insert(value);
+                continue;
+            }
+            _ => continue,
+        };
+        if !value.is_empty() && !list.contains(&value) {
+            list.push(value);
+        }
+    }
+
+    if face.families.is_empty() {
+        None
+    } else {
+        Some(face)
+    }
+}
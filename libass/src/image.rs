@@ -34,11 +34,20 @@ impl<'renderer> Iterator for Image<'renderer> {
         use crate::ImageKind::*;
         use ffi::ass_image__bindgen_ty_1::*;
 
+        // reading the bitmap row by row relies on these
+        assert!(
+            c_layer.w >= 0 && c_layer.h >= 0 && c_layer.stride >= c_layer.w,
+            "libass returned a {}x{} image with a stride of {}",
+            c_layer.w,
+            c_layer.h,
+            c_layer.stride
+        );
+
         let layer = Some(Layer {
             width: c_layer.w,
             height: c_layer.h,
             bitmap: {
-                let mut vec = Vec::with_capacity((c_layer.w * c_layer.h) as usize);
+                let mut vec = Vec::with_capacity(c_layer.w as usize * c_layer.h as usize);
                 let mut ptr = c_layer.bitmap;
                 for _ in 0..c_layer.h {
                     unsafe {
@@ -0,0 +1,81 @@
+use crate::tags::{EventText, Item, KaraokeKind, Part, Tag};
+use crate::track::Event;
+
+/// A karaoke syllable, started by a `\k`, `\K`, `\kf` or `\ko` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syllable {
+    /// Absolute start time in milliseconds.
+    pub start: i64,
+    /// Absolute end time in milliseconds.
+    pub end: i64,
+    /// The text up to the next syllable, with escapes like `\N` left as is.
+    pub text: String,
+    pub kind: KaraokeKind,
+    /// The other tags in effect for the syllable's text, in order: every
+    /// tag since the start of the event or the last `\r`, up to the
+    /// syllable's last text. Tags in the same block as the karaoke tag
+    /// count wherever they are in the block.
+    pub tags: Vec<Tag>,
+}
+
+impl<'track> Event<'track> {
+    /// Splits the event into karaoke syllables, timed the way libass does:
+    /// each syllable starts where the previous one ended, or where `\kt`
+    /// says. Text before the first karaoke tag belongs to no syllable.
+    pub fn syllables(&self) -> Vec<Syllable> {
+        syllables(&EventText::parse(&self.text()), self.start())
+    }
+}
+
+fn syllables(text: &EventText, event_start: i64) -> Vec<Syllable> {
+    let mut syllables: Vec<Syllable> = Vec::new();
+    // milliseconds from the event start
+    let mut offset = 0;
+    // the tags so far, which later text is drawn with
+    let mut state: Vec<Tag> = Vec::new();
+
+    for part in &text.parts {
+        let items = match part {
+            Part::Text(text) => {
+                if let Some(syllable) = syllables.last_mut() {
+                    syllable.text.push_str(text);
+                    syllable.tags.clone_from(&state);
+                }
+                continue;
+            }
+            Part::Block(items) => items,
+        };
+        let syllable_count = syllables.len();
+        for item in items {
+            let tag = match item {
+                Item::Tag(o) => &o.tag,
+                Item::Comment(_) => continue,
+            };
+            match *tag {
+                Tag::Karaoke {
+                    kind: KaraokeKind::Time,
+                    duration,
+                } => offset = (duration * 10.0) as i64,
+                Tag::Karaoke { kind, duration } => {
+                    let start = offset;
+                    offset += (duration * 10.0) as i64;
+                    syllables.push(Syllable {
+                        start: event_start + start,
+                        end: event_start + offset,
+                        text: String::new(),
+                        kind,
+                        tags: Vec::new(),
+                    });
+                }
+                Tag::Reset(_) => state = vec![tag.clone()],
+                _ => state.push(tag.clone()),
+            }
+        }
+        // the block's other tags apply from the syllable it starts
+        for syllable in &mut syllables[syllable_count..] {
+            syllable.tags.clone_from(&state);
+        }
+    }
+
+    syllables
+}
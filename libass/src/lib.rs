@@ -13,6 +13,58 @@ pub use crate::image::*;
 mod style;
 pub use crate::style::*;
 
+mod timeline;
+pub use crate::timeline::*;
+
+mod export;
+pub use crate::export::*;
+
+mod karaoke;
+pub use crate::karaoke::*;
+
+mod transcript;
+pub use crate::transcript::*;
+
+mod retime;
+
+mod timebase;
+pub use crate::timebase::*;
+
+mod packet;
+pub use crate::packet::*;
+
+mod fonts;
+pub use crate::fonts::*;
+
+mod font_report;
+pub use crate::font_report::*;
+
+mod subset;
+pub use crate::subset::*;
+
+mod renderer_config;
+pub use crate::renderer_config::*;
+
+mod pool;
+pub use crate::pool::*;
+
+mod live;
+pub use crate::live::*;
+
+pub mod script;
+
+pub mod srt;
+
+pub mod webvtt;
+
+pub mod tags;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "matroska")]
+pub mod matroska;
+
 #[derive(Debug)]
 pub struct Error;
 impl std::fmt::Display for Error {
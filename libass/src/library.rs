@@ -1,16 +1,23 @@
-use std::{ffi::CStr, os::raw::c_int};
+use std::cell::{Cell, Ref, RefCell};
+use std::ffi::CString;
 use std::ptr;
 use std::ptr::NonNull;
+use std::rc::Rc;
 use std::slice;
-use std::{ffi::CString, marker::PhantomData};
+use std::{ffi::CStr, os::raw::c_int};
 
 use libass_sys as ffi;
 
+use crate::fonts::{faces, Face};
 use crate::renderer::Renderer;
 use crate::track::Track;
 use crate::{err_if_null, Result};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DefaultFontProvider {
     None,
     Autodetect,
@@ -23,34 +30,111 @@ pub fn version() -> i32 {
     unsafe { ffi::ass_library_version() }
 }
 
-pub struct Library<'a> {
+/// The fonts and settings shared by renderers and tracks.
+///
+/// A `Library` is a handle that's cheap to clone, and every clone refers to
+/// the same fonts and settings. Renderers and tracks borrow the library they
+/// were created from, so it can't be dropped while they exist:
+///
+/// ```compile_fail
+/// # fn main() -> libass::Result<()> {
+/// let library = libass::Library::new()?;
+/// let track = library.new_track()?;
+/// drop(library);
+/// track.event_count();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # fn main() -> libass::Result<()> {
+/// let library = libass::Library::new()?;
+/// let mut renderer = library.new_renderer()?;
+/// drop(library);
+/// renderer.set_frame_size(640, 360);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// To store them next to the library, for example in a widget, use
+/// [`Library::new_owned_renderer`], [`Library::new_owned_track`] or
+/// [`Track::into_owned`], which keep the library alive instead:
+///
+/// ```no_run
+/// # fn main() -> libass::Result<()> {
+/// use libass::{Library, Renderer, Track};
+///
+/// struct Player {
+///     library: Library,
+///     renderer: Renderer<'static>,
+///     track: Track<'static>,
+/// }
+///
+/// let library = Library::new()?;
+/// let renderer = library.new_owned_renderer()?;
+/// let track = library
+///     .new_track_from_file("subs.ass", "UTF-8")?
+///     .into_owned();
+/// let player = Player {
+///     library,
+///     renderer,
+///     track,
+/// };
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A `Library` isn't `Send`, since its clones share the library. A
+/// [`RenderPool`](crate::RenderPool) renders on several threads with one.
+#[derive(Clone)]
+pub struct Library {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
     handle: NonNull<ffi::ass_library>,
-    phantom: PhantomData<&'a mut ffi::ass_library>,
+    // the faces of fonts added with `add_font`
+    faces: RefCell<Vec<Face>>,
+    // libass renderers keep using the added fonts
+    renderers: Cell<usize>,
 }
 
-impl<'a> Library<'a> {
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unsafe { ffi::ass_library_done(self.handle.as_ptr()) }
+    }
+}
+
+impl Library {
     pub fn new() -> Result<Self> {
         let lib = unsafe { ffi::ass_library_init() };
         err_if_null!(lib);
         Ok(Library {
-            handle: unsafe { NonNull::new_unchecked(lib) },
-            phantom: PhantomData,
+            inner: Rc::new(Inner {
+                handle: unsafe { NonNull::new_unchecked(lib) },
+                faces: RefCell::new(Vec::new()),
+                renderers: Cell::new(0),
+            }),
         })
     }
 
-    pub fn set_fonts_dir(&mut self, fonts_dir: &str) {
+    pub(crate) fn as_ptr(&self) -> *mut ffi::ass_library {
+        self.inner.handle.as_ptr()
+    }
+
+    pub fn set_fonts_dir(&self, fonts_dir: &str) {
         let fonts_dir = CString::new(fonts_dir).unwrap();
-        unsafe { ffi::ass_set_fonts_dir(self.handle.as_ptr(), fonts_dir.as_ptr()) }
+        unsafe { ffi::ass_set_fonts_dir(self.as_ptr(), fonts_dir.as_ptr()) }
     }
 
-    pub fn set_extract_fonts(&mut self, extract: bool) {
-        unsafe { ffi::ass_set_extract_fonts(self.handle.as_ptr(), extract as c_int) }
+    pub fn set_extract_fonts(&self, extract: bool) {
+        unsafe { ffi::ass_set_extract_fonts(self.as_ptr(), extract as c_int) }
     }
 
-    pub fn set_style_overrides(&mut self, list: &[&CStr]) {
+    pub fn set_style_overrides(&self, list: &[&CStr]) {
         unsafe {
             ffi::ass_set_style_overrides(
-                self.handle.as_ptr(),
+                self.as_ptr(),
                 list.iter()
                     .map(|x| x.as_ptr())
                     .collect::<Vec<_>>()
@@ -60,11 +144,15 @@ impl<'a> Library<'a> {
         };
     }
 
-    pub fn add_font(&mut self, name: &str, data: &[u8]) {
+    pub fn add_font(&self, name: &str, data: &[u8]) {
+        self.inner
+            .faces
+            .borrow_mut()
+            .extend(faces(data).unwrap_or_default());
         let name = CString::new(name).unwrap();
         unsafe {
             ffi::ass_add_font(
-                self.handle.as_ptr(),
+                self.as_ptr(),
                 name.as_ptr() as *mut _,
                 data.as_ptr() as *mut _,
                 data.len() as c_int,
@@ -72,20 +160,42 @@ impl<'a> Library<'a> {
         }
     }
 
-    pub fn clear_fonts(&mut self) {
-        unsafe { ffi::ass_clear_fonts(self.handle.as_ptr()) }
+    /// Removes the fonts added with [`Library::add_font`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a renderer created from the library still exists, since it
+    /// may still use the fonts.
+    pub fn clear_fonts(&self) {
+        assert_eq!(
+            self.inner.renderers.get(),
+            0,
+            "the library's fonts are still used by a renderer"
+        );
+        self.inner.faces.borrow_mut().clear();
+        unsafe { ffi::ass_clear_fonts(self.as_ptr()) }
+    }
+
+    pub(crate) fn renderer_created(&self) {
+        self.inner.renderers.set(self.inner.renderers.get() + 1);
+    }
+
+    pub(crate) fn renderer_dropped(&self) {
+        self.inner.renderers.set(self.inner.renderers.get() - 1);
+    }
+
+    pub(crate) fn faces(&self) -> Ref<'_, [Face]> {
+        Ref::map(self.inner.faces.borrow(), Vec::as_slice)
     }
 
-    pub fn get_available_font_providers(&mut self) -> Vec<DefaultFontProvider> {
+    pub fn get_available_font_providers(&self) -> Vec<DefaultFontProvider> {
         let mut providers: *mut ffi::ASS_DefaultFontProvider = ptr::null_mut();
         let providers_ptr = &mut providers as *mut *mut ffi::ASS_DefaultFontProvider;
 
         let mut size: usize = 0;
         let size_ptr = &mut size as *mut usize;
 
-        unsafe {
-            ffi::ass_get_available_font_providers(self.handle.as_ptr(), providers_ptr, size_ptr)
-        };
+        unsafe { ffi::ass_get_available_font_providers(self.as_ptr(), providers_ptr, size_ptr) };
 
         let providers_slice = unsafe { slice::from_raw_parts(providers, size) };
 
@@ -108,38 +218,58 @@ impl<'a> Library<'a> {
         vec
     }
 
-    pub fn new_renderer(&self) -> Result<Renderer> {
-        let renderer = unsafe { ffi::ass_renderer_init(self.handle.as_ptr() as *mut _) };
+    pub fn new_renderer(&self) -> Result<Renderer<'_>> {
+        self.renderer()
+    }
+
+    /// Creates a renderer that keeps the library alive instead of
+    /// borrowing it.
+    pub fn new_owned_renderer(&self) -> Result<Renderer<'static>> {
+        self.renderer()
+    }
+
+    fn renderer<'library>(&self) -> Result<Renderer<'library>> {
+        let renderer = unsafe { ffi::ass_renderer_init(self.as_ptr()) };
         err_if_null!(renderer);
-        unsafe { Ok(Renderer::new_unchecked(renderer)) }
+        unsafe { Ok(Renderer::new_unchecked(renderer, self)) }
     }
 
-    pub fn new_track(&self) -> Result<Track> {
-        let track = unsafe { ffi::ass_new_track(self.handle.as_ptr() as *mut _) };
-        err_if_null!(track);
-        unsafe { Ok(Track::new_unchecked(track)) }
+    pub fn new_track(&self) -> Result<Track<'_>> {
+        self.track()
     }
 
-    pub fn new_track_from_file(&self, filename: &str, codepage: &str) -> Result<Track> {
+    /// Creates an empty track that keeps the library alive instead of
+    /// borrowing it. Loaded tracks can be made so with
+    /// [`Track::into_owned`].
+    pub fn new_owned_track(&self) -> Result<Track<'static>> {
+        self.track()
+    }
+
+    fn track<'library>(&self) -> Result<Track<'library>> {
+        let track = unsafe { ffi::ass_new_track(self.as_ptr()) };
+        err_if_null!(track);
+        unsafe { Ok(Track::new_unchecked(track, self)) }
+    }
+    pub fn new_track_from_file(&self, filename: &str, codepage: &str) -> Result<Track<'_>> {
         let filename = CString::new(filename).unwrap();
         let cp = CString::new(codepage).unwrap();
         let track = unsafe {
             ffi::ass_read_file(
-                self.handle.as_ptr() as *mut _,
+                self.as_ptr() as *mut _,
                 filename.as_ptr() as *mut _,
                 cp.as_ptr() as *mut _,
             )
         };
 
         err_if_null!(track);
-        unsafe { Ok(Track::new_unchecked(track)) }
+        unsafe { Ok(Track::new_unchecked(track, self)) }
     }
 
-    pub fn new_track_from_memory(&self, data: &[u8], codepage: &str) -> Result<Track> {
+    pub fn new_track_from_memory(&self, data: &[u8], codepage: &str) -> Result<Track<'_>> {
         let cp = CString::new(codepage).unwrap();
         let track = unsafe {
             ffi::ass_read_memory(
-                self.handle.as_ptr() as *mut _,
+                self.as_ptr() as *mut _,
                 data.as_ptr() as *mut _,
                 data.len(),
                 cp.as_ptr() as *mut _,
@@ -147,12 +277,6 @@ impl<'a> Library<'a> {
         };
 
         err_if_null!(track);
-        unsafe { Ok(Track::new_unchecked(track)) }
-    }
-}
-
-impl<'a> Drop for Library<'a> {
-    fn drop(&mut self) {
-        unsafe { ffi::ass_library_done(self.handle.as_ptr()) }
+        unsafe { Ok(Track::new_unchecked(track, self)) }
     }
 }
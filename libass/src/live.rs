@@ -0,0 +1,99 @@
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use crate::packet::Packet;
+use crate::track::Track;
+
+/// A track fed with packets from another thread, for live captions.
+///
+/// The producer sends [`Packet`]s, in the format [`Track::packetize`]
+/// makes, and never waits for the renderer. Before rendering each frame,
+/// [`LiveTrack::update`] applies what has arrived, so a packet is on screen
+/// at most one frame after it's received, and drops the events that are
+/// over, so the track doesn't grow for as long as the feed runs.
+///
+/// ```no_run
+/// # fn main() -> libass::Result<()> {
+/// use std::thread;
+/// use libass::{Library, LiveTrack, Packet};
+///
+/// let library = Library::new()?;
+/// let mut renderer = library.new_renderer()?;
+/// let mut track = library.new_track()?;
+/// let mut header = std::fs::read("header.ass").unwrap();
+/// track.process_codec_private(&mut header);
+///
+/// let (mut live, sender) = LiveTrack::new(track);
+/// thread::spawn(move || {
+///     let data = b"0,0,Default,,0,0,0,,Hello".to_vec();
+///     let packet = Packet { start: 1000, duration: 2000, data };
+///     sender.send(packet).unwrap();
+/// });
+///
+/// for now in (0..5000).step_by(40) {
+///     live.update(now);
+///     let (image, _) = renderer.render_frame(live.track(), now);
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct LiveTrack<'library> {
+    track: Track<'library>,
+    packets: Receiver<Packet>,
+    connected: bool,
+}
+
+impl<'library> LiveTrack<'library> {
+    /// Wraps `track`, which should already have its header from
+    /// [`Track::process_codec_private`], and returns the sender for the
+    /// producer. The sender can be cloned for several producers.
+    pub fn new(track: Track<'library>) -> (Self, Sender<Packet>) {
+        let (sender, packets) = mpsc::channel();
+        let live = LiveTrack {
+            track,
+            packets,
+            connected: true,
+        };
+        (live, sender)
+    }
+
+    /// Applies the packets received since the last update and removes the
+    /// events that ended at or before `now`, in milliseconds. Packets that
+    /// are already over are skipped. Returns the number of packets applied.
+    pub fn update(&mut self, now: i64) -> usize {
+        let mut applied = 0;
+        loop {
+            match self.packets.try_recv() {
+                Ok(mut packet) => {
+                    if packet.start.saturating_add(packet.duration) <= now {
+                        continue;
+                    }
+                    self.track
+                        .process_chunk(&mut packet.data, packet.start, packet.duration);
+                    applied += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+        self.track.retain_events(|event| event.end() > now);
+        applied
+    }
+
+    /// `false` once every sender has been dropped and the last packets
+    /// have been applied.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn track(&self) -> &Track<'library> {
+        &self.track
+    }
+
+    pub fn into_track(self) -> Track<'library> {
+        self.track
+    }
+}
@@ -0,0 +1,618 @@
+//! Subtitle tracks and attachments of Matroska files.
+//!
+//! ```no_run
+//! use libass::matroska::Matroska;
+//! use libass::Library;
+//!
+//! let file = std::fs::File::open("movie.mkv").unwrap();
+//! let mkv = Matroska::read(file).unwrap();
+//! let library = Library::new().unwrap();
+//! mkv.add_fonts(&library);
+//! let track = mkv.tracks[0].load(&library).unwrap();
+//! ```
+
+use std::convert::TryFrom;
+use std::io::{self, BufReader, Read, Seek};
+
+use crate::library::Library;
+use crate::srt;
+use crate::track::Track;
+
+const EBML: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const INFO: u32 = 0x1549_A966;
+const TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_TYPE: u32 = 0x83;
+const FLAG_DEFAULT: u32 = 0x88;
+const NAME: u32 = 0x536E;
+const LANGUAGE: u32 = 0x22_B59C;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const CONTENT_ENCODINGS: u32 = 0x6D80;
+const CONTENT_ENCODING: u32 = 0x6240;
+const CONTENT_ENCODING_SCOPE: u32 = 0x5032;
+const CONTENT_ENCODING_TYPE: u32 = 0x5033;
+const CONTENT_COMPRESSION: u32 = 0x5034;
+const CONTENT_COMP_ALGO: u32 = 0x4254;
+const CONTENT_COMP_SETTINGS: u32 = 0x4255;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMECODE: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const BLOCK_DURATION: u32 = 0x9B;
+const CUES: u32 = 0x1C53_BB6B;
+const ATTACHMENTS: u32 = 0x1941_A469;
+const ATTACHED_FILE: u32 = 0x61A7;
+const FILE_NAME: u32 = 0x466E;
+const FILE_MIME_TYPE: u32 = 0x4660;
+const FILE_DATA: u32 = 0x465C;
+const CHAPTERS: u32 = 0x1043_A770;
+const TAGS: u32 = 0x1254_C367;
+
+const SUBTITLE_TRACK: u64 = 0x11;
+
+// the children of a segment, which end a cluster of unknown size
+const TOP_LEVEL: [u32; 8] = [
+    SEEK_HEAD,
+    INFO,
+    TRACKS,
+    CLUSTER,
+    CUES,
+    ATTACHMENTS,
+    CHAPTERS,
+    TAGS,
+];
+
+/// The subtitle tracks and attachments of a Matroska file.
+#[derive(Debug, Clone, Default)]
+pub struct Matroska {
+    /// ASS, SSA and SRT tracks, in file order.
+    pub tracks: Vec<SubtitleTrack>,
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SubtitleTrack {
+    pub number: u64,
+    /// `S_TEXT/ASS`, `S_TEXT/SSA` or `S_TEXT/UTF8`, or the older `S_ASS` or
+    /// `S_SSA`.
+    pub codec_id: String,
+    /// The script header for ASS and SSA tracks.
+    pub codec_private: Vec<u8>,
+    pub name: Option<String>,
+    pub language: Option<String>,
+    pub default: bool,
+    /// Decompressed blocks in file order.
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Block {
+    /// Time in milliseconds.
+    pub timecode: i64,
+    /// Duration in milliseconds, if the file has one.
+    pub duration: Option<i64>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Attachment {
+    pub name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// Whether the attachment looks like a font, by MIME type or extension.
+    pub fn is_font(&self) -> bool {
+        let mime_type = self.mime_type.to_ascii_lowercase();
+        let name = self.name.to_ascii_lowercase();
+        mime_type.starts_with("font/")
+            || mime_type.contains("truetype")
+            || mime_type.contains("opentype")
+            || mime_type.contains("font-sfnt")
+            || mime_type.contains("x-font")
+            || [".ttf", ".otf", ".ttc", ".otc"]
+                .iter()
+                .any(|extension| name.ends_with(extension))
+    }
+}
+
+impl Matroska {
+    /// Reads the subtitle tracks and attachments, seeking over the blocks
+    /// of other tracks.
+    pub fn read(reader: impl Read + Seek) -> io::Result<Self> {
+        let mut reader = Reader {
+            inner: BufReader::new(reader),
+            position: 0,
+            pending: None,
+        };
+        let mut mkv = Matroska::default();
+        let mut parser = Parser::default();
+
+        match reader.header()? {
+            Some(header) if header.id == EBML => reader.skip_element(&header)?,
+            _ => return Err(invalid("not a Matroska file")),
+        }
+        let segment = match reader.header()? {
+            Some(header) if header.id == SEGMENT => header,
+            _ => return Err(invalid("missing segment")),
+        };
+        let end = reader.end(&segment);
+
+        while reader.position < end {
+            let header = match reader.header()? {
+                Some(header) => header,
+                None => break,
+            };
+            match header.id {
+                INFO => parser.info(&mut reader, &header)?,
+                TRACKS => parser.tracks(&mut reader, &header, &mut mkv)?,
+                CLUSTER => parser.cluster(&mut reader, &header, &mut mkv)?,
+                ATTACHMENTS => parser.attachments(&mut reader, &header, &mut mkv)?,
+                _ => reader.skip_element(&header)?,
+            }
+        }
+
+        for track in &mut mkv.tracks {
+            track.blocks.sort_by_key(|block| block.timecode);
+        }
+        Ok(mkv)
+    }
+
+    /// Adds every font attachment to `library`.
+    pub fn add_fonts(&self, library: &Library) {
+        for attachment in self.attachments.iter().filter(|a| a.is_font()) {
+            library.add_font(&attachment.name, &attachment.data);
+        }
+    }
+}
+
+impl SubtitleTrack {
+    pub fn is_ass(&self) -> bool {
+        matches!(
+            self.codec_id.as_str(),
+            "S_TEXT/ASS" | "S_TEXT/SSA" | "S_ASS" | "S_SSA"
+        )
+    }
+
+    /// Creates a track from the subtitles, see
+    /// [`SubtitleTrack::process_into`].
+    pub fn load<'library>(&self, library: &'library Library) -> crate::Result<Track<'library>> {
+        let mut track = library.new_track()?;
+        self.process_into(&mut track);
+        Ok(track)
+    }
+
+    /// Feeds the codec private data and every block into `track`. SRT
+    /// blocks become events in the track's default style, and blocks
+    /// without a duration last until the next one.
+    pub fn process_into(&self, track: &mut Track) {
+        if self.is_ass() {
+            track.process_codec_private(&mut self.codec_private.clone());
+        }
+        let default_style = track.as_ass_track().default_style;
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            let duration = block.duration.unwrap_or_else(|| {
+                self.blocks
+                    .get(i + 1)
+                    .map_or(0, |next| next.timecode - block.timecode)
+            });
+            if self.is_ass() {
+                track.process_chunk(&mut block.data.clone(), block.timecode, duration);
+            } else {
+                let text = String::from_utf8_lossy(&block.data);
+                let mut event = track.add_event();
+                event.set_start(block.timecode);
+                event.set_duration(duration);
+                event.set_style(default_style);
+                event.set_text(&srt::to_ass_text(text.trim_end()));
+            }
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Header {
+    id: u32,
+    /// `None` for unknown sizes.
+    size: Option<u64>,
+}
+
+struct Reader<R> {
+    inner: BufReader<R>,
+    position: u64,
+    // a header read past the end of an element of unknown size
+    pending: Option<Header>,
+}
+
+impl<R: Read + Seek> Reader<R> {
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.inner.read(&mut byte)? {
+            0 => Ok(None),
+            _ => {
+                self.position += 1;
+                Ok(Some(byte[0]))
+            }
+        }
+    }
+
+    // Reads a variable-length integer, with its length marker if `keep_marker`.
+    fn vint(&mut self, keep_marker: bool) -> io::Result<Option<(u64, u32)>> {
+        let first = match self.byte()? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+        let length = first.leading_zeros() + 1;
+        if length > 8 {
+            return Err(invalid("invalid variable-length integer"));
+        }
+        let mut value = if keep_marker {
+            first as u64
+        } else {
+            (first as u64) & (0xFF >> length)
+        };
+        for _ in 1..length {
+            let byte = self.byte()?.ok_or_else(|| invalid("truncated file"))?;
+            value = value << 8 | byte as u64;
+        }
+        Ok(Some((value, length)))
+    }
+
+    fn header(&mut self) -> io::Result<Option<Header>> {
+        if let Some(header) = self.pending.take() {
+            return Ok(Some(header));
+        }
+        let id = match self.vint(true)? {
+            Some((id, length)) if length <= 4 => id as u32,
+            Some(_) => return Err(invalid("invalid element id")),
+            None => return Ok(None),
+        };
+        let (size, length) = self.vint(false)?.ok_or_else(|| invalid("truncated file"))?;
+        let unknown = size == (1 << (7 * length)) - 1;
+        Ok(Some(Header {
+            id,
+            size: if unknown { None } else { Some(size) },
+        }))
+    }
+
+    fn end(&self, header: &Header) -> u64 {
+        header.size.map_or(u64::MAX, |size| self.position + size)
+    }
+
+    fn skip(&mut self, count: u64) -> io::Result<()> {
+        let count = i64::try_from(count).map_err(|_| invalid("element too large"))?;
+        self.inner.seek_relative(count)?;
+        self.position += count as u64;
+        Ok(())
+    }
+
+    fn skip_element(&mut self, header: &Header) -> io::Result<()> {
+        match header.size {
+            Some(size) => self.skip(size),
+            None => Err(invalid("unknown size outside of a cluster")),
+        }
+    }
+
+    fn bytes(&mut self, header: &Header) -> io::Result<Vec<u8>> {
+        let size = header.size.ok_or_else(|| invalid("unknown size"))?;
+        let mut data = Vec::new();
+        (&mut self.inner).take(size).read_to_end(&mut data)?;
+        if (data.len() as u64) < size {
+            return Err(invalid("truncated file"));
+        }
+        self.position += size;
+        Ok(data)
+    }
+
+    fn uint(&mut self, header: &Header) -> io::Result<u64> {
+        let data = self.bytes(header)?;
+        if data.len() > 8 {
+            return Err(invalid("integer too large"));
+        }
+        Ok(data.iter().fold(0, |value, &byte| value << 8 | byte as u64))
+    }
+
+    fn string(&mut self, header: &Header) -> io::Result<String> {
+        let data = self.bytes(header)?;
+        let data = data.split(|&b| b == 0).next().unwrap_or(&[]);
+        Ok(String::from_utf8_lossy(data).into_owned())
+    }
+
+    // Calls `child` with each child of `parent` until its end.
+    fn children(
+        &mut self,
+        parent: &Header,
+        mut child: impl FnMut(&mut Self, Header) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let end = self.end(parent);
+        while self.position < end {
+            let header = match self.header()? {
+                Some(header) => header,
+                None => break,
+            };
+            if parent.size.is_none() && TOP_LEVEL.contains(&header.id) {
+                self.pending = Some(header);
+                break;
+            }
+            child(self, header)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum Compression {
+    Zlib,
+    HeaderStripping(Vec<u8>),
+}
+
+fn decompress(compression: &Option<Compression>, data: Vec<u8>) -> io::Result<Vec<u8>> {
+    match compression {
+        None => Ok(data),
+        Some(Compression::HeaderStripping(header)) => Ok([&header[..], &data[..]].concat()),
+        Some(Compression::Zlib) => {
+            let mut out = Vec::new();
+            flate2::read::ZlibDecoder::new(&data[..]).read_to_end(&mut out)?;
+            Ok(out)
+        }
+    }
+}
+
+struct Parser {
+    // nanoseconds per timecode unit
+    timecode_scale: u64,
+    // index in `Matroska::tracks` and block compression of each track number
+    tracks: Vec<(u64, usize, Option<Compression>)>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser {
+            timecode_scale: 1_000_000,
+            tracks: Vec::new(),
+        }
+    }
+}
+
+impl Parser {
+    fn ms(&self, timecode: i64) -> i64 {
+        (timecode as i128 * self.timecode_scale as i128 / 1_000_000) as i64
+    }
+
+    fn info<R: Read + Seek>(&mut self, reader: &mut Reader<R>, info: &Header) -> io::Result<()> {
+        reader.children(info, |reader, header| match header.id {
+            TIMECODE_SCALE => {
+                self.timecode_scale = reader.uint(&header)?.max(1);
+                Ok(())
+            }
+            _ => reader.skip_element(&header),
+        })
+    }
+
+    fn tracks<R: Read + Seek>(
+        &mut self,
+        reader: &mut Reader<R>,
+        tracks: &Header,
+        mkv: &mut Matroska,
+    ) -> io::Result<()> {
+        reader.children(tracks, |reader, header| {
+            if header.id != TRACK_ENTRY {
+                return reader.skip_element(&header);
+            }
+            let mut track = SubtitleTrack {
+                default: true,
+                ..SubtitleTrack::default()
+            };
+            let mut track_type = 0;
+            let mut encoding = Some((None, None));
+            reader.children(&header, |reader, header| {
+                match header.id {
+                    TRACK_NUMBER => track.number = reader.uint(&header)?,
+                    TRACK_TYPE => track_type = reader.uint(&header)?,
+                    FLAG_DEFAULT => track.default = reader.uint(&header)? != 0,
+                    NAME => track.name = Some(reader.string(&header)?),
+                    LANGUAGE => track.language = Some(reader.string(&header)?),
+                    CODEC_ID => track.codec_id = reader.string(&header)?,
+                    CODEC_PRIVATE => track.codec_private = reader.bytes(&header)?,
+                    CONTENT_ENCODINGS => encoding = content_encoding(reader, &header)?,
+                    _ => reader.skip_element(&header)?,
+                }
+                Ok(())
+            })?;
+
+            let supported = matches!(
+                track.codec_id.as_str(),
+                "S_TEXT/ASS" | "S_TEXT/SSA" | "S_ASS" | "S_SSA" | "S_TEXT/UTF8"
+            );
+            // encrypted tracks are left out
+            if let (SUBTITLE_TRACK, true, Some((compression, private))) =
+                (track_type, supported, encoding)
+            {
+                let data = std::mem::take(&mut track.codec_private);
+                track.codec_private = decompress(&private, data)?;
+                self.tracks
+                    .push((track.number, mkv.tracks.len(), compression));
+                mkv.tracks.push(track);
+            }
+            Ok(())
+        })
+    }
+
+    fn cluster<R: Read + Seek>(
+        &mut self,
+        reader: &mut Reader<R>,
+        cluster: &Header,
+        mkv: &mut Matroska,
+    ) -> io::Result<()> {
+        let mut timecode = 0;
+        reader.children(cluster, |reader, header| {
+            match header.id {
+                TIMECODE => timecode = reader.uint(&header)? as i64,
+                SIMPLE_BLOCK => {
+                    if let Some(block) = self.block(reader, &header, timecode)? {
+                        self.push(mkv, block);
+                    }
+                }
+                BLOCK_GROUP => {
+                    let mut block = None;
+                    let mut duration = None;
+                    reader.children(&header, |reader, header| {
+                        match header.id {
+                            BLOCK => block = self.block(reader, &header, timecode)?,
+                            BLOCK_DURATION => duration = Some(reader.uint(&header)? as i64),
+                            _ => reader.skip_element(&header)?,
+                        }
+                        Ok(())
+                    })?;
+                    if let Some((track, mut block)) = block {
+                        block.duration = duration.map(|duration| self.ms(duration));
+                        self.push(mkv, (track, block));
+                    }
+                }
+                _ => reader.skip_element(&header)?,
+            }
+            Ok(())
+        })
+    }
+
+    // Reads a block of a subtitle track, skipping others.
+    fn block<R: Read + Seek>(
+        &self,
+        reader: &mut Reader<R>,
+        header: &Header,
+        cluster_timecode: i64,
+    ) -> io::Result<Option<(usize, Block)>> {
+        let size = header.size.ok_or_else(|| invalid("unknown block size"))?;
+        let start = reader.position;
+        let (number, _) = reader
+            .vint(false)?
+            .ok_or_else(|| invalid("truncated file"))?;
+        let read = reader.position - start;
+        let track = self.tracks.iter().find(|track| track.0 == number);
+        let (index, compression) = match track {
+            Some((_, index, compression)) if size >= read + 3 => (*index, compression),
+            _ => {
+                reader.skip(size.saturating_sub(read))?;
+                return Ok(None);
+            }
+        };
+
+        let mut head = [0; 3];
+        reader.inner.read_exact(&mut head)?;
+        reader.position += 3;
+        let data = reader.bytes(&Header {
+            id: BLOCK,
+            size: Some(size - read - 3),
+        })?;
+        // laced subtitle blocks are exotic enough to leave out
+        if head[2] & 0x06 != 0 {
+            return Ok(None);
+        }
+
+        let relative = i16::from_be_bytes([head[0], head[1]]) as i64;
+        Ok(Some((
+            index,
+            Block {
+                timecode: self.ms(cluster_timecode + relative),
+                duration: None,
+                data: decompress(compression, data)?,
+            },
+        )))
+    }
+
+    fn push(&self, mkv: &mut Matroska, (index, block): (usize, Block)) {
+        mkv.tracks[index].blocks.push(block);
+    }
+
+    fn attachments<R: Read + Seek>(
+        &mut self,
+        reader: &mut Reader<R>,
+        attachments: &Header,
+        mkv: &mut Matroska,
+    ) -> io::Result<()> {
+        reader.children(attachments, |reader, header| {
+            if header.id != ATTACHED_FILE {
+                return reader.skip_element(&header);
+            }
+            let mut attachment = Attachment::default();
+            reader.children(&header, |reader, header| {
+                match header.id {
+                    FILE_NAME => attachment.name = reader.string(&header)?,
+                    FILE_MIME_TYPE => attachment.mime_type = reader.string(&header)?,
+                    FILE_DATA => attachment.data = reader.bytes(&header)?,
+                    _ => reader.skip_element(&header)?,
+                }
+                Ok(())
+            })?;
+            mkv.attachments.push(attachment);
+            Ok(())
+        })
+    }
+}
+
+// Reads the compression of the blocks of a track and of its codec private
+// data. `None` for encrypted tracks.
+fn content_encoding<R: Read + Seek>(
+    reader: &mut Reader<R>,
+    encodings: &Header,
+) -> io::Result<Option<(Option<Compression>, Option<Compression>)>> {
+    let mut result = Some((None, None));
+    reader.children(encodings, |reader, header| {
+        if header.id != CONTENT_ENCODING {
+            return reader.skip_element(&header);
+        }
+        let mut scope = 1;
+        let mut encoding_type = 0;
+        let mut algorithm = 0;
+        let mut settings = Vec::new();
+        reader.children(&header, |reader, header| {
+            match header.id {
+                CONTENT_ENCODING_SCOPE => scope = reader.uint(&header)?,
+                CONTENT_ENCODING_TYPE => encoding_type = reader.uint(&header)?,
+                CONTENT_COMPRESSION => reader.children(&header, |reader, header| {
+                    match header.id {
+                        CONTENT_COMP_ALGO => algorithm = reader.uint(&header)?,
+                        CONTENT_COMP_SETTINGS => settings = reader.bytes(&header)?,
+                        _ => reader.skip_element(&header)?,
+                    }
+                    Ok(())
+                })?,
+                _ => reader.skip_element(&header)?,
+            }
+            Ok(())
+        })?;
+
+        let compression = match (encoding_type, algorithm) {
+            (0, 0) => Some(Compression::Zlib),
+            (0, 3) => Some(Compression::HeaderStripping(std::mem::take(&mut settings))),
+            // encryption, or bzlib and lzo1x compression
+            _ => None,
+        };
+        // scope bit 1 is for the blocks and bit 2 for the codec private data
+        result = match (compression, result.take()) {
+            (Some(compression), Some((mut blocks, mut private))) => {
+                if scope & 2 != 0 {
+                    private = Some(compression.clone());
+                }
+                if scope & 1 != 0 {
+                    blocks = Some(compression);
+                }
+                Some((blocks, private))
+            }
+            _ => None,
+        };
+        Ok(())
+    })?;
+    Ok(result)
+}
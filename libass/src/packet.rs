@@ -0,0 +1,84 @@
+use crate::script::{EventLine, Script, Section};
+use crate::track::Track;
+
+/// A track split the way Matroska stores ASS subtitles, see
+/// [`Track::packetize`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Packets {
+    /// The script without its events, for the track's CodecPrivate.
+    pub codec_private: Vec<u8>,
+    /// One packet per event, ordered by start time.
+    pub packets: Vec<Packet>,
+}
+
+/// An event as a Matroska block.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Packet {
+    /// Start time in milliseconds.
+    pub start: i64,
+    /// Duration in milliseconds.
+    pub duration: i64,
+    /// `ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text`
+    pub data: Vec<u8>,
+}
+
+impl<'library> Track<'library> {
+    /// Splits the track into a header and one packet per event, the reverse
+    /// of [`Track::process_codec_private`] and [`Track::process_chunk`].
+    ///
+    /// Read orders are renumbered from zero in their current order, since
+    /// libass drops packets whose read order it has already seen.
+    pub fn packetize(&self) -> Packets {
+        let mut script = Script::from_track(self);
+        let mut events = Vec::new();
+        for section in &mut script.sections {
+            if let Section::Events(section) = section {
+                section.lines.retain(|line| match line {
+                    EventLine::Event(event) => {
+                        events.push(event.clone());
+                        false
+                    }
+                    _ => true,
+                });
+            }
+        }
+
+        // `Script::from_track` keeps the order of the track's events
+        let mut order: Vec<_> = self
+            .events()
+            .zip(events)
+            .map(|(e, event)| (e.read_order(), e.id, event))
+            .collect();
+        order.sort_by_key(|&(read_order, id, _)| (read_order, id));
+
+        let mut packets: Vec<_> = order
+            .into_iter()
+            .enumerate()
+            .map(|(read_order, (_, _, event))| {
+                let data = format!(
+                    "{},{},{},{},{},{},{},{},{}",
+                    read_order,
+                    event.layer,
+                    event.style,
+                    event.name,
+                    event.margin_l,
+                    event.margin_r,
+                    event.margin_v,
+                    event.effect,
+                    event.text
+                );
+                Packet {
+                    start: event.start,
+                    duration: event.end - event.start,
+                    data: data.into_bytes(),
+                }
+            })
+            .collect();
+        packets.sort_by_key(|packet| packet.start);
+
+        Packets {
+            codec_private: script.to_string().into_bytes(),
+            packets,
+        }
+    }
+}
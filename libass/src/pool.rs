@@ -0,0 +1,153 @@
+use std::panic;
+use std::thread;
+
+use crate::image::{Image, Layer};
+use crate::library::Library;
+use crate::renderer::Renderer;
+use crate::renderer_config::RendererConfig;
+use crate::track::Track;
+use crate::{Error, Result};
+
+/// Renders frames on several threads, each with its own renderer and its
+/// own copy of the track.
+///
+/// ```no_run
+/// # fn main() -> libass::Result<()> {
+/// use libass::{FontSettings, Library, RenderPool, RendererConfig};
+///
+/// let library = Library::new()?;
+/// let track = library.new_track_from_file("subs.ass", "UTF-8")?;
+/// let config = RendererConfig {
+///     frame_size: (1920, 1080),
+///     fonts: Some(FontSettings::default()),
+///     ..RendererConfig::default()
+/// };
+/// let mut pool = RenderPool::new(&library, 4, &config)?;
+/// pool.set_track(&track)?;
+/// let frames = pool.render(&[0, 1000, 2000, 3000]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct RenderPool<'library> {
+    library: &'library Library,
+    workers: Vec<Worker<'library>>,
+}
+
+struct Worker<'library> {
+    renderer: Renderer<'library>,
+    track: Option<Track<'library>>,
+}
+
+// SAFETY: a worker is only sent to a thread of `RenderPool::render`, which
+// has exclusive access to its renderer and track. While the threads run,
+// the calling thread waits for them and the library is neither `Send` nor
+// `Sync`, so nothing can add fonts to it, and the workers' tracks are
+// copies that were made beforehand. The threads don't clone or drop the
+// library handles kept by the renderer and track. libass keeps no other
+// state shared between renderers.
+struct SendWorker<'a, 'library>(&'a mut Worker<'library>);
+
+unsafe impl<'a, 'library> Send for SendWorker<'a, 'library> {}
+
+impl<'library> RenderPool<'library> {
+    /// Creates `threads` renderers, at least one, with `config`. Set
+    /// `config.fonts`, since libass doesn't render text before fonts are
+    /// set.
+    pub fn new(
+        library: &'library Library,
+        threads: usize,
+        config: &RendererConfig,
+    ) -> Result<Self> {
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let mut renderer = library.new_renderer()?;
+                renderer.set_config(config);
+                Ok(Worker {
+                    renderer,
+                    track: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(RenderPool { library, workers })
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn config(&self) -> &RendererConfig {
+        self.workers[0].renderer.config()
+    }
+
+    pub fn set_config(&mut self, config: &RendererConfig) {
+        for worker in &mut self.workers {
+            worker.renderer.set_config(config);
+        }
+    }
+
+    /// Gives every renderer a copy of `track`, see [`Track::try_clone`].
+    /// Fails if `track` wasn't created from the pool's library, since
+    /// libass only renders a track with renderers of the same library.
+    pub fn set_track(&mut self, track: &Track<'library>) -> Result<()> {
+        if !std::ptr::eq(track.as_ass_track().library, self.library.as_ptr()) {
+            return Err(Error);
+        }
+        for worker in &mut self.workers {
+            worker.track = Some(track.try_clone()?);
+        }
+        Ok(())
+    }
+
+    /// Renders the track at each of `times`, in milliseconds, and returns
+    /// the layers of each frame in the same order. The times are split into
+    /// one run per thread, so that consecutive frames share a renderer's
+    /// caches. Frames are empty until [`RenderPool::set_track`] is called.
+    pub fn render(&mut self, times: &[i64]) -> Vec<Vec<Layer>> {
+        self.render_with(times, |_, image| {
+            image.map_or_else(Vec::new, Iterator::collect)
+        })
+    }
+
+    /// Like [`RenderPool::render`], but calls `f` with the time and image
+    /// of each frame on the thread that rendered it, and returns what it
+    /// returns. The image is `None` when nothing is shown. A panic in `f`
+    /// is resumed on the calling thread once every thread has stopped.
+    pub fn render_with<T, F>(&mut self, times: &[i64], f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(i64, Option<Image<'_>>) -> T + Sync,
+    {
+        if times.is_empty() {
+            return Vec::new();
+        }
+        let run = times.len().div_ceil(self.workers.len());
+        let f = &f;
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .workers
+                .iter_mut()
+                .zip(times.chunks(run))
+                .map(|(worker, times)| {
+                    let worker = SendWorker(worker);
+                    scope.spawn(move || {
+                        let Worker { renderer, track } = worker.0;
+                        times
+                            .iter()
+                            .map(|&now| {
+                                let image = match track {
+                                    Some(track) => renderer.render_frame(track, now).0,
+                                    None => None,
+                                };
+                                f(now, image)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+}
@@ -4,19 +4,25 @@ use std::os::raw::c_int;
 use std::ptr::NonNull;
 
 use crate::image::Image;
-use crate::library::DefaultFontProvider;
+use crate::library::{DefaultFontProvider, Library};
+use crate::renderer_config::{CacheLimits, FontSettings, Margins, RendererConfig};
 use crate::style::{OverrideBits, Style};
 use crate::track::Track;
 
 use libass_sys as ffi;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ShapingLevel {
     Simple,
     Complex,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Hinting {
     None,
     Light,
@@ -31,20 +37,49 @@ pub enum Change {
     Content,
 }
 
+/// Renders tracks, created with [`Library::new_renderer`] or
+/// [`Library::new_owned_renderer`].
 pub struct Renderer<'library> {
     handle: NonNull<ffi::ass_renderer>,
-    phantom: PhantomData<&'library mut ffi::ass_renderer>,
+    // libass has no getters, so the settings are tracked here
+    config: RendererConfig,
+    // keeps the library alive for renderers that don't borrow it
+    library: Library,
+    borrow: PhantomData<&'library Library>,
 }
 
 impl<'library> Renderer<'library> {
-    pub(crate) unsafe fn new_unchecked(renderer: *mut ffi::ass_renderer) -> Self {
+    pub(crate) unsafe fn new_unchecked(
+        renderer: *mut ffi::ass_renderer,
+        library: &Library,
+    ) -> Self {
+        library.renderer_created();
         Renderer {
             handle: NonNull::new_unchecked(renderer),
-            phantom: PhantomData,
+            config: RendererConfig::default(),
+            library: library.clone(),
+            borrow: PhantomData,
         }
     }
 
-    pub fn render_frame(&mut self, track: Track, now: i64) -> (Option<Image>, Change) {
+    /// Renders `track` at `now` milliseconds. The image is valid until the
+    /// next frame is rendered.
+    ///
+    /// The track can't be changed while it's rendered, such as through an
+    /// event borrowed with [`Track::event_mut`]:
+    ///
+    /// ```compile_fail
+    /// # fn main() -> libass::Result<()> {
+    /// let library = libass::Library::new()?;
+    /// let mut renderer = library.new_renderer()?;
+    /// let mut track = library.new_track()?;
+    /// let mut event = track.add_event();
+    /// renderer.render_frame(&track, 0);
+    /// event.set_text("changed");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_frame(&mut self, track: &Track, now: i64) -> (Option<Image<'_>>, Change) {
         let mut change = 0;
         let change_ptr: *mut _ = &mut change;
 
@@ -79,11 +114,27 @@ impl<'library> Renderer<'library> {
         fontconfig_config_path: impl Into<Option<&'a str>>,
         update_fontconfig_cache: bool,
     ) {
-        let default_font: Option<CString> = default_font.into().map(|x| CString::new(x).unwrap());
-        let default_family: Option<CString> =
-            default_family.into().map(|x| CString::new(x).unwrap());
-        let fontconfig_config_path: Option<CString> = fontconfig_config_path
-            .into()
+        self.set_font_settings(FontSettings {
+            default_font: default_font.into().map(str::to_string),
+            default_family: default_family.into().map(str::to_string),
+            provider: default_font_provider,
+            fontconfig_config: fontconfig_config_path.into().map(str::to_string),
+            update_fontconfig_cache,
+        });
+    }
+
+    fn set_font_settings(&mut self, fonts: FontSettings) {
+        let default_font: Option<CString> = fonts
+            .default_font
+            .as_deref()
+            .map(|x| CString::new(x).unwrap());
+        let default_family: Option<CString> = fonts
+            .default_family
+            .as_deref()
+            .map(|x| CString::new(x).unwrap());
+        let fontconfig_config_path: Option<CString> = fonts
+            .fontconfig_config
+            .as_deref()
             .map(|x| CString::new(x).unwrap());
 
         macro_rules! unwrap_or_null {
@@ -96,7 +147,7 @@ impl<'library> Renderer<'library> {
         }
 
         use ffi::ASS_DefaultFontProvider::*;
-        let default_font_provider = match default_font_provider {
+        let default_font_provider = match fonts.provider {
             DefaultFontProvider::None => ASS_FONTPROVIDER_NONE,
             DefaultFontProvider::Autodetect => ASS_FONTPROVIDER_AUTODETECT,
             DefaultFontProvider::CoreText => ASS_FONTPROVIDER_CORETEXT,
@@ -111,17 +162,20 @@ impl<'library> Renderer<'library> {
                 unwrap_or_null!(default_family),
                 default_font_provider as c_int,
                 unwrap_or_null!(fontconfig_config_path),
-                update_fontconfig_cache as c_int,
+                fonts.update_fontconfig_cache as c_int,
             )
         };
+        self.config.fonts = Some(fonts);
     }
 
     pub fn set_frame_size(&mut self, width: i32, height: i32) {
         unsafe { ffi::ass_set_frame_size(self.handle.as_ptr(), width, height) }
+        self.config.frame_size = (width, height);
     }
 
     pub fn set_storage_size(&mut self, width: i32, height: i32) {
         unsafe { ffi::ass_set_storage_size(self.handle.as_ptr(), width, height) }
+        self.config.storage_size = (width, height);
     }
 
     pub fn set_shaper(&mut self, level: ShapingLevel) {
@@ -129,32 +183,44 @@ impl<'library> Renderer<'library> {
             use crate::renderer::ShapingLevel::*;
             use ffi::ASS_ShapingLevel::*;
             ffi::ass_set_shaper(self.handle.as_ptr(), {
-                match level {
+                match &level {
                     Simple => ASS_SHAPING_SIMPLE,
                     Complex => ASS_SHAPING_COMPLEX,
                 }
             })
         }
+        self.config.shaper = level;
     }
 
     pub fn set_margins(&mut self, top: i32, bottom: i32, left: i32, right: i32) {
         unsafe { ffi::ass_set_margins(self.handle.as_ptr(), top, bottom, left, right) }
+        self.config.margins = Margins {
+            top,
+            bottom,
+            left,
+            right,
+        };
     }
 
     pub fn use_margins(&mut self, use_: bool) {
         unsafe { ffi::ass_set_use_margins(self.handle.as_ptr(), use_ as c_int) }
+        self.config.use_margins = use_;
     }
 
     pub fn set_pixel_aspect_ratio(&mut self, par: f64) {
         unsafe { ffi::ass_set_pixel_aspect(self.handle.as_ptr(), par) }
+        self.config.pixel_aspect_ratio = par;
     }
 
     pub fn set_aspect_ratio(&mut self, dar: f64, sar: f64) {
         unsafe { ffi::ass_set_aspect_ratio(self.handle.as_ptr(), dar, sar) }
+        // what libass sets the pixel aspect ratio to
+        self.config.pixel_aspect_ratio = dar / sar;
     }
 
     pub fn set_font_scale(&mut self, font_scale: f64) {
         unsafe { ffi::ass_set_font_scale(self.handle.as_ptr(), font_scale) }
+        self.config.font_scale = font_scale;
     }
 
     pub fn set_hinting(&mut self, font_hinting: Hinting) {
@@ -162,7 +228,7 @@ impl<'library> Renderer<'library> {
             use crate::Hinting::*;
             use ffi::ASS_Hinting::*;
             ffi::ass_set_hinting(self.handle.as_ptr(), {
-                match font_hinting {
+                match &font_hinting {
                     None => ASS_HINTING_NONE,
                     Light => ASS_HINTING_LIGHT,
                     Normal => ASS_HINTING_NORMAL,
@@ -170,18 +236,86 @@ impl<'library> Renderer<'library> {
                 }
             })
         }
+        self.config.hinting = font_hinting;
     }
 
     pub fn set_line_spacing(&mut self, line_spacing: f64) {
         unsafe { ffi::ass_set_line_spacing(self.handle.as_ptr(), line_spacing) }
+        self.config.line_spacing = line_spacing;
     }
 
     pub fn set_line_position(&mut self, line_position: f64) {
         unsafe { ffi::ass_set_line_position(self.handle.as_ptr(), line_position) }
+        self.config.line_position = line_position;
     }
 
     pub fn set_cache_limits(&mut self, glyph_max: i32, bitmap_max_size: i32) {
         unsafe { ffi::ass_set_cache_limits(self.handle.as_ptr(), glyph_max, bitmap_max_size) }
+        self.config.cache_limits = CacheLimits {
+            glyph_max,
+            bitmap_max_size,
+        };
+    }
+
+    /// The settings applied so far, starting from
+    /// [`RendererConfig::default`].
+    pub fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    /// Applies every setting of `config` that differs from
+    /// [`Renderer::config`]. Fonts are only set if `config.fonts` isn't
+    /// `None`, since libass can't unset them.
+    pub fn set_config(&mut self, config: &RendererConfig) {
+        let current = self.config.clone();
+        if config.frame_size != current.frame_size {
+            self.set_frame_size(config.frame_size.0, config.frame_size.1);
+        }
+        if config.storage_size != current.storage_size {
+            self.set_storage_size(config.storage_size.0, config.storage_size.1);
+        }
+        if let Some(fonts) = &config.fonts {
+            if current.fonts.as_ref() != Some(fonts) {
+                self.set_font_settings(fonts.clone());
+            }
+        }
+        if config.margins != current.margins {
+            let Margins {
+                top,
+                bottom,
+                left,
+                right,
+            } = config.margins;
+            self.set_margins(top, bottom, left, right);
+        }
+        if config.use_margins != current.use_margins {
+            self.use_margins(config.use_margins);
+        }
+        if config.pixel_aspect_ratio != current.pixel_aspect_ratio {
+            self.set_pixel_aspect_ratio(config.pixel_aspect_ratio);
+        }
+        if config.font_scale != current.font_scale {
+            self.set_font_scale(config.font_scale);
+        }
+        if config.hinting != current.hinting {
+            self.set_hinting(config.hinting.clone());
+        }
+        if config.line_spacing != current.line_spacing {
+            self.set_line_spacing(config.line_spacing);
+        }
+        if config.line_position != current.line_position {
+            self.set_line_position(config.line_position);
+        }
+        if config.cache_limits != current.cache_limits {
+            let CacheLimits {
+                glyph_max,
+                bitmap_max_size,
+            } = config.cache_limits;
+            self.set_cache_limits(glyph_max, bitmap_max_size);
+        }
+        if config.shaper != current.shaper {
+            self.set_shaper(config.shaper.clone());
+        }
     }
 
     pub fn set_selective_style_override(&mut self, style: &Style) {
@@ -213,5 +347,6 @@ impl<'library> Renderer<'library> {
 impl<'library> Drop for Renderer<'library> {
     fn drop(&mut self) {
         unsafe { ffi::ass_renderer_done(self.handle.as_ptr()) }
+        self.library.renderer_dropped();
     }
 }
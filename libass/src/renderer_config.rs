@@ -0,0 +1,101 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::library::DefaultFontProvider;
+use crate::renderer::{Hinting, ShapingLevel};
+
+/// All settings of a [`Renderer`](crate::Renderer), applied with
+/// [`Renderer::set_config`](crate::Renderer::set_config) and read back with
+/// [`Renderer::config`](crate::Renderer::config).
+///
+/// The default is what libass starts a renderer with. With the `serde`
+/// feature, fields missing when deserializing take their default.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct RendererConfig {
+    /// Width and height in pixels, `(0, 0)` until set.
+    pub frame_size: (i32, i32),
+    /// Width and height of the video before scaling to the frame, `(0, 0)`
+    /// for the frame size.
+    pub storage_size: (i32, i32),
+    /// `None` until fonts are set, which libass needs before rendering.
+    pub fonts: Option<FontSettings>,
+    pub margins: Margins,
+    /// Whether to place toptitles and subtitles in the margins.
+    pub use_margins: bool,
+    /// 0 to derive it from the frame and storage sizes.
+    pub pixel_aspect_ratio: f64,
+    pub font_scale: f64,
+    pub hinting: Hinting,
+    pub line_spacing: f64,
+    /// 0 to 100, moving subtitles from the bottom towards the top.
+    pub line_position: f64,
+    pub cache_limits: CacheLimits,
+    pub shaper: ShapingLevel,
+}
+
+/// The arguments of [`Renderer::set_fonts`](crate::Renderer::set_fonts).
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct FontSettings {
+    /// Path of the font to use when none matches.
+    pub default_font: Option<String>,
+    /// Family to use when none matches.
+    pub default_family: Option<String>,
+    pub provider: DefaultFontProvider,
+    pub fontconfig_config: Option<String>,
+    pub update_fontconfig_cache: bool,
+}
+
+/// Margins in pixels, see
+/// [`Renderer::set_margins`](crate::Renderer::set_margins).
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Margins {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32,
+}
+
+/// See [`Renderer::set_cache_limits`](crate::Renderer::set_cache_limits),
+/// 0 for libass's defaults.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct CacheLimits {
+    /// Number of glyphs.
+    pub glyph_max: i32,
+    /// Size of the bitmap cache in megabytes.
+    pub bitmap_max_size: i32,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        RendererConfig {
+            frame_size: (0, 0),
+            storage_size: (0, 0),
+            fonts: None,
+            margins: Margins::default(),
+            use_margins: false,
+            pixel_aspect_ratio: 0.0,
+            font_scale: 1.0,
+            hinting: Hinting::None,
+            line_spacing: 0.0,
+            line_position: 0.0,
+            cache_limits: CacheLimits::default(),
+            shaper: ShapingLevel::Complex,
+        }
+    }
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        FontSettings {
+            default_font: None,
+            default_family: None,
+            provider: DefaultFontProvider::Autodetect,
+            fontconfig_config: None,
+            update_fontconfig_cache: true,
+        }
+    }
+}
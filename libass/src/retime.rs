@@ -0,0 +1,176 @@
+use crate::tags::{EventText, Item, KaraokeKind, Override, Part, Tag};
+use crate::timebase::Timebase;
+use crate::track::{Event, Track};
+
+impl<'library> Track<'library> {
+    /// Moves all events by `offset` milliseconds.
+    pub fn shift(&mut self, offset: i64) {
+        self.shift_if(offset, |_| true);
+    }
+
+    /// Moves the events for which `select` returns `true` by `offset`
+    /// milliseconds.
+    pub fn shift_if(&mut self, offset: i64, mut select: impl FnMut(&Event<'_>) -> bool) {
+        let selected: Vec<i32> = self.events().filter(|e| select(e)).map(|e| e.id).collect();
+        for id in selected {
+            if let Some(mut event) = self.event_mut(id) {
+                let start = event.start();
+                event.set_start(start + offset);
+            }
+        }
+    }
+
+    /// Multiplies all times by `factor`, e.g. by `24000.0 / 1001.0 / 25.0`
+    /// for subtitles timed for 23.976 fps video that is played at 25 fps.
+    pub fn rescale(&mut self, factor: f64) {
+        self.retime(|t| (t as f64 * factor).round() as i64);
+    }
+
+    /// Maps times through the line connecting `(old, new)` anchor points,
+    /// continuing the first and last segments beyond the outermost anchors.
+    /// A single anchor shifts everything.
+    pub fn retime_piecewise(&mut self, anchors: &[(i64, i64)]) {
+        let mut anchors = anchors.to_vec();
+        anchors.sort_unstable();
+        anchors.dedup_by_key(|a| a.0);
+        self.retime(|t| piecewise(&anchors, t));
+    }
+
+    /// Moves all events by `frames` frames of `timebase`.
+    pub fn shift_frames(&mut self, frames: i64, timebase: &Timebase) {
+        self.retime(|t| timebase.position_time(timebase.frame_position(t) + frames as f64));
+    }
+
+    /// Keeps events on the same frames when the video they were timed
+    /// against is replaced by one with the same frames at other times, e.g.
+    /// from 24000/1001 to 25 fps or from a constant rate to a timecode file.
+    pub fn convert_timebase(&mut self, from: &Timebase, to: &Timebase) {
+        self.retime(|t| to.position_time(from.frame_position(t)));
+    }
+
+    /// Maps all times through `map`, which should be increasing, adjusting
+    /// the times of `\move`, `\fad`, `\fade`, `\t` and karaoke tags so that
+    /// animations stay in sync.
+    pub fn retime(&mut self, map: impl Fn(i64) -> i64) {
+        for id in 0..self.event_count() as i32 {
+            let mut event = match self.event_mut(id) {
+                Some(event) => event,
+                None => continue,
+            };
+            let start = event.start();
+            let end = start + event.duration();
+            let new_start = map(start);
+            let new_end = map(end).max(new_start);
+            event.set_start(new_start);
+            event.set_duration(new_end - new_start);
+
+            let text = event.text().into_owned();
+            let mut parsed = EventText::parse(&text);
+            let times = Times {
+                start,
+                end,
+                new_start,
+                new_end,
+                map: &map,
+            };
+            retime_text(&mut parsed, &times);
+            let retimed = parsed.to_string();
+            if retimed != text {
+                event.set_text(&retimed);
+            }
+        }
+    }
+}
+
+fn piecewise(anchors: &[(i64, i64)], t: i64) -> i64 {
+    match anchors {
+        [] => t,
+        [(old, new)] => t + new - old,
+        _ => {
+            let i = anchors
+                .partition_point(|a| a.0 <= t)
+                .clamp(1, anchors.len() - 1);
+            let (x0, y0) = anchors[i - 1];
+            let (x1, y1) = anchors[i];
+            y0 + ((t - x0) as f64 * (y1 - y0) as f64 / (x1 - x0) as f64).round() as i64
+        }
+    }
+}
+
+struct Times<'a, F> {
+    start: i64,
+    end: i64,
+    new_start: i64,
+    new_end: i64,
+    map: &'a F,
+}
+
+impl<'a, F: Fn(i64) -> i64> Times<'a, F> {
+    // a time relative to the event start
+    fn relative(&self, t: i64) -> i64 {
+        (self.map)(self.start + t) - self.new_start
+    }
+}
+
+fn retime_text<F: Fn(i64) -> i64>(text: &mut EventText, times: &Times<'_, F>) {
+    // karaoke syllables start where the previous one ended
+    let mut karaoke = 0;
+    for part in &mut text.parts {
+        let items = match part {
+            Part::Block(items) => items,
+            Part::Text(_) => continue,
+        };
+        for item in items {
+            if let Item::Tag(Override { tag, .. }) = item {
+                retime_tag(tag, times, &mut karaoke);
+            }
+        }
+    }
+}
+
+fn retime_tag<F: Fn(i64) -> i64>(tag: &mut Tag, times: &Times<'_, F>, karaoke: &mut i64) {
+    match tag {
+        Tag::Move {
+            times: Some((t1, t2)),
+            ..
+        } => {
+            *t1 = times.relative(*t1);
+            *t2 = times.relative(*t2);
+        }
+        Tag::Fad { fade_in, fade_out } => {
+            *fade_in = times.relative(*fade_in);
+            *fade_out = times.new_end - (times.map)(times.end - *fade_out);
+        }
+        Tag::Fade { times: t, .. } => {
+            for t in t.iter_mut() {
+                *t = times.relative(*t);
+            }
+        }
+        Tag::Transform {
+            times: Some((t1, t2)),
+            ..
+        } => {
+            *t1 = times.relative(*t1);
+            *t2 = times.relative(*t2);
+        }
+        Tag::Karaoke {
+            kind: KaraokeKind::Time,
+            duration,
+        } => {
+            *karaoke = (*duration * 10.0).round() as i64;
+            *duration = centiseconds(times.relative(*karaoke));
+        }
+        // VSFilter ignores fractions of centiseconds, so both ends of the
+        // syllable are rounded, which keeps the error from adding up
+        Tag::Karaoke { duration, .. } => {
+            let from = times.relative(*karaoke);
+            *karaoke += (*duration * 10.0).round() as i64;
+            *duration = centiseconds(times.relative(*karaoke)) - centiseconds(from);
+        }
+        _ => {}
+    }
+}
+
+fn centiseconds(ms: i64) -> f64 {
+    (ms as f64 / 10.0).round()
+}
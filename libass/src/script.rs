@@ -0,0 +1,1487 @@
+//! A pure-Rust parser and writer for ASS and SSA scripts.
+//!
+//! Unlike a [`Track`], a [`Script`] keeps everything libass throws away:
+//! comments, `Comment:` events, unknown sections and the order of lines, so
+//! that parsing and writing a script gives back an equivalent file.
+//!
+//! The file is equivalent rather than identical, since the writer
+//! normalizes what it understood:
+//!
+//! - `Key:value` becomes `Key: value` and format lines are joined with `, `.
+//! - Style and event fields are trimmed, except the event text.
+//! - Numbers are rewritten, e.g. `100.00` as `100`, colors as `&HAABBGGRR`
+//!   in ASS and decimal in SSA, and times as `H:MM:SS.CC`.
+//! - Sections are separated by exactly one blank line, lines end with `\n`
+//!   and a byte order mark is dropped.
+//! - Blank lines in `[Fonts]` and `[Graphics]` are dropped.
+//!
+//! Parsing the written file again gives the same [`Script`].
+
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::fmt::{self, Display};
+use std::io;
+use std::os::raw::c_char;
+
+use libass_sys as ffi;
+
+use crate::library::Library;
+use crate::track::{replace_str, Track};
+use crate::Result;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Script {
+    /// Lines before the first section header.
+    pub preamble: Vec<String>,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Section {
+    ScriptInfo(ScriptInfo),
+    Styles(Styles),
+    Events(Events),
+    Fonts(Attachments),
+    Graphics(Attachments),
+    Unknown(Unknown),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptInfo {
+    pub header: String,
+    pub lines: Vec<InfoLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InfoLine {
+    Property {
+        key: String,
+        value: String,
+    },
+    /// A line starting with `;`, without the `;`.
+    Comment(String),
+    Raw(String),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Version {
+    /// SSA, with `[V4 Styles]`.
+    V4,
+    /// ASS, with `[V4+ Styles]`.
+    V4Plus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Styles {
+    pub header: String,
+    pub version: Version,
+    pub lines: Vec<StyleLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StyleLine {
+    Format(Vec<String>),
+    Style(Style),
+    Comment(String),
+    Raw(String),
+}
+
+/// A style as written in a script: colors are `&HAABBGGRR`, scales are
+/// percentages and the alignment is numpad-style for ASS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub name: String,
+    pub font_name: String,
+    pub font_size: f64,
+    pub primary_colour: Color,
+    pub secondary_colour: Color,
+    /// `TertiaryColour` in SSA.
+    pub outline_colour: Color,
+    pub back_colour: Color,
+    /// `-1` for bold, `0` for regular, or a font weight.
+    pub bold: i32,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike_out: bool,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub spacing: f64,
+    pub angle: f64,
+    pub border_style: i32,
+    pub outline: f64,
+    pub shadow: f64,
+    pub alignment: i32,
+    pub margin_l: i32,
+    pub margin_r: i32,
+    pub margin_v: i32,
+    /// SSA only.
+    pub alpha_level: i32,
+    pub encoding: i32,
+    pub blur: f64,
+    pub justify: i32,
+    /// Fields named in the format line that aren't known here.
+    pub extra: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Events {
+    pub header: String,
+    pub lines: Vec<EventLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventLine {
+    Format(Vec<String>),
+    Event(Event),
+    Comment(String),
+    /// Lines like `Picture:` or `Command:` that libass ignores.
+    Raw(String),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EventKind {
+    Dialogue,
+    /// A `Comment:` line, which is never displayed.
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// `Marked` in SSA.
+    pub layer: i32,
+    /// Start time in milliseconds.
+    pub start: i64,
+    /// End time in milliseconds.
+    pub end: i64,
+    pub style: String,
+    pub name: String,
+    pub margin_l: i32,
+    pub margin_r: i32,
+    pub margin_v: i32,
+    pub effect: String,
+    pub text: String,
+    /// Fields named in the format line that aren't known here.
+    pub extra: Vec<(String, String)>,
+}
+
+/// A `[Fonts]` or `[Graphics]` section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachments {
+    pub header: String,
+    pub files: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    /// The encoded data, one entry per line.
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unknown {
+    pub header: String,
+    pub lines: Vec<String>,
+}
+
+/// A color with ASS semantics: an alpha of 0 is opaque.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+const V4_PLUS_STYLE_FORMAT: &[&str] = &[
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "OutlineColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "Underline",
+    "StrikeOut",
+    "ScaleX",
+    "ScaleY",
+    "Spacing",
+    "Angle",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginV",
+    "Encoding",
+];
+
+const V4_STYLE_FORMAT: &[&str] = &[
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "TertiaryColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginV",
+    "AlphaLevel",
+    "Encoding",
+];
+
+const V4_PLUS_EVENT_FORMAT: &[&str] = &[
+    "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+const V4_EVENT_FORMAT: &[&str] = &[
+    "Marked", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+impl Script {
+    /// Creates an ASS script with empty `[Script Info]`, `[V4+ Styles]` and
+    /// `[Events]` sections.
+    pub fn new() -> Self {
+        let mut info = ScriptInfo::new();
+        info.set("ScriptType", "v4.00+");
+        Script {
+            preamble: Vec::new(),
+            sections: vec![
+                Section::ScriptInfo(info),
+                Section::Styles(Styles::new(Version::V4Plus)),
+                Section::Events(Events::new(Version::V4Plus)),
+            ],
+        }
+    }
+
+    /// Parses a script. Lines that can't be understood are kept as they are.
+    pub fn parse(text: &str) -> Self {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut script = Script::default();
+        let mut version = Version::V4Plus;
+
+        for line in text.lines() {
+            if let Some(header) = section_header(line) {
+                if let Some(section) = script.sections.last_mut() {
+                    section.trim_blank_lines();
+                }
+                let section = Section::new(header);
+                if let Section::Styles(styles) = &section {
+                    version = styles.version;
+                }
+                script.sections.push(section);
+                continue;
+            }
+
+            match script.sections.last_mut() {
+                Some(section) => section.push_line(line, version),
+                None => script.preamble.push(line.to_string()),
+            }
+        }
+
+        if let Some(section) = script.sections.last_mut() {
+            section.trim_blank_lines();
+        }
+        script
+    }
+
+    /// Whether this is an SSA script rather than an ASS one.
+    pub fn is_ssa(&self) -> bool {
+        if let Some(styles) = self.styles() {
+            return styles.version == Version::V4;
+        }
+        self.script_info()
+            .and_then(|info| info.get("ScriptType"))
+            .is_some_and(|t| t.trim().eq_ignore_ascii_case("v4.00"))
+    }
+
+    pub fn script_info(&self) -> Option<&ScriptInfo> {
+        self.sections.iter().find_map(|s| match s {
+            Section::ScriptInfo(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    pub fn script_info_mut(&mut self) -> Option<&mut ScriptInfo> {
+        self.sections.iter_mut().find_map(|s| match s {
+            Section::ScriptInfo(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    pub fn styles(&self) -> Option<&Styles> {
+        self.sections.iter().find_map(|s| match s {
+            Section::Styles(styles) => Some(styles),
+            _ => None,
+        })
+    }
+
+    pub fn styles_mut(&mut self) -> Option<&mut Styles> {
+        self.sections.iter_mut().find_map(|s| match s {
+            Section::Styles(styles) => Some(styles),
+            _ => None,
+        })
+    }
+
+    pub fn events(&self) -> Option<&Events> {
+        self.sections.iter().find_map(|s| match s {
+            Section::Events(events) => Some(events),
+            _ => None,
+        })
+    }
+
+    pub fn events_mut(&mut self) -> Option<&mut Events> {
+        self.sections.iter_mut().find_map(|s| match s {
+            Section::Events(events) => Some(events),
+            _ => None,
+        })
+    }
+
+    pub fn fonts(&self) -> Option<&Attachments> {
+        self.sections.iter().find_map(|s| match s {
+            Section::Fonts(fonts) => Some(fonts),
+            _ => None,
+        })
+    }
+
+    pub fn fonts_mut(&mut self) -> Option<&mut Attachments> {
+        self.sections.iter_mut().find_map(|s| match s {
+            Section::Fonts(fonts) => Some(fonts),
+            _ => None,
+        })
+    }
+
+    pub fn graphics(&self) -> Option<&Attachments> {
+        self.sections.iter().find_map(|s| match s {
+            Section::Graphics(graphics) => Some(graphics),
+            _ => None,
+        })
+    }
+
+    /// Embeds a font in the `[Fonts]` section, adding the section before
+    /// `[Events]` if there is none.
+    pub fn embed_font(&mut self, name: &str, data: &[u8]) {
+        if self.fonts().is_none() {
+            let index = self
+                .sections
+                .iter()
+                .position(|s| matches!(s, Section::Events(_)))
+                .unwrap_or(self.sections.len());
+            self.sections
+                .insert(index, Section::Fonts(Attachments::new_fonts()));
+        }
+        let fonts = self.fonts_mut().unwrap();
+        fonts.files.push(Attachment::encode(name, data));
+    }
+
+    /// Adds the fonts embedded in the script to `library`.
+    pub fn add_fonts(&self, library: &mut Library) {
+        for font in self.fonts().iter().flat_map(|fonts| &fonts.files) {
+            library.add_font(&font.name, &font.decode());
+        }
+    }
+
+    /// Writes the script as text, normalized as described in the
+    /// [module documentation](self).
+    pub fn write(&self, out: &mut impl io::Write) -> io::Result<()> {
+        write!(out, "{}", self)
+    }
+
+    /// Loads the script into a new track, as `Library::new_track_from_memory`
+    /// would, without going through text.
+    pub fn to_track<'a>(&self, library: &'a Library) -> Result<Track<'a>> {
+        let mut track = library.new_track()?;
+        let ssa = self.is_ssa();
+
+        let raw = track.as_ass_track_mut();
+        raw.track_type = if ssa {
+            ffi::ass_track__bindgen_ty_1::TRACK_TYPE_SSA
+        } else {
+            ffi::ass_track__bindgen_ty_1::TRACK_TYPE_ASS
+        };
+        if let Some(info) = self.script_info() {
+            for (key, value) in info.properties() {
+                apply_info(raw, key, value);
+            }
+        }
+
+        // (name, id) of every style, to resolve event style names like libass
+        let mut names: Vec<(String, i32)> = (0..track.style_count() as i32)
+            .filter_map(|id| Some((track.style(id)?.name.into_string().ok()?, id)))
+            .collect();
+
+        if let Some(styles) = self.styles() {
+            let raw = track.as_ass_track_mut();
+            unsafe { replace_str(&mut raw.style_format, &styles.format().join(", ")) };
+            for style in styles.styles() {
+                let id = unsafe { track.add_ass_style(style.to_ass_style(ssa)) };
+                let name = style.name.strip_prefix('*').unwrap_or(&style.name);
+                if name.eq_ignore_ascii_case("Default") {
+                    track.as_ass_track_mut().default_style = id;
+                }
+                names.push((name.to_string(), id));
+            }
+        }
+
+        if let Some(events) = self.events() {
+            let raw = track.as_ass_track_mut();
+            unsafe { replace_str(&mut raw.event_format, &events.format(ssa).join(", ")) };
+            let default_style = raw.default_style;
+            for event in events.events() {
+                if event.kind != EventKind::Dialogue {
+                    continue;
+                }
+                let mut name = event.style.trim_start_matches('*');
+                if name.eq_ignore_ascii_case("Default") {
+                    name = "Default";
+                }
+                let style = names
+                    .iter()
+                    .rev()
+                    .find(|(n, _)| n == name)
+                    .map_or(default_style, |&(_, id)| id);
+
+                let mut e = track.add_event();
+                e.set_start(event.start);
+                e.set_duration(event.end - event.start);
+                e.set_layer(event.layer);
+                e.set_style(style);
+                e.set_name(&event.name);
+                e.set_margin_l(event.margin_l);
+                e.set_margin_r(event.margin_r);
+                e.set_margin_v(event.margin_v);
+                e.set_effect(&event.effect);
+                e.set_text(&event.text);
+            }
+        }
+
+        Ok(track)
+    }
+
+    /// Builds an ASS script from the current contents of a track.
+    ///
+    /// The `Default` style libass adds to every track isn't included, since
+    /// loading the script again adds it back.
+    pub fn from_track(track: &Track) -> Self {
+        let raw = track.as_ass_track();
+
+        let mut info = ScriptInfo::new();
+        info.set("ScriptType", "v4.00+");
+        info_from_track(&mut info, raw);
+
+        let mut styles = Styles::new(Version::V4Plus);
+        let mut names = Vec::with_capacity(track.style_count());
+        for id in 0..track.style_count() {
+            let style = Style::from_ass_style(unsafe { &*raw.styles.add(id) });
+            names.push(style.name.clone());
+            if id > 0 {
+                styles.push(style);
+            }
+        }
+
+        let mut events = Events::new(Version::V4Plus);
+        for event in track.events() {
+            let style = usize::try_from(event.style())
+                .ok()
+                .and_then(|id| names.get(id))
+                .map_or("Default", |name| name.as_str());
+            events.push(Event {
+                kind: EventKind::Dialogue,
+                layer: event.layer(),
+                start: event.start(),
+                end: event.end(),
+                style: style.to_string(),
+                name: event.name().into_owned(),
+                margin_l: event.margin_l(),
+                margin_r: event.margin_r(),
+                margin_v: event.margin_v(),
+                effect: event.effect().into_owned(),
+                // a raw line break would end the line early
+                text: event.text().replace("\r\n", "\\N").replace('\n', "\\N"),
+                extra: Vec::new(),
+            });
+        }
+
+        Script {
+            preamble: Vec::new(),
+            sections: vec![
+                Section::ScriptInfo(info),
+                Section::Styles(styles),
+                Section::Events(events),
+            ],
+        }
+    }
+}
+
+impl<'library> Track<'library> {
+    /// Writes the track as an ASS script, see [`Script::from_track`].
+    pub fn write_ass(&self, out: &mut impl io::Write) -> io::Result<()> {
+        Script::from_track(self).write(out)
+    }
+}
+
+fn info_from_track(info: &mut ScriptInfo, track: &ffi::ass_track) {
+    use ffi::ASS_YCbCrMatrix::*;
+
+    let boolean = |b: i32| if b != 0 { "yes" } else { "no" };
+    if track.PlayResX > 0 {
+        info.set("PlayResX", &track.PlayResX.to_string());
+    }
+    if track.PlayResY > 0 {
+        info.set("PlayResY", &track.PlayResY.to_string());
+    }
+    if track.Timer > 0.0 {
+        info.set("Timer", &format!("{:.4}", track.Timer));
+    }
+    info.set("WrapStyle", &track.WrapStyle.to_string());
+    info.set(
+        "ScaledBorderAndShadow",
+        boolean(track.ScaledBorderAndShadow),
+    );
+    info.set("Kerning", boolean(track.Kerning));
+    let matrix = match track.YCbCrMatrix {
+        YCBCR_NONE => Some("None"),
+        YCBCR_BT601_TV => Some("TV.601"),
+        YCBCR_BT601_PC => Some("PC.601"),
+        YCBCR_BT709_TV => Some("TV.709"),
+        YCBCR_BT709_PC => Some("PC.709"),
+        YCBCR_SMPTE240M_TV => Some("TV.240M"),
+        YCBCR_SMPTE240M_PC => Some("PC.240M"),
+        YCBCR_FCC_TV => Some("TV.FCC"),
+        YCBCR_FCC_PC => Some("PC.FCC"),
+        YCBCR_DEFAULT | YCBCR_UNKNOWN => None,
+    };
+    if let Some(matrix) = matrix {
+        info.set("YCbCr Matrix", matrix);
+    }
+    if !track.Language.is_null() {
+        let language = unsafe { CStr::from_ptr(track.Language) };
+        info.set("Language", &language.to_string_lossy());
+    }
+}
+
+fn apply_info(track: &mut ffi::ass_track, key: &str, value: &str) {
+    use ffi::ASS_YCbCrMatrix::*;
+
+    let int = || parse_int(value).unwrap_or(0);
+    let boolean = || {
+        value
+            .trim_start()
+            .get(..3)
+            .is_some_and(|v| v.eq_ignore_ascii_case("yes"))
+            || int() > 0
+    };
+    match key.to_ascii_lowercase().as_str() {
+        "playresx" => track.PlayResX = int(),
+        "playresy" => track.PlayResY = int(),
+        "timer" => track.Timer = parse_float(value).unwrap_or(0.0),
+        "wrapstyle" => track.WrapStyle = int(),
+        "scaledborderandshadow" => track.ScaledBorderAndShadow = boolean() as i32,
+        "kerning" => track.Kerning = boolean() as i32,
+        "language" => {
+            let language: String = value.trim().chars().take(2).collect();
+            unsafe { replace_str(&mut track.Language, &language) }
+        }
+        "ycbcr matrix" => {
+            track.YCbCrMatrix = match value.trim().to_ascii_lowercase().as_str() {
+                "none" => YCBCR_NONE,
+                "tv.601" => YCBCR_BT601_TV,
+                "pc.601" => YCBCR_BT601_PC,
+                "tv.709" => YCBCR_BT709_TV,
+                "pc.709" => YCBCR_BT709_PC,
+                "tv.240m" => YCBCR_SMPTE240M_TV,
+                "pc.240m" => YCBCR_SMPTE240M_PC,
+                "tv.fcc" => YCBCR_FCC_TV,
+                "pc.fcc" => YCBCR_FCC_PC,
+                _ => YCBCR_UNKNOWN,
+            }
+        }
+        _ => {}
+    }
+}
+
+fn section_header(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.len() >= 2 && line.starts_with('[') && line.ends_with(']') {
+        Some(&line[1..line.len() - 1])
+    } else {
+        None
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+impl Section {
+    fn new(header: &str) -> Self {
+        let header_owned = header.to_string();
+        match header.trim().to_ascii_lowercase().as_str() {
+            "script info" => Section::ScriptInfo(ScriptInfo {
+                header: header_owned,
+                lines: Vec::new(),
+            }),
+            "v4+ styles" => Section::Styles(Styles {
+                header: header_owned,
+                version: Version::V4Plus,
+                lines: Vec::new(),
+            }),
+            "v4 styles" => Section::Styles(Styles {
+                header: header_owned,
+                version: Version::V4,
+                lines: Vec::new(),
+            }),
+            "events" => Section::Events(Events {
+                header: header_owned,
+                lines: Vec::new(),
+            }),
+            "fonts" => Section::Fonts(Attachments {
+                header: header_owned,
+                files: Vec::new(),
+            }),
+            "graphics" => Section::Graphics(Attachments {
+                header: header_owned,
+                files: Vec::new(),
+            }),
+            _ => Section::Unknown(Unknown {
+                header: header_owned,
+                lines: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn header(&self) -> &str {
+        match self {
+            Section::ScriptInfo(s) => &s.header,
+            Section::Styles(s) => &s.header,
+            Section::Events(s) => &s.header,
+            Section::Fonts(s) | Section::Graphics(s) => &s.header,
+            Section::Unknown(s) => &s.header,
+        }
+    }
+
+    fn push_line(&mut self, line: &str, version: Version) {
+        match self {
+            Section::ScriptInfo(info) => info.lines.push(InfoLine::parse(line)),
+            Section::Styles(styles) => {
+                let line = StyleLine::parse(line, styles.format());
+                styles.lines.push(line);
+            }
+            Section::Events(events) => {
+                let ssa = version == Version::V4;
+                let line = EventLine::parse(line, events.format(ssa));
+                events.lines.push(line);
+            }
+            Section::Fonts(attachments) => attachments.push_line(line, "fontname:"),
+            Section::Graphics(attachments) => attachments.push_line(line, "filename:"),
+            Section::Unknown(unknown) => unknown.lines.push(line.to_string()),
+        }
+    }
+
+    // the blank lines between sections are added back when writing
+    fn trim_blank_lines(&mut self) {
+        fn trim<T>(lines: &mut Vec<T>, blank: impl Fn(&T) -> bool) {
+            while lines.last().is_some_and(&blank) {
+                lines.pop();
+            }
+        }
+        match self {
+            Section::ScriptInfo(s) => trim(
+                &mut s.lines,
+                |l| matches!(l, InfoLine::Raw(r) if is_blank(r)),
+            ),
+            Section::Styles(s) => trim(
+                &mut s.lines,
+                |l| matches!(l, StyleLine::Raw(r) if is_blank(r)),
+            ),
+            Section::Events(s) => trim(
+                &mut s.lines,
+                |l| matches!(l, EventLine::Raw(r) if is_blank(r)),
+            ),
+            Section::Unknown(s) => trim(&mut s.lines, |l| is_blank(l)),
+            Section::Fonts(_) | Section::Graphics(_) => {}
+        }
+    }
+}
+
+impl ScriptInfo {
+    pub fn new() -> Self {
+        ScriptInfo {
+            header: "Script Info".to_string(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Iterates over the `Key: value` lines.
+    pub fn properties(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            InfoLine::Property { key, value } => Some((key.as_str(), value.as_str())),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of the first property named `key`, ignoring case.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    /// Sets the first property named `key`, or adds one.
+    pub fn set(&mut self, key: &str, value: &str) {
+        for line in &mut self.lines {
+            if let InfoLine::Property { key: k, value: v } = line {
+                if k.eq_ignore_ascii_case(key) {
+                    *v = value.to_string();
+                    return;
+                }
+            }
+        }
+        self.lines.push(InfoLine::Property {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+}
+
+impl Default for ScriptInfo {
+    fn default() -> Self {
+        ScriptInfo::new()
+    }
+}
+
+impl InfoLine {
+    fn parse(line: &str) -> Self {
+        if let Some(comment) = line.strip_prefix(';') {
+            return InfoLine::Comment(comment.to_string());
+        }
+        match line.split_once(':') {
+            Some((key, value)) if !is_blank(key) => InfoLine::Property {
+                key: key.trim().to_string(),
+                value: value.trim_start().to_string(),
+            },
+            _ => InfoLine::Raw(line.to_string()),
+        }
+    }
+}
+
+impl Styles {
+    /// Creates a section with only a default format line.
+    pub fn new(version: Version) -> Self {
+        let header = match version {
+            Version::V4 => "V4 Styles",
+            Version::V4Plus => "V4+ Styles",
+        };
+        Styles {
+            header: header.to_string(),
+            version,
+            lines: vec![StyleLine::Format(default_style_format(version))],
+        }
+    }
+
+    /// The last format line, or the default one for the version.
+    pub fn format(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                StyleLine::Format(format) => Some(format.clone()),
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or_else(|| default_style_format(self.version))
+    }
+
+    pub fn styles(&self) -> impl Iterator<Item = &Style> {
+        self.lines.iter().filter_map(|line| match line {
+            StyleLine::Style(style) => Some(style),
+            _ => None,
+        })
+    }
+
+    pub fn styles_mut(&mut self) -> impl Iterator<Item = &mut Style> {
+        self.lines.iter_mut().filter_map(|line| match line {
+            StyleLine::Style(style) => Some(style),
+            _ => None,
+        })
+    }
+
+    pub fn push(&mut self, style: Style) {
+        self.lines.push(StyleLine::Style(style));
+    }
+}
+
+fn default_style_format(version: Version) -> Vec<String> {
+    let format = match version {
+        Version::V4 => V4_STYLE_FORMAT,
+        Version::V4Plus => V4_PLUS_STYLE_FORMAT,
+    };
+    format.iter().map(|f| f.to_string()).collect()
+}
+
+fn default_event_format(ssa: bool) -> Vec<String> {
+    let format = if ssa {
+        V4_EVENT_FORMAT
+    } else {
+        V4_PLUS_EVENT_FORMAT
+    };
+    format.iter().map(|f| f.to_string()).collect()
+}
+
+fn parse_format(value: &str) -> Vec<String> {
+    value.split(',').map(|f| f.trim().to_string()).collect()
+}
+
+impl StyleLine {
+    fn parse(line: &str, format: Vec<String>) -> Self {
+        if let Some(comment) = line.strip_prefix(';') {
+            return StyleLine::Comment(comment.to_string());
+        }
+        let (kind, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => return StyleLine::Raw(line.to_string()),
+        };
+        match kind.trim() {
+            "Format" => StyleLine::Format(parse_format(value)),
+            "Style" => match Style::parse(value.trim_start(), &format) {
+                Some(style) => StyleLine::Style(style),
+                None => StyleLine::Raw(line.to_string()),
+            },
+            _ => StyleLine::Raw(line.to_string()),
+        }
+    }
+}
+
+impl Default for Style {
+    /// Fields missing from a style line keep these values.
+    fn default() -> Self {
+        Style {
+            name: "Default".to_string(),
+            font_name: "Arial".to_string(),
+            font_size: 18.0,
+            primary_colour: Color::from_abgr(0x00ffffff),
+            secondary_colour: Color::from_abgr(0x00ffff00),
+            outline_colour: Color::from_abgr(0x00000000),
+            back_colour: Color::from_abgr(0x80000000),
+            bold: 0,
+            italic: false,
+            underline: false,
+            strike_out: false,
+            scale_x: 100.0,
+            scale_y: 100.0,
+            spacing: 0.0,
+            angle: 0.0,
+            border_style: 1,
+            outline: 2.0,
+            shadow: 3.0,
+            alignment: 2,
+            margin_l: 20,
+            margin_r: 20,
+            margin_v: 20,
+            alpha_level: 0,
+            encoding: 1,
+            blur: 0.0,
+            justify: 0,
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl Style {
+    fn parse(value: &str, format: &[String]) -> Option<Self> {
+        let fields = split_fields(value, format.len());
+        let mut style = Style::default();
+        for (name, field) in format.iter().zip(fields) {
+            let field = field.trim();
+            match name.to_ascii_lowercase().as_str() {
+                "name" => style.name = field.to_string(),
+                "fontname" => style.font_name = field.to_string(),
+                "fontsize" => style.font_size = parse_float(field)?,
+                "primarycolour" => style.primary_colour = Color::parse(field)?,
+                "secondarycolour" => style.secondary_colour = Color::parse(field)?,
+                "outlinecolour" | "tertiarycolour" => style.outline_colour = Color::parse(field)?,
+                "backcolour" => style.back_colour = Color::parse(field)?,
+                "bold" => style.bold = parse_int(field)?,
+                "italic" => style.italic = parse_int(field)? != 0,
+                "underline" => style.underline = parse_int(field)? != 0,
+                "strikeout" => style.strike_out = parse_int(field)? != 0,
+                "scalex" => style.scale_x = parse_float(field)?,
+                "scaley" => style.scale_y = parse_float(field)?,
+                "spacing" => style.spacing = parse_float(field)?,
+                "angle" => style.angle = parse_float(field)?,
+                "borderstyle" => style.border_style = parse_int(field)?,
+                "outline" => style.outline = parse_float(field)?,
+                "shadow" => style.shadow = parse_float(field)?,
+                "alignment" => style.alignment = parse_int(field)?,
+                "marginl" => style.margin_l = parse_int(field)?,
+                "marginr" => style.margin_r = parse_int(field)?,
+                "marginv" => style.margin_v = parse_int(field)?,
+                "alphalevel" => style.alpha_level = parse_int(field)?,
+                "encoding" => style.encoding = parse_int(field)?,
+                "blur" => style.blur = parse_float(field)?,
+                "justify" => style.justify = parse_int(field)?,
+                _ => style.extra.push((name.clone(), field.to_string())),
+            }
+        }
+        Some(style)
+    }
+
+    fn write(&self, out: &mut impl fmt::Write, format: &[String], version: Version) -> fmt::Result {
+        let color = |c: Color| match version {
+            Version::V4 => (c.to_abgr() as i32).to_string(),
+            Version::V4Plus => c.to_string(),
+        };
+        let boolean = |b: bool| if b { "-1" } else { "0" };
+
+        out.write_str("Style: ")?;
+        for (i, name) in format.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            match name.to_ascii_lowercase().as_str() {
+                "name" => out.write_str(&self.name)?,
+                "fontname" => out.write_str(&self.font_name)?,
+                "fontsize" => write!(out, "{}", self.font_size)?,
+                "primarycolour" => out.write_str(&color(self.primary_colour))?,
+                "secondarycolour" => out.write_str(&color(self.secondary_colour))?,
+                "outlinecolour" | "tertiarycolour" => out.write_str(&color(self.outline_colour))?,
+                "backcolour" => out.write_str(&color(self.back_colour))?,
+                "bold" => write!(out, "{}", self.bold)?,
+                "italic" => out.write_str(boolean(self.italic))?,
+                "underline" => out.write_str(boolean(self.underline))?,
+                "strikeout" => out.write_str(boolean(self.strike_out))?,
+                "scalex" => write!(out, "{}", self.scale_x)?,
+                "scaley" => write!(out, "{}", self.scale_y)?,
+                "spacing" => write!(out, "{}", self.spacing)?,
+                "angle" => write!(out, "{}", self.angle)?,
+                "borderstyle" => write!(out, "{}", self.border_style)?,
+                "outline" => write!(out, "{}", self.outline)?,
+                "shadow" => write!(out, "{}", self.shadow)?,
+                "alignment" => write!(out, "{}", self.alignment)?,
+                "marginl" => write!(out, "{}", self.margin_l)?,
+                "marginr" => write!(out, "{}", self.margin_r)?,
+                "marginv" => write!(out, "{}", self.margin_v)?,
+                "alphalevel" => write!(out, "{}", self.alpha_level)?,
+                "encoding" => write!(out, "{}", self.encoding)?,
+                "blur" => write!(out, "{}", self.blur)?,
+                "justify" => write!(out, "{}", self.justify)?,
+                _ => out.write_str(extra_field(&self.extra, name))?,
+            }
+        }
+        Ok(())
+    }
+
+    // the inverse of to_ass_style, always giving an ASS style
+    fn from_ass_style(style: &ffi::ass_style) -> Self {
+        let string = |ptr: *const c_char| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(ptr) }
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+        Style {
+            name: string(style.Name),
+            font_name: string(style.FontName),
+            font_size: style.FontSize,
+            primary_colour: Color::from_rgba(style.PrimaryColour),
+            secondary_colour: Color::from_rgba(style.SecondaryColour),
+            outline_colour: Color::from_rgba(style.OutlineColour),
+            back_colour: Color::from_rgba(style.BackColour),
+            bold: style.Bold,
+            italic: style.Italic != 0,
+            underline: style.Underline != 0,
+            strike_out: style.StrikeOut != 0,
+            scale_x: style.ScaleX * 100.0,
+            scale_y: style.ScaleY * 100.0,
+            spacing: style.Spacing,
+            angle: style.Angle,
+            border_style: style.BorderStyle,
+            outline: style.Outline,
+            shadow: style.Shadow,
+            alignment: legacy_to_numpad_alignment(style.Alignment),
+            margin_l: style.MarginL,
+            margin_r: style.MarginR,
+            margin_v: style.MarginV,
+            alpha_level: 0,
+            encoding: style.Encoding,
+            blur: style.Blur,
+            justify: style.Justify,
+            extra: Vec::new(),
+        }
+    }
+
+    // the values libass stores for this style, see process_style in ass.c
+    fn to_ass_style(&self, ssa: bool) -> ffi::ass_style {
+        let name = self.name.strip_prefix('*').unwrap_or(&self.name);
+        let name = CString::new(name).unwrap_or_default();
+        let font_name = CString::new(self.font_name.as_str()).unwrap_or_default();
+        ffi::ass_style {
+            Name: unsafe { libc::strdup(name.as_ptr()) },
+            FontName: unsafe { libc::strdup(font_name.as_ptr()) },
+            FontSize: self.font_size,
+            PrimaryColour: self.primary_colour.to_rgba(),
+            SecondaryColour: self.secondary_colour.to_rgba(),
+            OutlineColour: self.outline_colour.to_rgba(),
+            BackColour: self.back_colour.to_rgba(),
+            Bold: self.bold,
+            Italic: -(self.italic as i32),
+            Underline: -(self.underline as i32),
+            StrikeOut: -(self.strike_out as i32),
+            ScaleX: self.scale_x.max(0.0) / 100.0,
+            ScaleY: self.scale_y.max(0.0) / 100.0,
+            Spacing: self.spacing,
+            Angle: self.angle,
+            BorderStyle: self.border_style,
+            Outline: self.outline.max(0.0),
+            Shadow: self.shadow.max(0.0),
+            Alignment: if ssa {
+                self.alignment
+            } else {
+                numpad_to_legacy_alignment(self.alignment)
+            },
+            MarginL: self.margin_l,
+            MarginR: self.margin_r,
+            MarginV: self.margin_v,
+            Encoding: self.encoding,
+            treat_fontname_as_pattern: 0,
+            Blur: self.blur,
+            Justify: self.justify,
+        }
+    }
+}
+
+/// Converts a numpad-style `\an` alignment to the SSA-style `\a` one libass
+/// uses internally.
+pub fn numpad_to_legacy_alignment(alignment: i32) -> i32 {
+    let alignment = alignment.checked_abs().unwrap_or(2);
+    let horizontal = (alignment - 1).rem_euclid(3) + 1;
+    match alignment {
+        0..=3 => horizontal,
+        4..=6 => horizontal | 8,
+        _ => horizontal | 4,
+    }
+}
+
+/// Converts an SSA-style `\a` alignment to a numpad-style `\an` one.
+pub fn legacy_to_numpad_alignment(alignment: i32) -> i32 {
+    let horizontal = alignment & 3;
+    let horizontal = if horizontal == 0 { 2 } else { horizontal };
+    match alignment & 12 {
+        4 => horizontal + 6,
+        8 => horizontal + 3,
+        _ => horizontal,
+    }
+}
+
+impl Events {
+    /// Creates a section with only a default format line.
+    pub fn new(version: Version) -> Self {
+        Events {
+            header: "Events".to_string(),
+            lines: vec![EventLine::Format(default_event_format(
+                version == Version::V4,
+            ))],
+        }
+    }
+
+    /// The last format line, or the default one.
+    pub fn format(&self, ssa: bool) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                EventLine::Format(format) => Some(format.clone()),
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or_else(|| default_event_format(ssa))
+    }
+
+    /// Iterates over the `Dialogue:` and `Comment:` events.
+    pub fn events(&self) -> impl Iterator<Item = &Event> {
+        self.lines.iter().filter_map(|line| match line {
+            EventLine::Event(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn events_mut(&mut self) -> impl Iterator<Item = &mut Event> {
+        self.lines.iter_mut().filter_map(|line| match line {
+            EventLine::Event(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.lines.push(EventLine::Event(event));
+    }
+}
+
+impl EventLine {
+    fn parse(line: &str, format: Vec<String>) -> Self {
+        if let Some(comment) = line.strip_prefix(';') {
+            return EventLine::Comment(comment.to_string());
+        }
+        let (kind, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => return EventLine::Raw(line.to_string()),
+        };
+        let kind = match kind.trim() {
+            "Format" => return EventLine::Format(parse_format(value)),
+            "Dialogue" => EventKind::Dialogue,
+            "Comment" => EventKind::Comment,
+            _ => return EventLine::Raw(line.to_string()),
+        };
+        match Event::parse(kind, value.trim_start(), &format) {
+            Some(event) => EventLine::Event(event),
+            None => EventLine::Raw(line.to_string()),
+        }
+    }
+}
+
+impl Default for Event {
+    fn default() -> Self {
+        Event {
+            kind: EventKind::Dialogue,
+            layer: 0,
+            start: 0,
+            end: 0,
+            style: "Default".to_string(),
+            name: String::new(),
+            margin_l: 0,
+            margin_r: 0,
+            margin_v: 0,
+            effect: String::new(),
+            text: String::new(),
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl Event {
+    fn parse(kind: EventKind, value: &str, format: &[String]) -> Option<Self> {
+        let fields = split_fields(value, format.len());
+        if fields.len() < format.len() {
+            return None;
+        }
+        let mut event = Event {
+            kind,
+            ..Event::default()
+        };
+        for (name, field) in format.iter().zip(fields) {
+            match name.to_ascii_lowercase().as_str() {
+                "layer" => event.layer = parse_int(field)?,
+                "marked" => {
+                    let marked = field.trim();
+                    let marked = marked.strip_prefix("Marked=").unwrap_or(marked);
+                    event.layer = parse_int(marked)?;
+                }
+                "start" => event.start = parse_time(field)?,
+                "end" => event.end = parse_time(field)?,
+                "style" => event.style = field.trim().to_string(),
+                "name" | "actor" => event.name = field.trim().to_string(),
+                "marginl" => event.margin_l = parse_int(field)?,
+                "marginr" => event.margin_r = parse_int(field)?,
+                "marginv" => event.margin_v = parse_int(field)?,
+                "effect" => event.effect = field.trim().to_string(),
+                "text" => event.text = field.to_string(),
+                _ => event.extra.push((name.clone(), field.to_string())),
+            }
+        }
+        Some(event)
+    }
+
+    fn write(&self, out: &mut impl fmt::Write, format: &[String]) -> fmt::Result {
+        out.write_str(match self.kind {
+            EventKind::Dialogue => "Dialogue: ",
+            EventKind::Comment => "Comment: ",
+        })?;
+        for (i, name) in format.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            match name.to_ascii_lowercase().as_str() {
+                "layer" => write!(out, "{}", self.layer)?,
+                "marked" => write!(out, "Marked={}", self.layer)?,
+                "start" => out.write_str(&format_time(self.start))?,
+                "end" => out.write_str(&format_time(self.end))?,
+                "style" => out.write_str(&self.style)?,
+                "name" | "actor" => out.write_str(&self.name)?,
+                "marginl" => write!(out, "{}", self.margin_l)?,
+                "marginr" => write!(out, "{}", self.margin_r)?,
+                "marginv" => write!(out, "{}", self.margin_v)?,
+                "effect" => out.write_str(&self.effect)?,
+                "text" => out.write_str(&self.text)?,
+                _ => out.write_str(extra_field(&self.extra, name))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn extra_field<'a>(extra: &'a [(String, String)], name: &str) -> &'a str {
+    extra
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map_or("", |(_, v)| v)
+}
+
+// Splits into at most `count` fields, the last one keeping its commas.
+fn split_fields(value: &str, count: usize) -> Vec<&str> {
+    value.splitn(count.max(1), ',').collect()
+}
+
+impl Attachments {
+    pub fn new_fonts() -> Self {
+        Attachments {
+            header: "Fonts".to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn new_graphics() -> Self {
+        Attachments {
+            header: "Graphics".to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    fn push_line(&mut self, line: &str, prefix: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if let Some(name) = line.strip_prefix(prefix) {
+            self.files.push(Attachment {
+                name: name.trim().to_string(),
+                lines: Vec::new(),
+            });
+        } else if let Some(file) = self.files.last_mut() {
+            file.lines.push(line.to_string());
+        }
+    }
+}
+
+// characters per line when encoding, as written by Aegisub
+const ENCODED_LINE_LENGTH: usize = 80;
+
+impl Attachment {
+    /// Encodes a file with the uuencode variant of ASS, in which each group
+    /// of three bytes becomes four characters of six bits plus 33.
+    pub fn encode(name: &str, data: &[u8]) -> Self {
+        let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+        for chunk in data.chunks(3) {
+            let mut bytes = [0; 3];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+            // a partial group only needs the characters that hold its bits
+            for i in 0..chunk.len() + 1 {
+                encoded.push(char::from((value >> (18 - 6 * i) & 63) as u8 + 33));
+            }
+        }
+        let lines = encoded
+            .as_bytes()
+            .chunks(ENCODED_LINE_LENGTH)
+            .map(|line| String::from_utf8_lossy(line).into_owned())
+            .collect();
+        Attachment {
+            name: name.to_string(),
+            lines,
+        }
+    }
+
+    /// Decodes the file the way libass does, ignoring a trailing single
+    /// character.
+    pub fn decode(&self) -> Vec<u8> {
+        let encoded: Vec<u8> = self.lines.iter().flat_map(|l| l.bytes()).collect();
+        let mut data = Vec::with_capacity(encoded.len() / 4 * 3);
+        for chunk in encoded.chunks(4) {
+            if chunk.len() < 2 {
+                break;
+            }
+            let value = chunk.iter().enumerate().fold(0, |value, (i, &c)| {
+                value | (u32::from(c.wrapping_sub(33)) & 63) << (18 - 6 * i)
+            });
+            for i in 0..chunk.len() - 1 {
+                data.push((value >> (16 - 8 * i)) as u8);
+            }
+        }
+        data
+    }
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// From the `0xAABBGGRR` value written in scripts.
+    pub fn from_abgr(value: u32) -> Self {
+        let [a, b, g, r] = value.to_be_bytes();
+        Color { r, g, b, a }
+    }
+
+    pub fn to_abgr(self) -> u32 {
+        u32::from_be_bytes([self.a, self.b, self.g, self.r])
+    }
+
+    /// From the `0xRRGGBBAA` value libass uses.
+    pub fn from_rgba(value: u32) -> Self {
+        let [r, g, b, a] = value.to_be_bytes();
+        Color { r, g, b, a }
+    }
+
+    pub fn to_rgba(self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// Parses `&HAABBGGRR&` style colors, or decimal ones as found in SSA.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let value = value.strip_suffix('&').unwrap_or(value);
+        let abgr = match value.get(..2) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("&h") => {
+                let hex = &value[2..];
+                if hex.is_empty() {
+                    0
+                } else {
+                    u32::from_str_radix(hex, 16).ok()?
+                }
+            }
+            _ => value.parse::<i64>().ok()? as u32,
+        };
+        Some(Color::from_abgr(abgr))
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "&H{:08X}", self.to_abgr())
+    }
+}
+
+fn parse_int(value: &str) -> Option<i32> {
+    value.trim().parse().ok()
+}
+
+fn parse_float(value: &str) -> Option<f64> {
+    value.trim().parse().ok()
+}
+
+/// Parses an `H:MM:SS.CC` timestamp into milliseconds.
+pub fn parse_time(value: &str) -> Option<i64> {
+    let mut parts = value.trim().splitn(3, ':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let (seconds, centis) = match parts.next()?.split_once('.') {
+        Some((s, cs)) => (s, cs),
+        None => (value.trim().rsplit(':').next()?, "0"),
+    };
+    let seconds: i64 = seconds.parse().ok()?;
+    let centis: i64 = centis.parse().ok()?;
+    // like libass and VSFilter, the fraction is always in centiseconds
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + centis * 10)
+}
+
+/// Formats milliseconds as an `H:MM:SS.CC` timestamp, rounding to the
+/// nearest centisecond.
+pub fn format_time(ms: i64) -> String {
+    let cs = (ms.max(0) + 5) / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+impl Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.preamble {
+            writeln!(f, "{}", line)?;
+        }
+        let version = self.styles().map_or(Version::V4Plus, |s| s.version);
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{}]", section.header())?;
+            match section {
+                Section::ScriptInfo(info) => {
+                    for line in &info.lines {
+                        match line {
+                            InfoLine::Property { key, value } => writeln!(f, "{}: {}", key, value)?,
+                            InfoLine::Comment(comment) => writeln!(f, ";{}", comment)?,
+                            InfoLine::Raw(raw) => writeln!(f, "{}", raw)?,
+                        }
+                    }
+                }
+                Section::Styles(styles) => {
+                    let mut format = default_style_format(styles.version);
+                    for line in &styles.lines {
+                        match line {
+                            StyleLine::Format(fields) => {
+                                format = fields.clone();
+                                writeln!(f, "Format: {}", fields.join(", "))?;
+                            }
+                            StyleLine::Style(style) => {
+                                style.write(f, &format, styles.version)?;
+                                writeln!(f)?;
+                            }
+                            StyleLine::Comment(comment) => writeln!(f, ";{}", comment)?,
+                            StyleLine::Raw(raw) => writeln!(f, "{}", raw)?,
+                        }
+                    }
+                }
+                Section::Events(events) => {
+                    let mut format = default_event_format(version == Version::V4);
+                    for line in &events.lines {
+                        match line {
+                            EventLine::Format(fields) => {
+                                format = fields.clone();
+                                writeln!(f, "Format: {}", fields.join(", "))?;
+                            }
+                            EventLine::Event(event) => {
+                                event.write(f, &format)?;
+                                writeln!(f)?;
+                            }
+                            EventLine::Comment(comment) => writeln!(f, ";{}", comment)?,
+                            EventLine::Raw(raw) => writeln!(f, "{}", raw)?,
+                        }
+                    }
+                }
+                Section::Fonts(attachments) | Section::Graphics(attachments) => {
+                    let prefix = match section {
+                        Section::Fonts(_) => "fontname",
+                        _ => "filename",
+                    };
+                    for file in &attachments.files {
+                        writeln!(f, "{}: {}", prefix, file.name)?;
+                        for line in &file.lines {
+                            writeln!(f, "{}", line)?;
+                        }
+                    }
+                }
+                Section::Unknown(unknown) => {
+                    for line in &unknown.lines {
+                        writeln!(f, "{}", line)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
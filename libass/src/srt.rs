@@ -0,0 +1,339 @@
+//! SubRip (SRT) subtitles.
+
+use std::io;
+
+use crate::export::{export_cues, ExportReport, Loss, LossKind};
+use crate::library::Library;
+use crate::style::Style;
+use crate::track::Track;
+use crate::Result;
+
+/// A single subtitle from an SRT file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cue {
+    /// Start time in milliseconds.
+    pub start: i64,
+    /// End time in milliseconds.
+    pub end: i64,
+    /// The text, with lines separated by `\n` and SRT's HTML-like tags left
+    /// in place.
+    pub text: String,
+}
+
+/// Parses SRT cues, skipping blocks without a valid timing line.
+pub fn parse(data: &str) -> Vec<Cue> {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    let mut cues = Vec::new();
+    let mut lines = data.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let (start, end) = match parse_timing(line) {
+            Some(timing) => timing,
+            // cue numbers, stray text and blank lines
+            None => continue,
+        };
+
+        let mut text = Vec::new();
+        while let Some(line) = lines.peek() {
+            if line.trim().is_empty() || parse_timing(line).is_some() {
+                break;
+            }
+            text.push(line.trim_end());
+            lines.next();
+        }
+        // the number of the next cue, if it came without a blank line
+        if lines.peek().is_some_and(|l| parse_timing(l).is_some()) {
+            if let Some(last) = text.last() {
+                if last.trim().parse::<u64>().is_ok() {
+                    text.pop();
+                }
+            }
+        }
+
+        cues.push(Cue {
+            start,
+            end,
+            text: text.join("\n"),
+        });
+    }
+
+    cues
+}
+
+// `00:00:01,000 --> 00:00:04,000`, possibly followed by coordinates
+fn parse_timing(line: &str) -> Option<(i64, i64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_time(start)?, parse_time(end)?))
+}
+
+/// Parses an `HH:MM:SS,mmm` timestamp into milliseconds.
+pub fn parse_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (clock, ms) = match value.rfind([',', '.']) {
+        Some(i) => (&value[..i], &value[i + 1..]),
+        None => (value, "0"),
+    };
+    let mut parts = clock.split(':').rev();
+    let seconds: i64 = parts.next()?.trim().parse().ok()?;
+    let minutes: i64 = parts.next().map_or(Some(0), |m| m.trim().parse().ok())?;
+    let hours: i64 = parts.next().map_or(Some(0), |h| h.trim().parse().ok())?;
+    if parts.next().is_some() || ms.is_empty() || ms.len() > 3 {
+        return None;
+    }
+    // `,5` is half a second, not five milliseconds
+    let ms = ms.parse::<i64>().ok()? * 10i64.pow(3 - ms.len() as u32);
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + ms)
+}
+
+/// Formats milliseconds as an `HH:MM:SS,mmm` timestamp.
+pub fn format_time(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Converts SRT text to ASS event text: line breaks become `\N`, and `<b>`,
+/// `<i>`, `<u>`, `<s>` and `<font>` become override tags. Unknown tags are
+/// dropped.
+///
+/// Literal `{` and `}` are escaped as `\{` and `\}`, and a word joiner
+/// follows a literal `\` that would otherwise start an escape, so that
+/// libass doesn't read them as override blocks or escapes. Only the `{\an8}`
+/// style alignment blocks that many players accept in SRT are kept as they
+/// are.
+pub fn to_ass_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    // tags undoing each open <font>
+    let mut fonts: Vec<Vec<&'static str>> = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('<') {
+        let close = match rest[open..].find('>') {
+            Some(i) => open + i,
+            None => break,
+        };
+        push_escaped(&mut out, &rest[..open], true);
+        let tag = rest[open + 1..close].trim();
+        rest = &rest[close + 1..];
+
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag.trim()),
+            None => (false, tag),
+        };
+        let name_len = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let (name, attributes) = tag.split_at(name_len);
+        let value = if closing { '0' } else { '1' };
+        match name.to_ascii_lowercase().as_str() {
+            "b" | "i" | "u" | "s" => {
+                out.push_str("{\\");
+                out.push_str(&name.to_ascii_lowercase());
+                out.push(value);
+                out.push('}');
+            }
+            "font" if !closing => {
+                let mut tags = String::new();
+                let mut resets = Vec::new();
+                for (key, value) in html_attributes(attributes) {
+                    match key.to_ascii_lowercase().as_str() {
+                        "color" => {
+                            if let Some((r, g, b)) = html_color(&value) {
+                                tags.push_str(&format!("\\c&H{:02X}{:02X}{:02X}&", b, g, r));
+                                resets.push("\\c");
+                            }
+                        }
+                        "face" => {
+                            // a brace or backslash would end the override block
+                            let face = value.replace(['{', '}', '\\'], "");
+                            tags.push_str(&format!("\\fn{}", face));
+                            resets.push("\\fn");
+                        }
+                        "size" => {
+                            if let Ok(size) = value.trim().parse::<f64>() {
+                                tags.push_str(&format!("\\fs{}", size));
+                                resets.push("\\fs");
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if !tags.is_empty() {
+                    out.push('{');
+                    out.push_str(&tags);
+                    out.push('}');
+                }
+                fonts.push(resets);
+            }
+            "font" => {
+                // back to the style's values; nested fonts are rare enough
+                // that restoring the outer one isn't worth the bookkeeping
+                if let Some(resets) = fonts.pop() {
+                    if !resets.is_empty() {
+                        out.push('{');
+                        out.push_str(&resets.concat());
+                        out.push('}');
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    push_escaped(&mut out, rest, false);
+
+    out.replace("\r\n", "\\N").replace('\n', "\\N")
+}
+
+// `before_tag` is set when an override block may follow the text
+fn push_escaped(out: &mut String, text: &str, before_tag: bool) {
+    let mut rest = text;
+    while let Some(i) = rest.find(['{', '}', '\\']) {
+        out.push_str(&rest[..i]);
+        let alignment = rest[i..]
+            .strip_prefix("{\\an")
+            .and_then(|tag| tag.get(..2))
+            .filter(|tag| matches!(tag.as_bytes(), [b'1'..=b'9', b'}']));
+        if alignment.is_some() {
+            out.push_str(&rest[i..i + 6]);
+            rest = &rest[i + 6..];
+            continue;
+        }
+        let next = rest[i + 1..].chars().next();
+        let c = rest[i..].chars().next().unwrap();
+        push_escaped_char(out, c, next.or(if before_tag { Some('{') } else { None }));
+        rest = &rest[i + 1..];
+    }
+    out.push_str(rest);
+}
+
+// Appends `c` so that libass shows it as is when `next` follows it.
+pub(crate) fn push_escaped_char(out: &mut String, c: char, next: Option<char>) {
+    match c {
+        '{' | '}' => {
+            out.push('\\');
+            out.push(c);
+        }
+        // a word joiner keeps the backslash from starting an escape
+        '\\' if matches!(next, Some('N' | 'n' | 'h' | '{' | '}')) => out.push_str("\\\u{2060}"),
+        _ => out.push(c),
+    }
+}
+
+fn html_attributes(attributes: &str) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    let mut rest = attributes.trim();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        let after = rest[eq + 1..].trim_start();
+        let (value, next) = match after.chars().next() {
+            Some(quote @ '"') | Some(quote @ '\'') => {
+                let end = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                (&after[1..end], after.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        out.push((key, value.to_string()));
+        rest = next.trim_start();
+    }
+    out
+}
+
+pub(crate) fn html_color(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim();
+    let hex = match value.strip_prefix('#') {
+        Some(hex) => hex,
+        None => match value.to_ascii_lowercase().as_str() {
+            "white" => "ffffff",
+            "black" => "000000",
+            "red" => "ff0000",
+            "lime" => "00ff00",
+            "green" => "008000",
+            "blue" => "0000ff",
+            "yellow" => "ffff00",
+            "cyan" | "aqua" => "00ffff",
+            "magenta" | "fuchsia" => "ff00ff",
+            "silver" => "c0c0c0",
+            "gray" | "grey" => "808080",
+            "maroon" => "800000",
+            "olive" => "808000",
+            "purple" => "800080",
+            "teal" => "008080",
+            "navy" => "000080",
+            "orange" => "ffa500",
+            _ => value,
+        },
+    };
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some((channel(0)?, channel(2)?, channel(4)?)),
+        3 => {
+            let digit = |i: usize| u8::from_str_radix(hex.get(i..=i)?, 16).ok().map(|d| d * 17);
+            Some((digit(0)?, digit(1)?, digit(2)?))
+        }
+        _ => None,
+    }
+}
+
+impl Library {
+    /// Creates a track from SubRip subtitles, with every event in a copy of
+    /// `style`, which becomes the track's default style.
+    ///
+    /// As with ASS scripts without `PlayResX` and `PlayResY`, sizes and
+    /// margins in `style` are relative to a 384x288 frame.
+    pub fn new_track_from_srt(&self, data: &str, style: &Style) -> Result<Track<'_>> {
+        let mut track = self.new_track()?;
+        let style = track.add_style(style);
+        track.as_ass_track_mut().default_style = style;
+
+        for cue in parse(data) {
+            let mut event = track.add_event();
+            event.set_start(cue.start);
+            event.set_duration(cue.end - cue.start);
+            event.set_style(style);
+            event.set_text(&to_ass_text(&cue.text));
+        }
+
+        Ok(track)
+    }
+}
+
+impl<'library> Track<'library> {
+    /// Writes the events as SubRip in time order.
+    ///
+    /// Line breaks, bold, italic and underline are kept, as is top-center
+    /// alignment in the common `{\an8}` form. Everything else is left out and
+    /// listed in the returned report.
+    pub fn write_srt(&self, out: &mut impl io::Write) -> io::Result<ExportReport> {
+        let mut report = ExportReport::default();
+        for (i, cue) in export_cues(self, false, &mut report).iter().enumerate() {
+            let alignment = match cue.alignment {
+                2 => "",
+                8 => "{\\an8}",
+                alignment => {
+                    report.losses.push(Loss {
+                        event: cue.event,
+                        kind: LossKind::Alignment(alignment),
+                    });
+                    ""
+                }
+            };
+            writeln!(out, "{}", i + 1)?;
+            writeln!(
+                out,
+                "{} --> {}",
+                format_time(cue.start),
+                format_time(cue.end)
+            )?;
+            writeln!(out, "{}{}", alignment, cue.text)?;
+            writeln!(out)?;
+        }
+        Ok(report)
+    }
+}
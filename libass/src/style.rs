@@ -4,6 +4,7 @@ use std::os::raw::c_int;
 
 use libass_sys as ffi;
 
+#[derive(Clone)]
 pub struct Style {
     pub name: CString,
     pub font_name: CString,
@@ -0,0 +1,1091 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::font_report::{best_face, face_name, family_name, font_runs, FontRequest};
+use crate::fonts::{
+    decode_name, face_offset, faces, font_files, tables, u16_at, u32_at, Face, FailedFont,
+};
+use crate::tags::{EventText, Tag};
+use crate::track::{replace_str, Track};
+use crate::{Error, Result};
+
+/// A font cut down to the characters a track draws in it, see
+/// [`Track::subset_fonts`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SubsetFont {
+    /// The font file it was made from.
+    pub source: PathBuf,
+    /// The source's file name with the subset's prefix, e.g.
+    /// `KQWZAB+DejaVuSans.ttf`.
+    pub file_name: String,
+    /// The renamed family, e.g. `KQWZAB+DejaVu Sans`.
+    pub family: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FontSubsets {
+    pub fonts: Vec<SubsetFont>,
+    /// Fonts that couldn't be read or subset, such as variable CFF2 fonts,
+    /// and subdirectories that couldn't be read. The script still refers
+    /// to their families by their original names, so the other faces of
+    /// those families aren't in `fonts` either.
+    pub failed: Vec<FailedFont>,
+}
+
+impl<'library> Track<'library> {
+    /// Subsets the fonts in `fonts_dir` to the characters the track draws
+    /// in each face, following styles and `\fn`, `\b`, `\i` and `\r` tags.
+    ///
+    /// Each subset family gets a prefix derived from its name and
+    /// characters, so that it can't be mistaken for the full font or
+    /// another subset in a font cache, and the track's styles and `\fn`
+    /// tags are updated to match. Faces missing from `fonts_dir` are left
+    /// alone, see [`Track::font_report`].
+    pub fn subset_fonts(&mut self, fonts_dir: &Path) -> io::Result<FontSubsets> {
+        let mut chars: BTreeMap<FontRequest, BTreeSet<char>> = BTreeMap::new();
+        for run in font_runs(self) {
+            chars
+                .entry(run.request)
+                .or_default()
+                .extend(run.text.chars().filter(|c| !c.is_control()));
+        }
+
+        let mut subsets = FontSubsets::default();
+        let mut candidates: Vec<((PathBuf, usize), Face)> = Vec::new();
+        for path in font_files(fonts_dir, &mut subsets.failed)? {
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(error) => {
+                    let error = error.to_string();
+                    subsets.failed.push(FailedFont { path, error });
+                    continue;
+                }
+            };
+            for (index, face) in faces(&data).unwrap_or_default().into_iter().enumerate() {
+                candidates.push(((path.clone(), index), face));
+            }
+        }
+
+        // the characters of each chosen face, and the names that refer to it
+        let mut selected: BTreeMap<usize, (BTreeSet<char>, Vec<String>)> = BTreeMap::new();
+        for (request, chars) in chars {
+            let (candidate, _) = match best_face(&request, &candidates) {
+                Some(found) => found,
+                None => continue,
+            };
+            let index = candidates
+                .iter()
+                .position(|c| std::ptr::eq(c, candidate))
+                .unwrap();
+            let (selected_chars, names) = selected.entry(index).or_default();
+            selected_chars.extend(chars);
+            let name = face_name(&candidate.1, &request.family)
+                .unwrap()
+                .to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        // faces of a family share a prefix so that libass still finds the
+        // bold and italic ones
+        let mut family_chars: BTreeMap<String, BTreeSet<char>> = BTreeMap::new();
+        for (&index, (chars, _)) in &selected {
+            let family = candidates[index].1.families[0].to_lowercase();
+            family_chars.entry(family).or_default().extend(chars);
+        }
+
+        let mut subset = Vec::new();
+        let mut failed_families = BTreeSet::new();
+        for (index, (chars, names)) in selected {
+            let ((path, face_index), face) = &candidates[index];
+            let family = face.families[0].to_lowercase();
+            let prefix = subset_prefix(&family, &family_chars[&family]);
+            let result = fs::read(path).map_err(|e| e.to_string()).and_then(|data| {
+                subset_font(&data, *face_index, &chars, &prefix)
+                    .map_err(|_| "unsupported font format".to_string())
+            });
+            match result {
+                Ok(data) => {
+                    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let font = SubsetFont {
+                        source: path.clone(),
+                        file_name: format!("{}+{}", prefix, file_name),
+                        family: format!("{}+{}", prefix, face.families[0]),
+                        data,
+                    };
+                    let renames: Vec<_> = names
+                        .into_iter()
+                        .map(|name| (name.to_lowercase(), format!("{}+{}", prefix, name)))
+                        .collect();
+                    subset.push((family, font, renames));
+                }
+                Err(error) => {
+                    failed_families.insert(family);
+                    subsets.failed.push(FailedFont {
+                        path: path.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+
+        // a family is only renamed when all of its faces could be subset,
+        // as the renamed one would be missing the others
+        let mut renames: Vec<(String, String)> = Vec::new();
+        for (family, font, font_renames) in subset {
+            if !failed_families.contains(&family) {
+                renames.extend(font_renames);
+                subsets.fonts.push(font);
+            }
+        }
+
+        self.rename_fonts(&renames);
+        Ok(subsets)
+    }
+
+    // Replaces the font names of styles and `\fn` tags, keeping the `@` of
+    // vertical fonts.
+    fn rename_fonts(&mut self, renames: &[(String, String)]) {
+        let rename = |name: &str| -> Option<String> {
+            let family = family_name(name).to_lowercase();
+            let (_, renamed) = renames.iter().find(|(from, _)| *from == family)?;
+            let vertical = if name.trim().starts_with('@') {
+                "@"
+            } else {
+                ""
+            };
+            Some(format!("{}{}", vertical, renamed))
+        };
+
+        for id in 0..self.style_count() {
+            let name = self.style(id as i32).unwrap().font_name;
+            if let Some(renamed) = rename(&name.to_string_lossy()) {
+                let style = unsafe { &mut *self.as_ass_track_mut().styles.add(id) };
+                unsafe { replace_str(&mut style.FontName, &renamed) };
+            }
+        }
+
+        for id in 0..self.event_count() as i32 {
+            let mut event = self.event_mut(id).unwrap();
+            let text = event.text().into_owned();
+            let mut parsed = EventText::parse(&text);
+            for tag in parsed.tags_mut() {
+                if let Tag::FontName(name) = tag {
+                    if let Some(renamed) = rename(name) {
+                        *name = renamed;
+                    }
+                }
+            }
+            let renamed = parsed.to_string();
+            if renamed != text {
+                event.set_text(&renamed);
+            }
+        }
+    }
+}
+
+/// Six capital letters from a hash of `family` and `chars`, like the
+/// subset tags of fonts embedded in PDFs.
+fn subset_prefix(family: &str, chars: &BTreeSet<char>) -> String {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let bytes = family
+        .bytes()
+        .chain(chars.iter().flat_map(|&c| (c as u32).to_le_bytes()));
+    for byte in bytes {
+        hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    (0..6)
+        .map(|_| {
+            let letter = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            letter
+        })
+        .collect()
+}
+
+/// Cuts the face at `index` of a TrueType or OpenType font or collection
+/// down to the glyphs needed for `chars`, and prefixes its names with
+/// `prefix` and `+`.
+///
+/// Glyph ids don't change: unused glyphs are emptied, and glyphs that
+/// components, ligatures or other substitutions can reach from the used
+/// ones are kept. Fails for variable CFF2 fonts and bitmap-only fonts.
+pub fn subset_font(
+    data: &[u8],
+    index: usize,
+    chars: &BTreeSet<char>,
+    prefix: &str,
+) -> Result<Vec<u8>> {
+    let offset = face_offset(data, index).ok_or(Error)?;
+    let tables = tables(data, offset).ok_or(Error)?;
+    let table = |tag: &[u8; 4]| {
+        tables
+            .iter()
+            .find(|t| &t.0 == tag)
+            .map(|&(_, start, length)| &data[start..start + length])
+    };
+
+    let glyph_count = u16_at(table(b"maxp").ok_or(Error)?, 4).ok_or(Error)? as usize;
+    if glyph_count == 0 {
+        return Err(Error);
+    }
+    let cmap = table(b"cmap").ok_or(Error)?;
+    let mut keep = vec![false; glyph_count];
+    keep[0] = true;
+    let mut mapping = Vec::new();
+    for &c in chars {
+        if let Some(glyph) = cmap_lookup(cmap, c as u32).filter(|&g| (g as usize) < glyph_count) {
+            keep[glyph as usize] = true;
+            mapping.push((c as u32, glyph));
+        }
+    }
+    if let Some(gsub) = table(b"GSUB") {
+        gsub_closure(gsub, &mut keep);
+    }
+
+    let mut replaced: Vec<([u8; 4], Vec<u8>)> = vec![
+        (*b"cmap", build_cmap(&mapping)),
+        (
+            *b"name",
+            rename(table(b"name").ok_or(Error)?, prefix).ok_or(Error)?,
+        ),
+    ];
+    if let (Some(glyf), Some(loca), Some(head)) = (table(b"glyf"), table(b"loca"), table(b"head")) {
+        let (glyf, loca, head) = subset_glyf(glyf, loca, head, &mut keep).ok_or(Error)?;
+        replaced.push((*b"glyf", glyf));
+        replaced.push((*b"loca", loca));
+        replaced.push((*b"head", head));
+    } else if let Some(cff) = table(b"CFF ") {
+        replaced.push((*b"CFF ", subset_cff(cff, &keep, prefix).ok_or(Error)?));
+    } else {
+        return Err(Error);
+    }
+
+    let mut out_tables: Vec<([u8; 4], Vec<u8>)> = tables
+        .iter()
+        // the signature wouldn't match anymore
+        .filter(|(tag, _, _)| tag != b"DSIG")
+        .map(
+            |&(tag, start, length)| match replaced.iter().position(|(t, _)| *t == tag) {
+                Some(i) => (tag, std::mem::take(&mut replaced[i].1)),
+                None => (tag, data[start..start + length].to_vec()),
+            },
+        )
+        .collect();
+    out_tables.sort_by_key(|&(tag, _)| tag);
+    let version = u32_at(data, offset).ok_or(Error)?;
+    Ok(write_font(version, &out_tables))
+}
+
+fn i16_at(data: &[u8], offset: usize) -> Option<i16> {
+    u16_at(data, offset).map(|value| value as i16)
+}
+
+fn cmap_lookup(cmap: &[u8], c: u32) -> Option<u16> {
+    let count = u16_at(cmap, 2)? as usize;
+    // full Unicode tables first, then BMP ones
+    let mut best: Option<(u8, usize)> = None;
+    for i in 0..count {
+        let record = 4 + 8 * i;
+        let platform = u16_at(cmap, record)?;
+        let encoding = u16_at(cmap, record + 2)?;
+        let offset = u32_at(cmap, record + 4)? as usize;
+        let format = u16_at(cmap, offset)?;
+        let rank = match (platform, encoding, format) {
+            (3, 10, 12) | (0, _, 12) => 2,
+            (3, 1, 4) | (0, _, 4) => 1,
+            _ => continue,
+        };
+        if best.map_or(true, |(r, _)| rank > r) {
+            best = Some((rank, offset));
+        }
+    }
+    let (_, offset) = best?;
+    let subtable = cmap.get(offset..)?;
+    let glyph = match u16_at(subtable, 0)? {
+        4 => cmap_format_4(subtable, c)?,
+        _ => cmap_format_12(subtable, c)?,
+    };
+    Some(glyph).filter(|&glyph| glyph != 0)
+}
+
+fn cmap_format_4(subtable: &[u8], c: u32) -> Option<u16> {
+    if c > 0xFFFF {
+        return None;
+    }
+    let segments = u16_at(subtable, 6)? as usize / 2;
+    let ends = 14;
+    let starts = ends + 2 * segments + 2;
+    let deltas = starts + 2 * segments;
+    let range_offsets = deltas + 2 * segments;
+    for i in 0..segments {
+        if u16_at(subtable, ends + 2 * i)? as u32 >= c {
+            let start = u16_at(subtable, starts + 2 * i)? as u32;
+            if start > c {
+                return None;
+            }
+            let delta = u16_at(subtable, deltas + 2 * i)?;
+            let range_offset = u16_at(subtable, range_offsets + 2 * i)? as usize;
+            if range_offset == 0 {
+                return Some((c as u16).wrapping_add(delta));
+            }
+            let at = range_offsets + 2 * i + range_offset + 2 * (c - start) as usize;
+            let glyph = u16_at(subtable, at)?;
+            return Some(glyph)
+                .filter(|&g| g != 0)
+                .map(|g| g.wrapping_add(delta));
+        }
+    }
+    None
+}
+
+fn cmap_format_12(subtable: &[u8], c: u32) -> Option<u16> {
+    let groups = u32_at(subtable, 12)? as usize;
+    for i in 0..groups {
+        let group = 16 + 12 * i;
+        let start = u32_at(subtable, group)?;
+        let end = u32_at(subtable, group + 4)?;
+        if (start..=end).contains(&c) {
+            return Some(u32_at(subtable, group + 8)?.wrapping_add(c - start) as u16);
+        }
+    }
+    None
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// A `cmap` with a format 4 table for the BMP, if it fits, and a format 12
+/// table for everything.
+fn build_cmap(mapping: &[(u32, u16)]) -> Vec<u8> {
+    // runs of consecutive characters mapped to consecutive glyphs
+    let mut groups: Vec<(u32, u32, u16)> = Vec::new();
+    for &(c, glyph) in mapping {
+        match groups.last_mut() {
+            Some((start, end, first))
+                if c == *end + 1 && glyph as u32 == *first as u32 + (c - *start) =>
+            {
+                *end = c
+            }
+            _ => groups.push((c, c, glyph)),
+        }
+    }
+
+    let mut segments: Vec<(u16, u16, u16)> = groups
+        .iter()
+        .filter(|&&(start, _, _)| start <= 0xFFFF)
+        .map(|&(start, end, glyph)| {
+            let end = end.min(0xFFFF) as u16;
+            (start as u16, end, glyph.wrapping_sub(start as u16))
+        })
+        .collect();
+    segments.push((0xFFFF, 0xFFFF, 1));
+    let format_4_length = 16 + 8 * segments.len();
+
+    let mut format_4 = Vec::new();
+    if format_4_length <= 0xFFFF {
+        let count = segments.len() as u16;
+        let entry_selector = 15 - count.leading_zeros() as u16;
+        let search_range = 2 << entry_selector;
+        push_u16(&mut format_4, 4);
+        push_u16(&mut format_4, format_4_length as u16);
+        push_u16(&mut format_4, 0);
+        push_u16(&mut format_4, count * 2);
+        push_u16(&mut format_4, search_range);
+        push_u16(&mut format_4, entry_selector);
+        push_u16(&mut format_4, count * 2 - search_range);
+        for &(_, end, _) in &segments {
+            push_u16(&mut format_4, end);
+        }
+        push_u16(&mut format_4, 0);
+        for &(start, _, _) in &segments {
+            push_u16(&mut format_4, start);
+        }
+        for &(_, _, delta) in &segments {
+            push_u16(&mut format_4, delta);
+        }
+        for _ in &segments {
+            push_u16(&mut format_4, 0);
+        }
+    }
+
+    let mut format_12 = Vec::new();
+    push_u16(&mut format_12, 12);
+    push_u16(&mut format_12, 0);
+    push_u32(&mut format_12, 16 + 12 * groups.len() as u32);
+    push_u32(&mut format_12, 0);
+    push_u32(&mut format_12, groups.len() as u32);
+    for &(start, end, glyph) in &groups {
+        push_u32(&mut format_12, start);
+        push_u32(&mut format_12, end);
+        push_u32(&mut format_12, glyph as u32);
+    }
+
+    let mut subtables = vec![((3, 10), format_12)];
+    if !format_4.is_empty() {
+        subtables.insert(0, ((3, 1), format_4));
+    }
+    let mut cmap = Vec::new();
+    push_u16(&mut cmap, 0);
+    push_u16(&mut cmap, subtables.len() as u16);
+    let mut offset = 4 + 8 * subtables.len();
+    for ((platform, encoding), subtable) in &subtables {
+        push_u16(&mut cmap, *platform);
+        push_u16(&mut cmap, *encoding);
+        push_u32(&mut cmap, offset as u32);
+        offset += subtable.len();
+    }
+    for (_, subtable) in subtables {
+        cmap.extend(subtable);
+    }
+    cmap
+}
+
+/// Prefixes the family, full, PostScript and unique names with `prefix`.
+fn rename(name: &[u8], prefix: &str) -> Option<Vec<u8>> {
+    let count = u16_at(name, 2)? as usize;
+    let strings = u16_at(name, 4)? as usize;
+    let mut records = Vec::new();
+    for i in 0..count {
+        let record = 6 + 12 * i;
+        let platform = u16_at(name, record)?;
+        let encoding = u16_at(name, record + 2)?;
+        let language = u16_at(name, record + 4)?;
+        let id = u16_at(name, record + 6)?;
+        let length = u16_at(name, record + 8)? as usize;
+        let start = strings + u16_at(name, record + 10)? as usize;
+        // language tags of format 1 tables aren't kept
+        if language >= 0x8000 {
+            continue;
+        }
+        let mut bytes = name.get(start..start + length)?.to_vec();
+        if let (1 | 3 | 4 | 6 | 16 | 21, Some(value)) =
+            (id, decode_name(platform, encoding, &bytes))
+        {
+            let value = format!("{}+{}", prefix, value);
+            bytes = match platform {
+                // decoded a char per byte
+                1 => value.chars().map(|c| c as u8).collect(),
+                _ => value.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            };
+        }
+        records.push(([platform, encoding, language, id], bytes));
+    }
+
+    // counts, lengths and offsets are 16 bits, which longer names can
+    // overflow
+    let mut out = Vec::new();
+    push_u16(&mut out, 0);
+    push_u16(&mut out, u16::try_from(records.len()).ok()?);
+    push_u16(&mut out, u16::try_from(6 + 12 * records.len()).ok()?);
+    let mut offset = 0;
+    for (fields, bytes) in &records {
+        for &field in fields {
+            push_u16(&mut out, field);
+        }
+        push_u16(&mut out, u16::try_from(bytes.len()).ok()?);
+        push_u16(&mut out, u16::try_from(offset).ok()?);
+        offset += bytes.len();
+    }
+    for (_, bytes) in records {
+        out.extend(bytes);
+    }
+    Some(out)
+}
+
+/// Keeps the glyphs that substitutions can produce from kept glyphs,
+/// without checking their contexts.
+fn gsub_closure(gsub: &[u8], keep: &mut [bool]) {
+    let subtables = gsub_subtables(gsub).unwrap_or_default();
+    loop {
+        let mut added = Vec::new();
+        for &(kind, subtable) in &subtables {
+            gsub_outputs(kind, subtable, keep, &mut added);
+        }
+        let mut changed = false;
+        for glyph in added {
+            if let Some(kept) = keep.get_mut(glyph as usize) {
+                changed |= !*kept;
+                *kept = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+// the subtables of every lookup with their types, looking through
+// extension lookups
+fn gsub_subtables(gsub: &[u8]) -> Option<Vec<(u16, &[u8])>> {
+    let lookups = gsub.get(u16_at(gsub, 8)? as usize..)?;
+    let mut subtables = Vec::new();
+    for i in 0..u16_at(lookups, 0)? as usize {
+        let lookup = lookups.get(u16_at(lookups, 2 + 2 * i)? as usize..)?;
+        let kind = u16_at(lookup, 0)?;
+        for j in 0..u16_at(lookup, 4)? as usize {
+            let subtable = lookup.get(u16_at(lookup, 6 + 2 * j)? as usize..)?;
+            if kind == 7 {
+                let kind = u16_at(subtable, 2)?;
+                subtables.push((kind, subtable.get(u32_at(subtable, 4)? as usize..)?));
+            } else {
+                subtables.push((kind, subtable));
+            }
+        }
+    }
+    Some(subtables)
+}
+
+fn coverage(subtable: &[u8], offset: usize) -> Option<Vec<u16>> {
+    let table = subtable.get(u16_at(subtable, offset)? as usize..)?;
+    let count = u16_at(table, 2)? as usize;
+    match u16_at(table, 0)? {
+        1 => (0..count).map(|i| u16_at(table, 4 + 2 * i)).collect(),
+        2 => {
+            let mut glyphs = Vec::new();
+            for i in 0..count {
+                let start = u16_at(table, 4 + 6 * i)?;
+                let end = u16_at(table, 6 + 6 * i)?;
+                glyphs.extend(start..=end);
+                // overlapping ranges can't cover more than every glyph id
+                // once without being broken
+                if glyphs.len() > 0x1_0000 {
+                    return None;
+                }
+            }
+            Some(glyphs)
+        }
+        _ => None,
+    }
+}
+
+fn gsub_outputs(kind: u16, subtable: &[u8], keep: &[bool], added: &mut Vec<u16>) -> Option<()> {
+    // contextual lookups only apply other lookups
+    if ![1, 2, 3, 4, 8].contains(&kind) {
+        return Some(());
+    }
+    let kept = |glyph: u16| keep.get(glyph as usize).copied().unwrap_or(false);
+    let covered = coverage(subtable, 2)?;
+    let array = |at: usize| -> Option<Vec<u16>> {
+        (0..u16_at(subtable, at)? as usize)
+            .map(|i| u16_at(subtable, at + 2 + 2 * i))
+            .collect()
+    };
+    match (kind, u16_at(subtable, 0)?) {
+        // single
+        (1, 1) => {
+            let delta = u16_at(subtable, 4)?;
+            added.extend(
+                covered
+                    .into_iter()
+                    .filter(|&g| kept(g))
+                    .map(|g| g.wrapping_add(delta)),
+            );
+        }
+        (1, 2) => {
+            let substitutes = array(4)?;
+            for (glyph, substitute) in covered.into_iter().zip(substitutes) {
+                if kept(glyph) {
+                    added.push(substitute);
+                }
+            }
+        }
+        // multiple and alternate
+        (2, 1) | (3, 1) => {
+            for (i, glyph) in covered.into_iter().enumerate() {
+                if kept(glyph) {
+                    let sequence = subtable.get(u16_at(subtable, 6 + 2 * i)? as usize..)?;
+                    for j in 0..u16_at(sequence, 0)? as usize {
+                        added.push(u16_at(sequence, 2 + 2 * j)?);
+                    }
+                }
+            }
+        }
+        // ligature
+        (4, 1) => {
+            for (i, glyph) in covered.into_iter().enumerate() {
+                if !kept(glyph) {
+                    continue;
+                }
+                let set = subtable.get(u16_at(subtable, 6 + 2 * i)? as usize..)?;
+                for j in 0..u16_at(set, 0)? as usize {
+                    let ligature = set.get(u16_at(set, 2 + 2 * j)? as usize..)?;
+                    let components = u16_at(ligature, 2)? as usize;
+                    let all_kept =
+                        (1..components).all(|k| u16_at(ligature, 2 + 2 * k).is_some_and(kept));
+                    if all_kept {
+                        added.push(u16_at(ligature, 0)?);
+                    }
+                }
+            }
+        }
+        // reverse chaining single
+        (8, 1) => {
+            let backtrack = u16_at(subtable, 4)? as usize;
+            let lookahead_at = 6 + 2 * backtrack;
+            let lookahead = u16_at(subtable, lookahead_at)? as usize;
+            let substitutes = array(lookahead_at + 2 + 2 * lookahead)?;
+            for (glyph, substitute) in covered.into_iter().zip(substitutes) {
+                if kept(glyph) {
+                    added.push(substitute);
+                }
+            }
+        }
+        _ => {}
+    }
+    Some(())
+}
+
+/// Empties the unused glyphs of a `glyf` table, keeping the components of
+/// composite glyphs, and returns it with a long `loca` and a matching
+/// `head`.
+fn subset_glyf(
+    glyf: &[u8],
+    loca: &[u8],
+    head: &[u8],
+    keep: &mut [bool],
+) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let long = i16_at(head, 50)? == 1;
+    let location = |i: usize| -> Option<usize> {
+        if long {
+            u32_at(loca, 4 * i).map(|offset| offset as usize)
+        } else {
+            u16_at(loca, 2 * i).map(|offset| offset as usize * 2)
+        }
+    };
+    let glyphs: Vec<Range<usize>> = (0..keep.len())
+        .map(|i| {
+            let start = location(i)?.min(glyf.len());
+            let end = location(i + 1)?.min(glyf.len()).max(start);
+            Some(start..end)
+        })
+        .collect::<Option<_>>()?;
+
+    let mut pending: Vec<usize> = (0..keep.len()).filter(|&i| keep[i]).collect();
+    while let Some(i) = pending.pop() {
+        for component in components(&glyf[glyphs[i].clone()]) {
+            if !keep.get(component).copied().unwrap_or(true) {
+                keep[component] = true;
+                pending.push(component);
+            }
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::new();
+    for (i, range) in glyphs.into_iter().enumerate() {
+        push_u32(&mut new_loca, new_glyf.len() as u32);
+        if keep[i] {
+            new_glyf.extend_from_slice(&glyf[range]);
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+        }
+    }
+    push_u32(&mut new_loca, new_glyf.len() as u32);
+
+    let mut head = head.to_vec();
+    head.get_mut(50..52)?.copy_from_slice(&1u16.to_be_bytes());
+    Some((new_glyf, new_loca, head))
+}
+
+// the glyphs a composite glyph is made of
+fn components(glyph: &[u8]) -> Vec<usize> {
+    const ARGS_ARE_WORDS: u16 = 0x0001;
+    const HAVE_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const HAVE_X_AND_Y_SCALE: u16 = 0x0040;
+    const HAVE_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut components = Vec::new();
+    if i16_at(glyph, 0).map_or(true, |contours| contours >= 0) {
+        return components;
+    }
+    let mut at = 10;
+    while let (Some(flags), Some(component)) = (u16_at(glyph, at), u16_at(glyph, at + 2)) {
+        components.push(component as usize);
+        at += 4 + if flags & ARGS_ARE_WORDS != 0 { 4 } else { 2 };
+        at += if flags & HAVE_SCALE != 0 {
+            2
+        } else if flags & HAVE_X_AND_Y_SCALE != 0 {
+            4
+        } else if flags & HAVE_TWO_BY_TWO != 0 {
+            8
+        } else {
+            0
+        };
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    components
+}
+
+/// The items of the CFF INDEX at `at`, and where it ends.
+fn cff_index(cff: &[u8], at: usize) -> Option<(Vec<Range<usize>>, usize)> {
+    let count = u16_at(cff, at)? as usize;
+    if count == 0 {
+        return Some((Vec::new(), at + 2));
+    }
+    let offset_size = *cff.get(at + 2)? as usize;
+    if !(1..=4).contains(&offset_size) {
+        return None;
+    }
+    let offset = |i: usize| -> Option<usize> {
+        let start = at + 3 + i * offset_size;
+        let bytes = cff.get(start..start + offset_size)?;
+        Some(bytes.iter().fold(0, |value, &b| value << 8 | b as usize))
+    };
+    // offsets count from the byte before the data
+    let data = at + 2 + (count + 1) * offset_size;
+    let items = (0..count)
+        .map(|i| Some(data + offset(i)?..data + offset(i + 1)?))
+        .collect::<Option<Vec<_>>>()?;
+    let end = data + offset(count)?;
+    let broken = |item: &Range<usize>| item.start > item.end || item.end > end;
+    if end > cff.len() || items.iter().any(broken) {
+        return None;
+    }
+    Some((items, end))
+}
+
+fn write_cff_index(out: &mut Vec<u8>, items: &[Vec<u8>]) {
+    push_u16(out, items.len() as u16);
+    if items.is_empty() {
+        return;
+    }
+    let total: usize = items.iter().map(Vec::len).sum::<usize>() + 1;
+    let offset_size = match total {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4,
+    };
+    out.push(offset_size as u8);
+    let mut offset = 1;
+    for item in items.iter().map(Vec::len).chain(Some(0)) {
+        out.extend_from_slice(&(offset as u32).to_be_bytes()[4 - offset_size..]);
+        offset += item;
+    }
+    for item in items {
+        out.extend_from_slice(item);
+    }
+}
+
+fn cff_index_size(items: &[Vec<u8>]) -> usize {
+    let mut out = Vec::new();
+    write_cff_index(&mut out, items);
+    out.len()
+}
+
+const CHARSET: u16 = 15;
+const ENCODING: u16 = 16;
+const CHAR_STRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const SUBRS: u16 = 19;
+const FD_ARRAY: u16 = 1236;
+const FD_SELECT: u16 = 1237;
+const OFFSET_OPERATORS: [u16; 6] = [
+    CHARSET,
+    ENCODING,
+    CHAR_STRINGS,
+    PRIVATE,
+    FD_ARRAY,
+    FD_SELECT,
+];
+
+/// A DICT entry: the raw bytes of its operands and their integer values.
+struct DictEntry {
+    operands: Vec<(Range<usize>, Option<i64>)>,
+    operator: u16,
+}
+
+fn parse_dict(dict: &[u8]) -> Option<Vec<DictEntry>> {
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    let mut at = 0;
+    while at < dict.len() {
+        let b0 = dict[at];
+        let byte = |i: usize| dict.get(at + i).map(|&b| b as i64);
+        let (length, value) = match b0 {
+            0..=21 => {
+                let operator = if b0 == 12 {
+                    1200 + byte(1)? as u16
+                } else {
+                    b0 as u16
+                };
+                entries.push(DictEntry {
+                    operands: std::mem::take(&mut operands),
+                    operator,
+                });
+                at += if b0 == 12 { 2 } else { 1 };
+                continue;
+            }
+            28 => (3, Some((byte(1)? << 8 | byte(2)?) as i16 as i64)),
+            29 => (
+                5,
+                Some((byte(1)? << 24 | byte(2)? << 16 | byte(3)? << 8 | byte(4)?) as i32 as i64),
+            ),
+            30 => {
+                // a real number, nibbles up to the 0xf terminator
+                let end = dict[at + 1..]
+                    .iter()
+                    .position(|&b| b & 0x0F == 0x0F || b >> 4 == 0x0F)?;
+                (end + 2, None)
+            }
+            32..=246 => (1, Some(b0 as i64 - 139)),
+            247..=250 => (2, Some((b0 as i64 - 247) * 256 + byte(1)? + 108)),
+            251..=254 => (2, Some(-(b0 as i64 - 251) * 256 - byte(1)? - 108)),
+            _ => return None,
+        };
+        if at + length > dict.len() {
+            return None;
+        }
+        operands.push((at..at + length, value));
+        at += length;
+    }
+    Some(entries)
+}
+
+/// Writes `entries`, with the operands of offset operators as five-byte
+/// integers mapped through `relocate`, so that the size doesn't depend on
+/// where things end up.
+fn write_dict(
+    dict: &[u8],
+    entries: &[DictEntry],
+    relocate: &dyn Fn(u16, i64) -> Option<i64>,
+) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    for entry in entries {
+        let offsets = OFFSET_OPERATORS.contains(&entry.operator);
+        for (i, (range, value)) in entry.operands.iter().enumerate() {
+            // the first operand of Private is its size
+            let is_offset = offsets && !(entry.operator == PRIVATE && i == 0);
+            if is_offset {
+                let value = relocate(entry.operator, (*value)?)?;
+                out.push(29);
+                out.extend_from_slice(&i32::try_from(value).ok()?.to_be_bytes());
+            } else {
+                out.extend_from_slice(&dict[range.clone()]);
+            }
+        }
+        if entry.operator >= 1200 {
+            out.push(12);
+            out.push((entry.operator - 1200) as u8);
+        } else {
+            out.push(entry.operator as u8);
+        }
+    }
+    Some(out)
+}
+
+fn dict_value(entries: &[DictEntry], operator: u16, operand: usize) -> Option<i64> {
+    let entry = entries.iter().find(|e| e.operator == operator)?;
+    entry.operands.get(operand)?.1
+}
+
+// an offset operand of a DICT, `None` if it's negative
+fn dict_offset(entries: &[DictEntry], operator: u16, operand: usize) -> Option<usize> {
+    usize::try_from(dict_value(entries, operator, operand)?).ok()
+}
+
+/// Replaces the charstrings of unused glyphs with `endchar` and prefixes
+/// the PostScript names in the Name INDEX with `prefix`. The CharStrings
+/// and FDArray indexes move to the end and everything else keeps its
+/// layout, so only the offsets in the top and font DICTs change.
+fn subset_cff(cff: &[u8], keep: &[bool], prefix: &str) -> Option<Vec<u8>> {
+    if *cff.first()? != 1 {
+        return None;
+    }
+    let header_size = *cff.get(2)? as usize;
+    let (names, names_end) = cff_index(cff, header_size)?;
+    let (top_dicts, top_end) = cff_index(cff, names_end)?;
+    let (_, strings_end) = cff_index(cff, top_end)?;
+    let (_, rest_start) = cff_index(cff, strings_end)?;
+
+    let names: Vec<Vec<u8>> = names
+        .into_iter()
+        .map(|range| [prefix.as_bytes(), b"+", &cff[range]].concat())
+        .collect();
+    let top_dict = &cff[top_dicts.first()?.clone()];
+    let top = parse_dict(top_dict)?;
+    let char_strings_at = dict_offset(&top, CHAR_STRINGS, 0)?;
+    let (char_strings, char_strings_end) = cff_index(cff, char_strings_at)?;
+    let char_strings: Vec<Vec<u8>> = char_strings
+        .into_iter()
+        .enumerate()
+        .map(|(i, range)| match keep.get(i) {
+            Some(true) => cff[range].to_vec(),
+            _ => vec![14],
+        })
+        .collect();
+
+    let mut font_dicts = Vec::new();
+    let mut font_dicts_range = None;
+    if let Some(at) = dict_value(&top, FD_ARRAY, 0) {
+        let at = usize::try_from(at).ok()?;
+        let (dicts, end) = cff_index(cff, at)?;
+        for range in dicts {
+            let dict = cff[range].to_vec();
+            let entries = parse_dict(&dict)?;
+            font_dicts.push((dict, entries));
+        }
+        font_dicts_range = Some(at..end);
+    }
+    let mut removed: Vec<Range<usize>> = std::iter::once(char_strings_at..char_strings_end)
+        .chain(font_dicts_range)
+        .collect();
+    removed.sort_by_key(|range| range.start);
+
+    // the parts of the table after the global subroutines that are copied
+    let mut kept = Vec::new();
+    let mut start = rest_start;
+    for range in &removed {
+        if range.start < start {
+            return None;
+        }
+        kept.push(start..range.start);
+        start = range.end;
+    }
+    kept.push(start..cff.len());
+
+    let placeholder = |_: u16, _: i64| Some(0);
+    let top_size = cff_index_size(&[write_dict(top_dict, &top, &placeholder)?]);
+    let font_dicts_size = cff_index_size(
+        &font_dicts
+            .iter()
+            .map(|(dict, entries)| write_dict(dict, entries, &placeholder))
+            .collect::<Option<Vec<_>>>()?,
+    );
+
+    let mut position = header_size + cff_index_size(&names) + top_size + (rest_start - top_end);
+    let mut pieces = Vec::new();
+    for range in &kept {
+        pieces.push((range.clone(), position));
+        position += range.len();
+    }
+    let new_char_strings_at = position;
+    let new_font_dicts_at = new_char_strings_at + cff_index_size(&char_strings);
+    if position + font_dicts_size > i32::MAX as usize {
+        return None;
+    }
+
+    let map = |offset: usize| -> Option<usize> {
+        pieces
+            .iter()
+            .find(|(range, _)| range.contains(&offset) || range.end == offset)
+            .map(|(range, new)| new + offset - range.start)
+    };
+    let relocate = |operator: u16, offset: i64| -> Option<i64> {
+        match operator {
+            // predefined charsets and encodings
+            CHARSET if offset <= 2 => Some(offset),
+            ENCODING if offset <= 1 => Some(offset),
+            CHAR_STRINGS => Some(new_char_strings_at as i64),
+            FD_ARRAY => Some(new_font_dicts_at as i64),
+            _ => map(offset as usize).map(|offset| offset as i64),
+        }
+    };
+
+    // local subroutines are found relative to their Private DICT, so they
+    // have to move together
+    let private_dicts = std::iter::once(&top).chain(font_dicts.iter().map(|(_, entries)| entries));
+    for entries in private_dicts {
+        if let (Some(size), Some(at)) = (
+            dict_value(entries, PRIVATE, 0),
+            dict_value(entries, PRIVATE, 1),
+        ) {
+            let at = usize::try_from(at).ok()?;
+            let size = usize::try_from(size).ok()?;
+            let private = parse_dict(cff.get(at..at.checked_add(size)?)?)?;
+            if let Some(subrs) = dict_offset(&private, SUBRS, 0) {
+                let subrs = at.checked_add(subrs)?;
+                if map(subrs)? as i64 - map(at)? as i64 != (subrs - at) as i64 {
+                    return None;
+                }
+            }
+        }
+    }
+
+    let mut out = cff[..header_size].to_vec();
+    write_cff_index(&mut out, &names);
+    write_cff_index(&mut out, &[write_dict(top_dict, &top, &relocate)?]);
+    out.extend_from_slice(&cff[top_end..rest_start]);
+    for (range, _) in &pieces {
+        out.extend_from_slice(&cff[range.clone()]);
+    }
+    write_cff_index(&mut out, &char_strings);
+    if !font_dicts.is_empty() {
+        let dicts = font_dicts
+            .iter()
+            .map(|(dict, entries)| write_dict(dict, entries, &relocate))
+            .collect::<Option<Vec<_>>>()?;
+        write_cff_index(&mut out, &dicts);
+    }
+    Some(out)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Writes a font from tables sorted by tag, fixing `head`'s checksum
+/// adjustment.
+fn write_font(version: u32, tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    // in 32 bits, since 16 times the number of tables can overflow 16
+    let count = tables.len() as u32;
+    let entry_selector = 31 - count.leading_zeros();
+    let search_range = 16 << entry_selector;
+    let mut out = Vec::new();
+    push_u32(&mut out, version);
+    push_u16(&mut out, count as u16);
+    push_u16(&mut out, search_range as u16);
+    push_u16(&mut out, entry_selector as u16);
+    push_u16(&mut out, (count * 16 - search_range) as u16);
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut head = None;
+    for (tag, data) in tables {
+        let mut data = data.clone();
+        if tag == b"head" && data.len() >= 12 {
+            data[8..12].copy_from_slice(&[0; 4]);
+            head = Some(offset);
+        }
+        out.extend_from_slice(tag);
+        push_u32(&mut out, checksum(&data));
+        push_u32(&mut out, offset as u32);
+        push_u32(&mut out, data.len() as u32);
+        offset += (data.len() + 3) & !3;
+    }
+    for (tag, data) in tables {
+        let start = out.len();
+        out.extend_from_slice(data);
+        if tag == b"head" && data.len() >= 12 {
+            out[start + 8..start + 12].copy_from_slice(&[0; 4]);
+        }
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+    }
+    if let Some(head) = head {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
@@ -0,0 +1,646 @@
+//! Override tags in event text.
+//!
+//! ```
+//! use libass::tags::{EventText, Tag};
+//!
+//! let mut text = EventText::parse(r"{\pos(320,50)\fad(200,200)\c&H00FFFF&}Hello");
+//! for tag in text.tags_mut() {
+//!     if let Tag::Pos { y, .. } = tag {
+//!         *y += 10.0;
+//!     }
+//! }
+//! assert_eq!(
+//!     text.to_string(),
+//!     r"{\pos(320,60)\fad(200,200)\c&H00FFFF&}Hello"
+//! );
+//! ```
+
+use std::fmt::{self, Display};
+
+use crate::script::Color;
+
+// Override tag names, to tell `\fnArial` and `\rDefault` from their
+// arguments. Unknown tags are named by their leading letters.
+const TAG_NAMES: &[&str] = &[
+    "1a", "1c", "2a", "2c", "3a", "3c", "4a", "4c", "a", "alpha", "an", "b", "be", "blur", "bord",
+    "c", "clip", "fad", "fade", "fax", "fay", "fe", "fn", "fr", "frx", "fry", "frz", "fs", "fsc",
+    "fscx", "fscy", "fsp", "i", "iclip", "K", "k", "kf", "ko", "kt", "move", "org", "p", "pbo",
+    "pos", "q", "r", "s", "shad", "t", "u", "xbord", "xshad", "ybord", "yshad",
+];
+
+/// Event text split into plain text and override blocks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventText {
+    pub parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    /// Text outside of override blocks, with escapes like `\N` left as is.
+    Text(String),
+    /// The contents of a `{...}` block.
+    Block(Vec<Item>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Tag(Override),
+    /// Anything in a block that isn't a tag, usually a comment.
+    Comment(String),
+}
+
+/// A tag along with the text it was parsed from, so that it is written back
+/// unchanged unless it was edited.
+#[derive(Debug, Clone)]
+pub struct Override {
+    pub tag: Tag,
+    source: Option<(Tag, String)>,
+}
+
+impl Override {
+    pub fn new(tag: Tag) -> Self {
+        Override { tag, source: None }
+    }
+}
+
+impl From<Tag> for Override {
+    fn from(tag: Tag) -> Self {
+        Override::new(tag)
+    }
+}
+
+impl PartialEq for Override {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+    }
+}
+
+/// An override tag. Times are in milliseconds relative to the event start,
+/// `None` means a tag without arguments, which resets to the style.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    /// `\pos(x,y)`
+    Pos { x: f64, y: f64 },
+    /// `\move(x1,y1,x2,y2[,t1,t2])`
+    Move {
+        from: (f64, f64),
+        to: (f64, f64),
+        times: Option<(i64, i64)>,
+    },
+    /// `\fad(in,out)`
+    Fad { fade_in: i64, fade_out: i64 },
+    /// `\fade(a1,a2,a3,t1,t2,t3,t4)`
+    Fade { alpha: [i32; 3], times: [i64; 4] },
+    /// `\c` or `\1c` to `\4c`, with `a` unused.
+    Color { index: u8, color: Option<Color> },
+    /// `\alpha` when `index` is `None`, `\1a` to `\4a` otherwise.
+    Alpha {
+        index: Option<u8>,
+        alpha: Option<u8>,
+    },
+    /// `\fn`, empty to reset.
+    FontName(String),
+    /// `\fs`
+    FontSize(Option<f64>),
+    /// `\b`, 0, 1 or a font weight.
+    Bold(Option<i32>),
+    /// `\i`
+    Italic(Option<bool>),
+    /// `\u`
+    Underline(Option<bool>),
+    /// `\s`
+    StrikeOut(Option<bool>),
+    /// `\bord`
+    Border(Option<f64>),
+    /// `\shad`
+    Shadow(Option<f64>),
+    /// `\blur`
+    Blur(Option<f64>),
+    /// `\be`
+    BlurEdges(Option<f64>),
+    /// `\t([t1,t2,][accel,]tags)`
+    Transform {
+        times: Option<(i64, i64)>,
+        accel: Option<f64>,
+        tags: Vec<Override>,
+    },
+    /// `\clip` or `\iclip`.
+    Clip { inverse: bool, shape: ClipShape },
+    /// `\p`, 0 to end drawing mode.
+    Drawing(i32),
+    /// `\k` family, with the duration in centiseconds. A bare `\k`, `\kf`
+    /// or `\ko` lasts 100.
+    Karaoke { kind: KaraokeKind, duration: f64 },
+    /// `\r`, with an optional style name.
+    Reset(Option<String>),
+    /// `\an`
+    Alignment(i32),
+    /// Any other tag, with everything after its name as `args`.
+    Unknown { name: String, args: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipShape {
+    /// `(x1,y1,x2,y2)`
+    Rect { x1: f64, y1: f64, x2: f64, y2: f64 },
+    /// `([scale,]commands)`
+    Vector {
+        scale: Option<i32>,
+        commands: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum KaraokeKind {
+    /// `\k`, highlighted all at once.
+    Instant,
+    /// `\kf` or `\K`, filled from left to right. An edited `\K` is
+    /// written back as `\K`.
+    Fill,
+    /// `\ko`, outline highlighted at once.
+    Outline,
+    /// `\kt`, sets the time at which the next syllable starts.
+    Time,
+}
+
+impl EventText {
+    /// Parses event text. Text that can't be parsed is kept as is, e.g. an
+    /// unclosed `{` and an escaped `\{` as text and malformed tags as
+    /// [`Tag::Unknown`].
+    pub fn parse(text: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let block =
+                find_block(rest).and_then(|open| Some((open, open + rest[open..].find('}')?)));
+            let (open, close) = match block {
+                Some(block) => block,
+                None => {
+                    parts.push(Part::Text(rest.to_string()));
+                    break;
+                }
+            };
+            if open > 0 {
+                parts.push(Part::Text(rest[..open].to_string()));
+            }
+            parts.push(Part::Block(parse_items(&rest[open + 1..close])));
+            rest = &rest[close + 1..];
+        }
+        EventText { parts }
+    }
+
+    /// The tags of all blocks in order, not including those inside `\t`.
+    pub fn tags(&self) -> impl Iterator<Item = &Tag> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Block(items) => Some(items),
+                Part::Text(_) => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                Item::Tag(o) => Some(&o.tag),
+                Item::Comment(_) => None,
+            })
+    }
+
+    pub fn tags_mut(&mut self) -> impl Iterator<Item = &mut Tag> {
+        self.parts
+            .iter_mut()
+            .filter_map(|part| match part {
+                Part::Block(items) => Some(items),
+                Part::Text(_) => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                Item::Tag(o) => Some(&mut o.tag),
+                Item::Comment(_) => None,
+            })
+    }
+
+    /// The visible text, without override blocks or drawings. `\N` becomes a
+    /// line break, `\n` a space, `\h` a no-break space and `\{` and `\}`
+    /// braces.
+    pub fn plain_text(&self) -> String {
+        let mut out = String::new();
+        let mut drawing = false;
+        for part in &self.parts {
+            match part {
+                Part::Text(text) if !drawing => push_plain_text(&mut out, text),
+                Part::Text(_) => {}
+                Part::Block(items) => {
+                    for item in items {
+                        if let Item::Tag(Override {
+                            tag: Tag::Drawing(scale),
+                            ..
+                        }) = item
+                        {
+                            drawing = *scale > 0;
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+pub(crate) fn push_plain_text(out: &mut String, text: &str) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some('N')) => '\n',
+            ('\\', Some('n')) => ' ',
+            ('\\', Some('h')) => '\u{a0}',
+            ('\\', Some('{')) => '{',
+            ('\\', Some('}')) => '}',
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        out.push(escaped);
+    }
+}
+
+// The start of the first override block. As in libass, `\{` is a literal
+// brace rather than the start of a block.
+pub(crate) fn find_block(text: &str) -> Option<usize> {
+    text.match_indices('{')
+        .map(|(i, _)| i)
+        .find(|&i| !text[..i].ends_with('\\'))
+}
+
+impl Display for EventText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => f.write_str(text)?,
+                Part::Block(items) => {
+                    f.write_str("{")?;
+                    for item in items {
+                        match item {
+                            Item::Tag(o) => write!(f, "{}", o)?,
+                            Item::Comment(comment) => f.write_str(comment)?,
+                        }
+                    }
+                    f.write_str("}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.source, &self.tag) {
+            (Some((parsed, source)), _) if *parsed == self.tag => f.write_str(source),
+            // `\K` is parsed as `\kf`, but keeps its spelling when edited
+            (Some((_, source)), Tag::Karaoke { kind, duration })
+                if *kind == KaraokeKind::Fill && source.starts_with("\\K") =>
+            {
+                write!(f, "\\K{}", duration)
+            }
+            _ => write!(f, "{}", self.tag),
+        }
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn opt<T: Display>(value: &Option<T>) -> String {
+            value.as_ref().map_or_else(String::new, T::to_string)
+        }
+        fn flag(value: &Option<bool>) -> String {
+            opt(&value.map(i32::from))
+        }
+
+        match self {
+            Tag::Pos { x, y } => write!(f, "\\pos({},{})", x, y),
+            Tag::Move { from, to, times } => {
+                write!(f, "\\move({},{},{},{}", from.0, from.1, to.0, to.1)?;
+                if let Some((t1, t2)) = times {
+                    write!(f, ",{},{}", t1, t2)?;
+                }
+                f.write_str(")")
+            }
+            Tag::Fad { fade_in, fade_out } => write!(f, "\\fad({},{})", fade_in, fade_out),
+            Tag::Fade { alpha, times } => write!(
+                f,
+                "\\fade({},{},{},{},{},{},{})",
+                alpha[0], alpha[1], alpha[2], times[0], times[1], times[2], times[3]
+            ),
+            Tag::Color { index, color } => {
+                match index {
+                    1 => f.write_str("\\c")?,
+                    _ => write!(f, "\\{}c", index)?,
+                }
+                match color {
+                    Some(color) => write!(f, "&H{:06X}&", color.to_abgr() & 0xff_ffff),
+                    None => Ok(()),
+                }
+            }
+            Tag::Alpha { index, alpha } => {
+                match index {
+                    Some(index) => write!(f, "\\{}a", index)?,
+                    None => f.write_str("\\alpha")?,
+                }
+                match alpha {
+                    Some(alpha) => write!(f, "&H{:02X}&", alpha),
+                    None => Ok(()),
+                }
+            }
+            Tag::FontName(name) => write!(f, "\\fn{}", name),
+            Tag::FontSize(size) => write!(f, "\\fs{}", opt(size)),
+            Tag::Bold(weight) => write!(f, "\\b{}", opt(weight)),
+            Tag::Italic(on) => write!(f, "\\i{}", flag(on)),
+            Tag::Underline(on) => write!(f, "\\u{}", flag(on)),
+            Tag::StrikeOut(on) => write!(f, "\\s{}", flag(on)),
+            Tag::Border(size) => write!(f, "\\bord{}", opt(size)),
+            Tag::Shadow(size) => write!(f, "\\shad{}", opt(size)),
+            Tag::Blur(strength) => write!(f, "\\blur{}", opt(strength)),
+            Tag::BlurEdges(strength) => write!(f, "\\be{}", opt(strength)),
+            Tag::Transform { times, accel, tags } => {
+                f.write_str("\\t(")?;
+                if let Some((t1, t2)) = times {
+                    write!(f, "{},{},", t1, t2)?;
+                }
+                if let Some(accel) = accel {
+                    write!(f, "{},", accel)?;
+                }
+                for tag in tags {
+                    write!(f, "{}", tag)?;
+                }
+                f.write_str(")")
+            }
+            Tag::Clip { inverse, shape } => {
+                f.write_str(if *inverse { "\\iclip(" } else { "\\clip(" })?;
+                match shape {
+                    ClipShape::Rect { x1, y1, x2, y2 } => write!(f, "{},{},{},{}", x1, y1, x2, y2)?,
+                    ClipShape::Vector { scale, commands } => {
+                        if let Some(scale) = scale {
+                            write!(f, "{},", scale)?;
+                        }
+                        f.write_str(commands)?;
+                    }
+                }
+                f.write_str(")")
+            }
+            Tag::Drawing(scale) => write!(f, "\\p{}", scale),
+            Tag::Karaoke { kind, duration } => {
+                let name = match kind {
+                    KaraokeKind::Instant => "k",
+                    KaraokeKind::Fill => "kf",
+                    KaraokeKind::Outline => "ko",
+                    KaraokeKind::Time => "kt",
+                };
+                write!(f, "\\{}{}", name, duration)
+            }
+            Tag::Reset(style) => write!(f, "\\r{}", opt(style)),
+            Tag::Alignment(alignment) => write!(f, "\\an{}", alignment),
+            Tag::Unknown { name, args } => write!(f, "\\{}{}", name, args),
+        }
+    }
+}
+
+// A tag as found in a block: its name, its arguments without parentheses
+// and its whole text.
+pub(crate) struct RawTag<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) args: &'a str,
+    pub(crate) source: &'a str,
+}
+
+// Splits the contents of an override block into tags and the text between
+// them.
+pub(crate) fn tokenize(block: &str) -> Vec<Result<RawTag<'_>, &str>> {
+    let mut tokens = Vec::new();
+    let mut rest = block;
+    while !rest.is_empty() {
+        let slash = match rest.find('\\') {
+            Some(slash) => slash,
+            None => {
+                tokens.push(Err(rest));
+                break;
+            }
+        };
+        if slash > 0 {
+            tokens.push(Err(&rest[..slash]));
+        }
+
+        let tag = &rest[slash + 1..];
+        let name_len = TAG_NAMES
+            .iter()
+            .filter(|name| tag.starts_with(*name))
+            .map(|name| name.len())
+            .max()
+            .unwrap_or_else(|| {
+                tag.char_indices()
+                    .find(|&(i, c)| !(c.is_ascii_alphabetic() || (i == 0 && c.is_ascii_digit())))
+                    .map_or(tag.len(), |(i, _)| i)
+            });
+        let (name, after) = tag.split_at(name_len);
+        let spaces = after.len() - after.trim_start().len();
+        let after = &after[spaces..];
+        let (args, len) = if let Some(inner) = after.strip_prefix('(') {
+            let mut depth = 1;
+            let close = inner.char_indices().find(|&(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            });
+            match close {
+                Some((close, _)) => (&inner[..close], close + 2),
+                None => (inner, after.len()),
+            }
+        } else {
+            let end = after.find('\\').unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        let end = slash + 1 + name_len + spaces + len;
+        tokens.push(Ok(RawTag {
+            name,
+            args,
+            source: &rest[slash..end],
+        }));
+        rest = &rest[end..];
+    }
+    tokens
+}
+
+// Splits tag arguments on top-level commas, leaving nested tags in `\t` alone.
+pub(crate) fn split_args(args: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut from = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(&args[from..i]);
+                from = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args.is_empty() {
+        out.push(&args[from..]);
+    }
+    out
+}
+
+fn parse_items(block: &str) -> Vec<Item> {
+    tokenize(block)
+        .into_iter()
+        .map(|token| match token {
+            Ok(raw) => Item::Tag(parse_override(&raw)),
+            Err(comment) => Item::Comment(comment.to_string()),
+        })
+        .collect()
+}
+
+fn parse_override(raw: &RawTag) -> Override {
+    let tag = parse_tag(raw.name, raw.args).unwrap_or_else(|| Tag::Unknown {
+        name: raw.name.to_string(),
+        args: raw.source[1 + raw.name.len()..].to_string(),
+    });
+    Override {
+        source: Some((tag.clone(), raw.source.to_string())),
+        tag,
+    }
+}
+
+fn parse_tag(name: &str, args: &str) -> Option<Tag> {
+    let list = split_args(args);
+    let float = |i: usize| list.get(i)?.trim().parse::<f64>().ok();
+    let int = |i: usize| list.get(i)?.trim().parse::<f64>().ok().map(|v| v as i64);
+    let arg = args.trim();
+    let optional = || match arg {
+        "" => Some(None),
+        _ => arg.parse::<f64>().ok().map(Some),
+    };
+    let switch = || match arg {
+        "" => Some(None),
+        _ => arg.parse::<i32>().ok().map(|v| Some(v != 0)),
+    };
+
+    let tag = match name {
+        "pos" if list.len() == 2 => Tag::Pos {
+            x: float(0)?,
+            y: float(1)?,
+        },
+        "move" if list.len() == 4 || list.len() == 6 => Tag::Move {
+            from: (float(0)?, float(1)?),
+            to: (float(2)?, float(3)?),
+            times: match list.len() {
+                6 => Some((int(4)?, int(5)?)),
+                _ => None,
+            },
+        },
+        "fad" if list.len() == 2 => Tag::Fad {
+            fade_in: int(0)?,
+            fade_out: int(1)?,
+        },
+        "fade" if list.len() == 7 => Tag::Fade {
+            alpha: [int(0)? as i32, int(1)? as i32, int(2)? as i32],
+            times: [int(3)?, int(4)?, int(5)?, int(6)?],
+        },
+        "c" | "1c" | "2c" | "3c" | "4c" => Tag::Color {
+            index: if name == "c" {
+                1
+            } else {
+                name.as_bytes()[0] - b'0'
+            },
+            color: match arg {
+                "" => None,
+                _ => Some(Color::parse(arg)?),
+            },
+        },
+        "alpha" | "1a" | "2a" | "3a" | "4a" => Tag::Alpha {
+            index: if name == "alpha" {
+                None
+            } else {
+                Some(name.as_bytes()[0] - b'0')
+            },
+            alpha: match arg {
+                "" => None,
+                _ => Some(Color::parse(arg)?.to_abgr() as u8),
+            },
+        },
+        "fn" => Tag::FontName(arg.to_string()),
+        "fs" => Tag::FontSize(optional()?),
+        "b" => Tag::Bold(match arg {
+            "" => None,
+            _ => Some(arg.parse().ok()?),
+        }),
+        "i" => Tag::Italic(switch()?),
+        "u" => Tag::Underline(switch()?),
+        "s" => Tag::StrikeOut(switch()?),
+        "bord" => Tag::Border(optional()?),
+        "shad" => Tag::Shadow(optional()?),
+        "blur" => Tag::Blur(optional()?),
+        "be" => Tag::BlurEdges(optional()?),
+        "t" if !list.is_empty() => {
+            let (times, accel) = match list.len() {
+                1 => (None, None),
+                2 => (None, Some(float(0)?)),
+                3 => (Some((int(0)?, int(1)?)), None),
+                4 => (Some((int(0)?, int(1)?)), Some(float(2)?)),
+                _ => return None,
+            };
+            let tags = tokenize(list[list.len() - 1])
+                .into_iter()
+                .filter_map(|token| token.ok())
+                .map(|raw| parse_override(&raw))
+                .collect();
+            Tag::Transform { times, accel, tags }
+        }
+        "clip" | "iclip" => Tag::Clip {
+            inverse: name == "iclip",
+            shape: match list.len() {
+                4 => ClipShape::Rect {
+                    x1: float(0)?,
+                    y1: float(1)?,
+                    x2: float(2)?,
+                    y2: float(3)?,
+                },
+                1 => ClipShape::Vector {
+                    scale: None,
+                    commands: list[0].to_string(),
+                },
+                2 => ClipShape::Vector {
+                    scale: Some(list[0].trim().parse().ok()?),
+                    commands: list[1].to_string(),
+                },
+                _ => return None,
+            },
+        },
+        "p" => Tag::Drawing(arg.parse().ok()?),
+        "k" | "K" | "kf" | "ko" | "kt" => Tag::Karaoke {
+            kind: match name {
+                "k" => KaraokeKind::Instant,
+                "K" | "kf" => KaraokeKind::Fill,
+                "ko" => KaraokeKind::Outline,
+                _ => KaraokeKind::Time,
+            },
+            // as in libass, only `\kt` needs its argument
+            duration: match arg {
+                "" if name != "kt" => 100.0,
+                _ => arg.parse().ok()?,
+            },
+        },
+        "r" => Tag::Reset(match arg {
+            "" => None,
+            _ => Some(arg.to_string()),
+        }),
+        "an" => Tag::Alignment(arg.parse().ok()?),
+        _ => return None,
+    };
+    Some(tag)
+}
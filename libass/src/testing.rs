@@ -0,0 +1,275 @@
+//! Golden-image testing for subtitle rendering.
+//!
+//! The harness renders with a [`Library`] that only knows the fonts it was
+//! given and a [`Renderer`] that never asks the system for fonts, so output
+//! does not depend on the machine running the tests.
+//!
+//! ```no_run
+//! use libass::testing::{hermetic_library, Harness};
+//!
+//! let script = std::fs::read_to_string("tests/scripts/basic.ass").unwrap();
+//! let font = std::fs::read("tests/fonts/DejaVuSans.ttf").unwrap();
+//! let library = hermetic_library(&[("DejaVuSans.ttf", &font)]).unwrap();
+//! let mut harness = Harness::new(&library, 640, 360, "DejaVu Sans").unwrap();
+//! harness.set_tolerance(2);
+//! harness.assert_golden(&script, 1000, "tests/golden/basic-1000.png");
+//! ```
+//!
+//! Set `LIBASS_BLESS=1` to write the rendered frames as the new golden files
+//! instead of comparing against them.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::image::Layer;
+use crate::library::{DefaultFontProvider, Library};
+use crate::renderer::{Hinting, Renderer};
+use crate::Result;
+
+/// Creates a library that only uses the given `(name, data)` fonts.
+pub fn hermetic_library(fonts: &[(&str, &[u8])]) -> Result<Library> {
+    let library = Library::new()?;
+    library.set_extract_fonts(false);
+    for (name, data) in fonts {
+        library.add_font(name, data);
+    }
+    Ok(library)
+}
+
+pub struct Harness<'library> {
+    renderer: Renderer<'library>,
+    library: &'library Library,
+    width: i32,
+    height: i32,
+    tolerance: u8,
+}
+
+impl<'library> Harness<'library> {
+    /// Creates a harness rendering `width`x`height` frames, using
+    /// `default_family` for text whose font isn't available.
+    pub fn new(
+        library: &'library Library,
+        width: i32,
+        height: i32,
+        default_family: &str,
+    ) -> Result<Self> {
+        let mut renderer = library.new_renderer()?;
+        renderer.set_frame_size(width, height);
+        renderer.set_storage_size(width, height);
+        renderer.set_hinting(Hinting::None);
+        renderer.set_fonts(None, default_family, DefaultFontProvider::None, None, false);
+        Ok(Harness {
+            renderer,
+            library,
+            width,
+            height,
+            tolerance: 0,
+        })
+    }
+
+    /// Sets the largest per-channel difference that still counts as equal.
+    pub fn set_tolerance(&mut self, tolerance: u8) {
+        self.tolerance = tolerance;
+    }
+
+    pub fn renderer(&mut self) -> &mut Renderer<'library> {
+        &mut self.renderer
+    }
+
+    /// Renders `script` at `now` milliseconds onto an opaque black frame.
+    pub fn render(&mut self, script: &str, now: i64) -> Result<Frame> {
+        let track = self
+            .library
+            .new_track_from_memory(script.as_bytes(), "UTF-8")?;
+        let mut frame = Frame::new(self.width as u32, self.height as u32);
+        if let (Some(image), _) = self.renderer.render_frame(&track, now) {
+            for layer in image {
+                frame.blend(&layer);
+            }
+        }
+        Ok(frame)
+    }
+
+    /// Renders `script` at `now` and compares it with the PNG at `golden`.
+    ///
+    /// On mismatch, writes the rendered frame next to the golden file as
+    /// `<name>.actual.png` and a diff as `<name>.diff.png`, then panics.
+    pub fn assert_golden(&mut self, script: &str, now: i64, golden: impl AsRef<Path>) {
+        let golden = golden.as_ref();
+        let actual = self.render(script, now).expect("failed to render script");
+
+        if env::var_os("LIBASS_BLESS").is_some() {
+            actual
+                .write_png(golden)
+                .unwrap_or_else(|e| panic!("failed to write {}: {}", golden.display(), e));
+            return;
+        }
+
+        let expected = Frame::read_png(golden)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", golden.display(), e));
+        let mismatch = match actual.compare(&expected, self.tolerance) {
+            Some(mismatch) => mismatch,
+            None => return,
+        };
+
+        let actual_path = sibling(golden, "actual");
+        let diff_path = sibling(golden, "diff");
+        let _ = actual.write_png(&actual_path);
+        let _ = mismatch.diff.write_png(&diff_path);
+        panic!(
+            "{} pixels differ from {} (max difference {}, tolerance {}), see {}",
+            mismatch.pixels,
+            golden.display(),
+            mismatch.max_difference,
+            self.tolerance,
+            diff_path.display(),
+        );
+    }
+}
+
+// foo/bar.png -> foo/bar.<suffix>.png
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+/// An 8-bit RGBA image.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// The result of a failed [`Frame::compare`].
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    /// Number of pixels differing by more than the tolerance.
+    pub pixels: usize,
+    pub max_difference: u8,
+    /// The actual frame dimmed, with differing pixels in red.
+    pub diff: Frame,
+}
+
+impl Frame {
+    /// Creates an opaque black frame.
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut data = vec![0; width as usize * height as usize * 4];
+        for pixel in data.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+        Frame {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Alpha-blends a rendered layer onto the frame.
+    pub fn blend(&mut self, layer: &Layer) {
+        // RGBA order, with alpha meaning transparency
+        let color = layer.color.to_be_bytes();
+        let opacity = 255 - color[3] as u32;
+
+        for y in 0..layer.height.max(0) {
+            let dst_y = layer.y + y;
+            if dst_y < 0 || dst_y as u32 >= self.height {
+                continue;
+            }
+            for x in 0..layer.width.max(0) {
+                let dst_x = layer.x + x;
+                if dst_x < 0 || dst_x as u32 >= self.width {
+                    continue;
+                }
+                let k = layer.bitmap[(y * layer.width + x) as usize] as u32 * opacity / 255;
+                let dst = (dst_y as usize * self.width as usize + dst_x as usize) * 4;
+                for (out, &c) in self.data[dst..dst + 3].iter_mut().zip(&color[..3]) {
+                    *out = ((k * c as u32 + (255 - k) * *out as u32) / 255) as u8;
+                }
+            }
+        }
+    }
+
+    /// Compares two frames channel by channel, returning `None` if no
+    /// channel differs by more than `tolerance`.
+    pub fn compare(&self, expected: &Frame, tolerance: u8) -> Option<Mismatch> {
+        let mut diff = Frame::new(self.width, self.height);
+        if (self.width, self.height) != (expected.width, expected.height) {
+            return Some(Mismatch {
+                pixels: self.data.len().max(expected.data.len()) / 4,
+                max_difference: 255,
+                diff,
+            });
+        }
+
+        let mut pixels = 0;
+        let mut max_difference = 0;
+        let pairs = self.data.chunks_exact(4).zip(expected.data.chunks_exact(4));
+        for ((actual, expected), out) in pairs.zip(diff.data.chunks_exact_mut(4)) {
+            let difference = actual
+                .iter()
+                .zip(expected)
+                .map(|(&a, &b)| (a as i16 - b as i16).unsigned_abs() as u8)
+                .max()
+                .unwrap_or(0);
+            max_difference = max_difference.max(difference);
+            if difference > tolerance {
+                pixels += 1;
+                out[..3].copy_from_slice(&[255, 0, 0]);
+            } else {
+                for (out, &a) in out[..3].iter_mut().zip(actual) {
+                    *out = a / 4;
+                }
+            }
+        }
+
+        if pixels == 0 {
+            None
+        } else {
+            Some(Mismatch {
+                pixels,
+                max_difference,
+                diff,
+            })
+        }
+    }
+
+    pub fn read_png(path: impl AsRef<Path>) -> io::Result<Frame> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf)?;
+
+        let data = match (info.color_type, info.bit_depth) {
+            (png::ColorType::RGBA, png::BitDepth::Eight) => buf,
+            (png::ColorType::RGB, png::BitDepth::Eight) => buf
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            (color_type, bit_depth) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported PNG format {:?} {:?}", color_type, bit_depth),
+                ))
+            }
+        };
+
+        Ok(Frame {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+
+    pub fn write_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use std::ops::Range;
+
+use crate::timeline::Timeline;
+use crate::{Error, Result};
+
+/// Maps frame numbers to timestamps, for a constant frame rate or a
+/// Matroska timecode file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timebase {
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    /// `num / den` frames per second.
+    Constant { num: i64, den: i64 },
+    /// Timestamps of the first frames in milliseconds, and the frame
+    /// duration after them.
+    Timecodes { times: Vec<f64>, tail: f64 },
+    /// Constant rates by range of frames, sorted, starting at frame 0. The
+    /// first one continues before it and the last one after it.
+    Ranges(Vec<Segment>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    frame: i64,
+    /// Timestamp of `frame` in milliseconds.
+    time: f64,
+    fps: f64,
+}
+
+impl Segment {
+    fn frame_time(&self, frame: i64) -> f64 {
+        self.time + (frame - self.frame) as f64 * 1000.0 / self.fps
+    }
+}
+
+impl Timebase {
+    /// A constant `num / den` frames per second, e.g. `24000 / 1001`.
+    pub fn constant(num: u32, den: u32) -> Result<Self> {
+        if num == 0 || den == 0 {
+            return Err(Error);
+        }
+        Ok(Timebase {
+            kind: Kind::Constant {
+                num: num as i64,
+                den: den as i64,
+            },
+        })
+    }
+
+    /// Frame timestamps in milliseconds. Frames after the last one continue
+    /// at the rate of the last two.
+    pub fn from_timestamps(times: Vec<f64>) -> Result<Self> {
+        if times.len() < 2 || !times.windows(2).all(|w| w[1] > w[0]) {
+            return Err(Error);
+        }
+        let tail = times[times.len() - 1] - times[times.len() - 2];
+        Ok(Timebase {
+            kind: Kind::Timecodes { times, tail },
+        })
+    }
+
+    /// Parses a Matroska timecode file in the v1 or v2 format, as written by
+    /// mkvextract.
+    pub fn parse_timecodes(data: &str) -> Result<Self> {
+        let mut lines = data.lines().map(str::trim).filter(|line| !line.is_empty());
+        let header = lines.next().ok_or(Error)?.to_ascii_lowercase();
+        let lines = lines.filter(|line| !line.starts_with('#'));
+        if header.starts_with("# timecode format v2") {
+            let times = lines
+                .map(|line| line.parse::<f64>().map_err(|_| Error))
+                .collect::<Result<Vec<_>>>()?;
+            Timebase::from_timestamps(times)
+        } else if header.starts_with("# timecode format v1") {
+            parse_v1(lines)
+        } else {
+            Err(Error)
+        }
+    }
+
+    /// The exact timestamp of `frame` in milliseconds.
+    pub fn frame_time(&self, frame: i64) -> f64 {
+        match &self.kind {
+            Kind::Constant { num, den } => frame as f64 * 1000.0 * *den as f64 / *num as f64,
+            Kind::Timecodes { times, tail } => {
+                if frame < 0 {
+                    times[0] + frame as f64 * (times[1] - times[0])
+                } else if (frame as usize) < times.len() {
+                    times[frame as usize]
+                } else {
+                    times[times.len() - 1] + (frame as usize - times.len() + 1) as f64 * tail
+                }
+            }
+            Kind::Ranges(segments) => {
+                let i = segments.partition_point(|s| s.frame <= frame).max(1) - 1;
+                segments[i].frame_time(frame)
+            }
+        }
+    }
+
+    /// The timestamp of `frame`, rounded up to whole milliseconds, which is
+    /// the time to render it at.
+    pub fn timestamp(&self, frame: i64) -> i64 {
+        self.frame_time(frame).ceil() as i64
+    }
+
+    /// The frame being displayed at `ms`, i.e. the last one that starts at
+    /// or before it.
+    pub fn frame_at(&self, ms: i64) -> i64 {
+        match &self.kind {
+            Kind::Constant { num, den } => {
+                (ms as i128 * *num as i128).div_euclid(*den as i128 * 1000) as i64
+            }
+            Kind::Timecodes { times, tail } => {
+                let ms = ms as f64;
+                if ms < times[0] {
+                    let interval = times[1] - times[0];
+                    ((ms - times[0]) / interval).floor() as i64
+                } else if ms < times[times.len() - 1] {
+                    times.partition_point(|&t| t <= ms) as i64 - 1
+                } else {
+                    let last = times.len() as i64 - 1;
+                    last + ((ms - times[times.len() - 1]) / tail).floor() as i64
+                }
+            }
+            Kind::Ranges(segments) => {
+                let ms = ms as f64;
+                let i = segments.partition_point(|s| s.time <= ms).max(1) - 1;
+                let segment = &segments[i];
+                let frame =
+                    segment.frame + ((ms - segment.time) * segment.fps / 1000.0).floor() as i64;
+                // rounding can land a frame off at exact frame times
+                if self.frame_time(frame + 1) <= ms {
+                    frame + 1
+                } else if self.frame_time(frame) > ms {
+                    frame - 1
+                } else {
+                    frame
+                }
+            }
+        }
+    }
+
+    /// The time for an event to start on `frame`, in centiseconds so that it
+    /// survives being written to a script: halfway between the previous
+    /// frame and this one, rounded up to the next centisecond.
+    pub fn start_time(&self, frame: i64) -> i64 {
+        let middle = (self.frame_time(frame - 1) + self.frame_time(frame)) / 2.0;
+        (middle / 10.0).ceil() as i64 * 10
+    }
+
+    /// The time for an event to end before `frame` is shown, which is the
+    /// time for one to start on it.
+    pub fn end_time(&self, frame: i64) -> i64 {
+        self.start_time(frame)
+    }
+
+    /// The position of `ms` in frames, with the fraction telling how far it
+    /// is from the frame being displayed towards the next one.
+    pub(crate) fn frame_position(&self, ms: i64) -> f64 {
+        let frame = self.frame_at(ms);
+        let start = self.frame_time(frame);
+        let next = self.frame_time(frame + 1);
+        frame as f64 + ((ms as f64 - start) / (next - start)).clamp(0.0, 1.0)
+    }
+
+    /// The inverse of [`Timebase::frame_position`].
+    pub(crate) fn position_time(&self, position: f64) -> i64 {
+        let frame = position.floor();
+        let start = self.frame_time(frame as i64);
+        let next = self.frame_time(frame as i64 + 1);
+        (start + (position - frame) * (next - start)).round() as i64
+    }
+}
+
+fn is_frame_rate(fps: f64) -> bool {
+    fps.is_finite() && fps > 0.0
+}
+
+fn parse_v1<'a>(mut lines: impl Iterator<Item = &'a str>) -> Result<Timebase> {
+    let assume = lines
+        .next()
+        .and_then(|line| {
+            let (keyword, fps) = line.split_once(char::is_whitespace)?;
+            if keyword.eq_ignore_ascii_case("assume") {
+                fps.trim().parse::<f64>().ok()
+            } else {
+                None
+            }
+        })
+        .filter(|&fps| is_frame_rate(fps))
+        .ok_or(Error)?;
+
+    // start frame, end frame and frame rate
+    let mut ranges = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if let [start, end, fps] = fields[..] {
+            let start: i64 = start.parse().map_err(|_| Error)?;
+            let end: i64 = end.parse().map_err(|_| Error)?;
+            let fps: f64 = fps.parse().map_err(|_| Error)?;
+            if start < 0 || end < start || end == i64::MAX || !is_frame_rate(fps) {
+                return Err(Error);
+            }
+            ranges.push((start, end, fps));
+        } else {
+            return Err(Error);
+        }
+    }
+    ranges.sort_by_key(|range| range.0);
+
+    // the ranges with the assumed rate filling the gaps between them
+    let mut segments = Vec::new();
+    let mut segment = Segment {
+        frame: 0,
+        time: 0.0,
+        fps: assume,
+    };
+    for (start, end, fps) in ranges {
+        if start < segment.frame {
+            // overlaps the previous range
+            return Err(Error);
+        }
+        if start > segment.frame {
+            let time = segment.frame_time(start);
+            segments.push(segment);
+            segment = Segment {
+                frame: start,
+                time,
+                fps,
+            };
+        } else {
+            segment.fps = fps;
+        }
+        let time = segment.frame_time(end + 1);
+        segments.push(segment);
+        segment = Segment {
+            frame: end + 1,
+            time,
+            fps: assume,
+        };
+    }
+    segments.push(segment);
+    Ok(Timebase {
+        kind: Kind::Ranges(segments),
+    })
+}
+
+impl Timeline {
+    /// The frames in `frames` that need to be rendered because the output
+    /// differs from the frame before, always including the first one. Each
+    /// comes with the timestamp to render it at.
+    pub fn frames<'a>(
+        &'a self,
+        timebase: &'a Timebase,
+        frames: Range<i64>,
+    ) -> impl Iterator<Item = (i64, i64)> + 'a {
+        let mut next = Some(frames.start).filter(|_| !frames.is_empty());
+        std::iter::from_fn(move || {
+            let frame = next?;
+            let now = timebase.timestamp(frame);
+            // a change is visible from the first frame at or after it
+            next = self
+                .next_change(now)
+                .map(|change| (timebase.frame_at(change - 1) + 1).max(frame + 1))
+                .filter(|&frame| frame < frames.end);
+            Some((frame, now))
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use crate::tags::{find_block, split_args, tokenize, RawTag};
+use crate::track::{Event, Track};
+
+/// A moment at which the rendered output of a track may change.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ChangePoint {
+    /// Time in milliseconds.
+    pub time: i64,
+    /// Whether the output keeps changing continuously until the next point,
+    /// e.g. during a `\move`, `\fad` or `\t` animation.
+    pub animated: bool,
+}
+
+/// Sorted change points of a track, as returned by [`Track::change_points`].
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    points: Vec<ChangePoint>,
+}
+
+impl Timeline {
+    pub fn points(&self) -> &[ChangePoint] {
+        &self.points
+    }
+
+    /// Whether the output is being animated at `now`.
+    pub fn is_animated(&self, now: i64) -> bool {
+        let i = self.points.partition_point(|p| p.time <= now);
+        i > 0 && self.points[i - 1].animated
+    }
+
+    /// The earliest time after `now` at which the output may differ from the
+    /// one at `now`, or `None` if it never changes again.
+    ///
+    /// Inside an animation this is always `now + 1`.
+    pub fn next_change(&self, now: i64) -> Option<i64> {
+        if self.is_animated(now) {
+            return Some(now + 1);
+        }
+        let i = self.points.partition_point(|p| p.time <= now);
+        self.points.get(i).map(|p| p.time)
+    }
+
+    fn from_parts(mut instants: Vec<i64>, mut spans: Vec<(i64, i64)>) -> Self {
+        spans.sort_unstable();
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(spans.len());
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        for &(start, end) in &merged {
+            instants.push(start);
+            instants.push(end);
+        }
+        instants.sort_unstable();
+        instants.dedup();
+
+        let mut points = Vec::with_capacity(instants.len());
+        let mut spans = merged.iter().peekable();
+        for time in instants {
+            while spans.peek().is_some_and(|&&(_, end)| end <= time) {
+                spans.next();
+            }
+            let animated = match spans.peek() {
+                // points in the middle of an animation add nothing
+                Some(&&(start, _)) if start < time => continue,
+                Some(&&(start, _)) => start == time,
+                None => false,
+            };
+            points.push(ChangePoint { time, animated });
+        }
+
+        Timeline { points }
+    }
+}
+
+impl<'library> Track<'library> {
+    /// Computes every time at which the rendered output of this track can
+    /// change, from event start and end times and from the animation tags
+    /// `\move`, `\fad`, `\fade`, `\t` and the `\k` family.
+    pub fn change_points(&self) -> Timeline {
+        let mut instants = Vec::new();
+        let mut spans = Vec::new();
+        for event in self.events() {
+            collect_event(&event, &mut instants, &mut spans);
+        }
+        Timeline::from_parts(instants, spans)
+    }
+}
+
+fn collect_event(event: &Event, instants: &mut Vec<i64>, spans: &mut Vec<(i64, i64)>) {
+    let start = event.start();
+    let end = event.end();
+    if end <= start {
+        return;
+    }
+    instants.push(start);
+    instants.push(end);
+
+    let duration = end - start;
+    let mut span = |from: i64, to: i64| {
+        let (from, to) = (from.max(0), to.min(duration));
+        if from < to {
+            spans.push((start + from, start + to));
+        }
+    };
+
+    // karaoke syllables start where the previous one ended
+    let mut karaoke = 0;
+
+    let text = event.text();
+    for (name, args) in override_tags(&text) {
+        let args = split_args(args);
+        let arg = |i: usize| args.get(i).and_then(|a| parse_ms(a));
+        match name {
+            "move" => match (arg(4), arg(5)) {
+                (Some(t1), Some(t2)) if (t1, t2) != (0, 0) => span(t1.min(t2), t1.max(t2)),
+                _ => span(0, duration),
+            },
+            "fad" => {
+                if let (Some(t1), Some(t2)) = (arg(0), arg(1)) {
+                    span(0, t1);
+                    span(duration - t2, duration);
+                }
+            }
+            "fade" => {
+                if let (Some(t1), Some(t2), Some(t3), Some(t4)) = (arg(3), arg(4), arg(5), arg(6)) {
+                    span(t1, t2);
+                    span(t3, t4);
+                }
+            }
+            "t" => match (arg(0), arg(1)) {
+                (Some(t1), Some(t2)) if args.len() >= 3 && (t1, t2) != (0, 0) => span(t1, t2),
+                _ => span(0, duration),
+            },
+            "k" | "K" | "kf" | "ko" | "kt" => {
+                let value = match args.first().and_then(|a| a.trim().parse::<f64>().ok()) {
+                    Some(value) => (value * 10.0) as i64,
+                    None => continue,
+                };
+                if name == "kt" {
+                    karaoke = value;
+                    continue;
+                }
+                if karaoke > 0 && karaoke < duration {
+                    instants.push(start + karaoke);
+                }
+                if name == "K" || name == "kf" {
+                    span(karaoke, karaoke + value);
+                }
+                karaoke += value;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_ms(arg: &str) -> Option<i64> {
+    arg.trim().parse::<f64>().ok().map(|v| v as i64)
+}
+
+// Yields the name and raw arguments of each tag in the override blocks of
+// `text`, e.g. `("pos", "320,50")` or `("k", "20")`.
+pub(crate) fn override_tags(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = text;
+    let mut block = Vec::new().into_iter();
+    std::iter::from_fn(move || loop {
+        if let Some(token) = block.next() {
+            if let Ok(RawTag { name, args, .. }) = token {
+                return Some((name, args));
+            }
+            continue;
+        }
+
+        let open = find_block(rest)?;
+        let close = rest[open..].find('}').map_or(rest.len(), |i| open + i);
+        block = tokenize(&rest[open + 1..close]).into_iter();
+        rest = rest.get(close + 1..).unwrap_or("");
+    })
+}
@@ -1,39 +1,227 @@
-use std::ffi::CStr;
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::os::raw::c_char;
 use std::os::raw::c_int;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
 
 use libass_sys as ffi;
 
+use crate::library::Library;
+use crate::style;
+use crate::tags::EventText;
+use crate::{err_if_null, Result};
+
+/// Subtitle events and styles, created with [`Library::new_track`] and the
+/// other `new_track_*` methods.
 pub struct Track<'library> {
     handle: NonNull<ffi::ass_track>,
-    phantom: PhantomData<&'library mut ffi::ass_track>,
+    // keeps the library alive for tracks that don't borrow it
+    library: Library,
+    borrow: PhantomData<&'library Library>,
 }
 
 impl<'library> Track<'library> {
-    pub(crate) unsafe fn new_unchecked(track: *mut ffi::ass_track) -> Self {
+    pub(crate) unsafe fn new_unchecked(track: *mut ffi::ass_track, library: &Library) -> Self {
         Track {
             handle: NonNull::new_unchecked(track),
-            phantom: PhantomData,
+            library: library.clone(),
+            borrow: PhantomData,
         }
     }
 
+    /// Makes the track keep its library alive instead of borrowing it, so
+    /// it can be stored next to the library, see [`Library`].
+    pub fn into_owned(self) -> Track<'static> {
+        let track = ManuallyDrop::new(self);
+        Track {
+            handle: track.handle,
+            library: unsafe { ptr::read(&track.library) },
+            borrow: PhantomData,
+        }
+    }
+
+    /// Creates an independent copy of the track in the same library, with
+    /// its header, styles and events.
+    ///
+    /// libass's parser state isn't copied, so the copy doesn't know the
+    /// read orders of chunks the original processed, or where in the
+    /// script incremental data passed to [`Track::process_data`] stopped.
+    pub fn try_clone(&self) -> Result<Track<'library>> {
+        let source = self.as_ass_track();
+        let handle = unsafe { ffi::ass_new_track(source.library) };
+        err_if_null!(handle);
+        let mut copy = unsafe { Track::new_unchecked(handle, &self.library) };
+
+        let target = copy.as_ass_track_mut();
+        unsafe {
+            // the style every new track starts with
+            for id in 0..target.n_styles {
+                ffi::ass_free_style(handle, id);
+            }
+            for ptr in [
+                target.style_format,
+                target.event_format,
+                target.Language,
+                target.name,
+            ] {
+                libc::free(ptr as *mut _);
+            }
+            *target = ffi::ass_track {
+                n_styles: 0,
+                max_styles: target.max_styles,
+                n_events: 0,
+                max_events: target.max_events,
+                styles: target.styles,
+                events: target.events,
+                style_format: strdup_or_null(source.style_format),
+                event_format: strdup_or_null(source.event_format),
+                Language: strdup_or_null(source.Language),
+                name: strdup_or_null(source.name),
+                library: target.library,
+                parser_priv: target.parser_priv,
+                ..*source
+            };
+
+            for id in 0..source.n_styles as usize {
+                let mut style = *source.styles.add(id);
+                style.Name = strdup_or_null(style.Name);
+                style.FontName = strdup_or_null(style.FontName);
+                copy.add_ass_style(style);
+            }
+            for id in 0..source.n_events as usize {
+                let mut event = *source.events.add(id);
+                event.Name = strdup_or_null(event.Name);
+                event.Effect = strdup_or_null(event.Effect);
+                event.Text = strdup_or_null(event.Text);
+                event.render_priv = ptr::null_mut();
+                let new_id = ffi::ass_alloc_event(handle);
+                *copy.as_ass_track_mut().events.add(new_id as usize) = event;
+            }
+        }
+        Ok(copy)
+    }
+
     pub(crate) fn as_ptr(&self) -> *const ffi::ass_track {
         self.handle.as_ptr()
     }
 
-    pub fn new_style(&self) -> Style {
-        Style {
-            id: unsafe { ffi::ass_alloc_style(self.handle.as_ptr()) },
-            parent: &self,
+    pub(crate) fn as_ass_track(&self) -> &ffi::ass_track {
+        unsafe { self.handle.as_ref() }
+    }
+
+    pub(crate) fn as_ass_track_mut(&mut self) -> &mut ffi::ass_track {
+        unsafe { self.handle.as_mut() }
+    }
+
+    pub fn style_count(&self) -> usize {
+        self.as_ass_track().n_styles as usize
+    }
+
+    /// Returns a copy of the style at `id`.
+    pub fn style(&self, id: i32) -> Option<style::Style> {
+        if id < 0 || id as usize >= self.style_count() {
+            return None;
+        }
+        let s = unsafe { &*self.as_ass_track().styles.offset(id as isize) };
+        Some(style::Style {
+            name: unsafe { cstring_from_ptr(s.Name) },
+            font_name: unsafe { cstring_from_ptr(s.FontName) },
+            font_size: s.FontSize,
+            primary_color: s.PrimaryColour,
+            secondary_color: s.SecondaryColour,
+            outline_color: s.OutlineColour,
+            back_color: s.BackColour,
+            bold: s.Bold != 0,
+            italic: s.Italic != 0,
+            underline: s.Underline != 0,
+            strikeout: s.StrikeOut != 0,
+            scale_x: s.ScaleX,
+            scale_y: s.ScaleY,
+            spacing: s.Spacing,
+            angle: s.Angle,
+            border_style: s.BorderStyle,
+            outline: s.Outline,
+            shadow: s.Shadow,
+            alignment: s.Alignment,
+            margin_l: s.MarginL,
+            margin_r: s.MarginR,
+            margin_v: s.MarginV,
+            encoding: s.Encoding,
+            treat_fontname_as_pattern: s.treat_fontname_as_pattern != 0,
+            blur: s.Blur,
+            justify: s.Justify,
+        })
+    }
+
+    /// Appends a copy of `style` and returns its id.
+    pub fn add_style(&mut self, style: &style::Style) -> i32 {
+        unsafe {
+            let mut copy = style.as_ass_style();
+            copy.Name = libc::strdup(style.name.as_ptr());
+            copy.FontName = libc::strdup(style.font_name.as_ptr());
+            self.add_ass_style(copy)
+        }
+    }
+
+    // the strings in `style` are owned by the track afterwards, so they have
+    // to come from malloc()
+    pub(crate) unsafe fn add_ass_style(&mut self, style: ffi::ass_style) -> i32 {
+        let id = ffi::ass_alloc_style(self.handle.as_ptr());
+        *self.as_ass_track_mut().styles.offset(id as isize) = style;
+        id
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.as_ass_track().n_events as usize
+    }
+
+    pub fn event(&self, id: i32) -> Option<Event<'_>> {
+        if id < 0 || id as usize >= self.event_count() {
+            return None;
         }
+        Some(Event { id, parent: self })
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = Event<'_>> {
+        (0..self.event_count() as i32).map(move |id| Event { id, parent: self })
     }
 
-    pub fn new_event(&self) -> Event {
-        Event {
-            id: unsafe { ffi::ass_alloc_event(self.handle.as_ptr()) },
-            parent: &self,
+    pub fn event_mut(&mut self, id: i32) -> Option<EventMut<'_>> {
+        if id < 0 || id as usize >= self.event_count() {
+            return None;
         }
+        let event = unsafe { &mut *self.as_ass_track_mut().events.offset(id as isize) };
+        Some(EventMut { id, event })
+    }
+
+    /// Appends an empty event, with its read order set to its id.
+    pub fn add_event(&mut self) -> EventMut<'_> {
+        let id = unsafe { ffi::ass_alloc_event(self.handle.as_ptr()) };
+        let mut event = self.event_mut(id).unwrap();
+        event.set_read_order(id);
+        event
+    }
+
+    /// Removes the events for which `keep` returns `false`. The remaining
+    /// events keep their order and are renumbered from zero.
+    pub fn retain_events(&mut self, mut keep: impl FnMut(&Event<'_>) -> bool) {
+        let removed: Vec<bool> = self.events().map(|e| !keep(&e)).collect();
+        let handle = self.handle.as_ptr();
+        let track = self.as_ass_track_mut();
+        let mut kept = 0;
+        for (id, removed) in removed.into_iter().enumerate() {
+            unsafe {
+                if removed {
+                    ffi::ass_free_event(handle, id as c_int);
+                } else {
+                    ptr::copy(track.events.add(id), track.events.add(kept), 1);
+                    kept += 1;
+                }
+            }
+        }
+        track.n_events = kept as c_int;
     }
 
     pub fn step_sub(&self, now: i64, movement: i32) -> i64 {
@@ -101,24 +289,166 @@ impl<'library> Drop for Track<'library> {
     }
 }
 
-pub struct Style<'track> {
+pub struct Event<'track> {
     pub id: i32,
     parent: &'track Track<'track>,
 }
 
-impl<'track> Drop for Style<'track> {
-    fn drop(&mut self) {
-        unsafe { ffi::ass_free_style(self.parent.handle.as_ptr(), self.id) }
+impl<'track> Event<'track> {
+    fn as_ass_event(&self) -> &'track ffi::ass_event {
+        unsafe { &*self.parent.as_ass_track().events.offset(self.id as isize) }
+    }
+
+    /// Start time in milliseconds.
+    pub fn start(&self) -> i64 {
+        self.as_ass_event().Start
+    }
+
+    /// Duration in milliseconds.
+    pub fn duration(&self) -> i64 {
+        self.as_ass_event().Duration
+    }
+
+    /// End time in milliseconds.
+    pub fn end(&self) -> i64 {
+        self.start().saturating_add(self.duration())
+    }
+
+    pub fn read_order(&self) -> i32 {
+        self.as_ass_event().ReadOrder
+    }
+
+    pub fn layer(&self) -> i32 {
+        self.as_ass_event().Layer
+    }
+
+    /// Index of the event's style in the parent track.
+    pub fn style(&self) -> i32 {
+        self.as_ass_event().Style
+    }
+
+    pub fn name(&self) -> Cow<'track, str> {
+        unsafe { str_from_ptr(self.as_ass_event().Name) }
+    }
+
+    pub fn margin_l(&self) -> i32 {
+        self.as_ass_event().MarginL
+    }
+
+    pub fn margin_r(&self) -> i32 {
+        self.as_ass_event().MarginR
+    }
+
+    pub fn margin_v(&self) -> i32 {
+        self.as_ass_event().MarginV
+    }
+
+    pub fn effect(&self) -> Cow<'track, str> {
+        unsafe { str_from_ptr(self.as_ass_event().Effect) }
+    }
+
+    /// The raw event text, including override blocks.
+    pub fn text(&self) -> Cow<'track, str> {
+        unsafe { str_from_ptr(self.as_ass_event().Text) }
+    }
+
+    /// The visible text, see [`EventText::plain_text`].
+    pub fn plain_text(&self) -> String {
+        EventText::parse(&self.text()).plain_text()
     }
 }
 
-pub struct Event<'track> {
+pub struct EventMut<'track> {
     pub id: i32,
-    parent: &'track Track<'track>,
+    event: &'track mut ffi::ass_event,
 }
 
-impl<'track> Drop for Event<'track> {
-    fn drop(&mut self) {
-        unsafe { ffi::ass_free_event(self.parent.handle.as_ptr(), self.id) }
+impl<'track> EventMut<'track> {
+    pub fn start(&self) -> i64 {
+        self.event.Start
+    }
+
+    pub fn duration(&self) -> i64 {
+        self.event.Duration
+    }
+
+    pub fn text(&self) -> Cow<'_, str> {
+        unsafe { str_from_ptr(self.event.Text) }
+    }
+
+    pub fn set_start(&mut self, start: i64) {
+        self.event.Start = start;
+    }
+
+    pub fn set_duration(&mut self, duration: i64) {
+        self.event.Duration = duration;
+    }
+
+    pub fn set_read_order(&mut self, read_order: i32) {
+        self.event.ReadOrder = read_order;
+    }
+
+    pub fn set_layer(&mut self, layer: i32) {
+        self.event.Layer = layer;
     }
+
+    pub fn set_style(&mut self, style: i32) {
+        self.event.Style = style;
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        unsafe { replace_str(&mut self.event.Name, name) }
+    }
+
+    pub fn set_margin_l(&mut self, margin: i32) {
+        self.event.MarginL = margin;
+    }
+
+    pub fn set_margin_r(&mut self, margin: i32) {
+        self.event.MarginR = margin;
+    }
+
+    pub fn set_margin_v(&mut self, margin: i32) {
+        self.event.MarginV = margin;
+    }
+
+    pub fn set_effect(&mut self, effect: &str) {
+        unsafe { replace_str(&mut self.event.Effect, effect) }
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        unsafe { replace_str(&mut self.event.Text, text) }
+    }
+}
+
+// libass leaves unset strings as null
+unsafe fn str_from_ptr<'a>(ptr: *const c_char) -> Cow<'a, str> {
+    if ptr.is_null() {
+        Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy()
+    }
+}
+
+unsafe fn cstring_from_ptr(ptr: *const c_char) -> CString {
+    if ptr.is_null() {
+        CString::default()
+    } else {
+        CStr::from_ptr(ptr).to_owned()
+    }
+}
+
+unsafe fn strdup_or_null(ptr: *const c_char) -> *mut c_char {
+    if ptr.is_null() {
+        ptr::null_mut()
+    } else {
+        libc::strdup(ptr)
+    }
+}
+
+// libass frees its strings with free(), so they have to come from malloc()
+pub(crate) unsafe fn replace_str(ptr: &mut *mut c_char, value: &str) {
+    let value = CString::new(value).unwrap();
+    libc::free(*ptr as *mut _);
+    *ptr = libc::strdup(value.as_ptr());
 }
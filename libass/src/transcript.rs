@@ -0,0 +1,68 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use crate::track::Track;
+use crate::webvtt::format_time;
+
+/// A line of a transcript, as returned by [`Track::transcript`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TranscriptLine {
+    /// Start time in milliseconds.
+    pub start: i64,
+    /// End time in milliseconds.
+    pub end: i64,
+    /// The actor, empty if the event has none.
+    pub name: String,
+    /// The visible text, with line breaks and runs of spaces collapsed to
+    /// single spaces.
+    pub text: String,
+}
+
+/// `[00:00:01.000 --> 00:00:03.500] Name: text`
+impl Display for TranscriptLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{} --> {}] ",
+            format_time(self.start),
+            format_time(self.end)
+        )?;
+        if !self.name.is_empty() {
+            write!(f, "{}: ", self.name)?;
+        }
+        f.write_str(&self.text)
+    }
+}
+
+impl<'library> Track<'library> {
+    /// The visible text of all events in time order, leaving out events with
+    /// nothing to read such as drawings.
+    pub fn transcript(&self) -> Vec<TranscriptLine> {
+        let mut events: Vec<_> = self.events().collect();
+        events.sort_by_key(|e| (e.start(), e.read_order(), e.id));
+        events
+            .iter()
+            .filter_map(|event| {
+                let text = event.plain_text();
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.is_empty() {
+                    return None;
+                }
+                Some(TranscriptLine {
+                    start: event.start(),
+                    end: event.end(),
+                    name: event.name().into_owned(),
+                    text,
+                })
+            })
+            .collect()
+    }
+
+    /// Writes [`Track::transcript`], one line per event.
+    pub fn write_transcript(&self, out: &mut impl io::Write) -> io::Result<()> {
+        for line in self.transcript() {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+}
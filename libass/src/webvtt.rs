@@ -0,0 +1,737 @@
+//! WebVTT captions.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+
+use crate::export::{export_cues, ExportReport};
+use crate::library::Library;
+use crate::script::legacy_to_numpad_alignment;
+use crate::srt;
+use crate::style::Style;
+use crate::track::Track;
+use crate::Result;
+
+// libass's frame for scripts without PlayResX and PlayResY
+const PLAY_RES: (i32, i32) = (384, 288);
+
+// classes every WebVTT renderer knows without a STYLE block
+const COLOR_CLASSES: [&str; 8] = [
+    "white", "lime", "cyan", "red", "yellow", "magenta", "blue", "black",
+];
+
+/// The cues and style rules of a WebVTT file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebVtt {
+    pub cues: Vec<Cue>,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub id: Option<String>,
+    /// Start time in milliseconds.
+    pub start: i64,
+    /// End time in milliseconds.
+    pub end: i64,
+    pub settings: CueSettings,
+    /// The cue payload, with its tags and entities left in place.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSettings {
+    pub line: Option<Line>,
+    pub line_align: Option<Anchor>,
+    /// Horizontal position in percent of the frame width.
+    pub position: Option<f64>,
+    pub position_align: Option<Anchor>,
+    /// Width of the cue box in percent of the frame width.
+    pub size: Option<f64>,
+    pub align: Option<Align>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Line {
+    Percent(f64),
+    /// A line number, counted from the bottom if negative.
+    Number(i32),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Anchor {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+/// A rule from a `STYLE` block. Only `::cue` and `::cue(.class)` selectors
+/// are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// The class of a `::cue(.class)` rule, `None` for `::cue`.
+    pub class: Option<String>,
+    /// Lowercased property names and their values.
+    pub declarations: Vec<(String, String)>,
+}
+
+/// Parses WebVTT, skipping `NOTE` and `REGION` blocks and cues without a
+/// valid timing line.
+pub fn parse(data: &str) -> WebVtt {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    let mut blocks = Vec::new();
+    let mut block = Vec::new();
+    for line in data.lines() {
+        if !line.trim().is_empty() {
+            block.push(line);
+        } else if !block.is_empty() {
+            blocks.push(std::mem::take(&mut block));
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+
+    let mut vtt = WebVtt::default();
+    for (i, block) in blocks.iter().enumerate() {
+        let first = block[0].trim();
+        if (i == 0 && first.starts_with("WEBVTT"))
+            || first.starts_with("NOTE")
+            || first.starts_with("REGION")
+        {
+            continue;
+        }
+        if first == "STYLE" {
+            vtt.rules.extend(parse_css(&block[1..].join("\n")));
+            continue;
+        }
+
+        let (id, timing, text) = match block.iter().position(|l| l.contains("-->")) {
+            Some(0) => (None, block[0], &block[1..]),
+            Some(1) => (Some(block[0].to_string()), block[1], &block[2..]),
+            _ => continue,
+        };
+        let (start, rest) = match timing.split_once("-->") {
+            Some(timing) => timing,
+            None => continue,
+        };
+        let mut rest = rest.split_whitespace();
+        let times = (
+            srt::parse_time(start),
+            rest.next().and_then(srt::parse_time),
+        );
+        if let (Some(start), Some(end)) = times {
+            vtt.cues.push(Cue {
+                id,
+                start,
+                end,
+                settings: parse_settings(rest),
+                text: text.join("\n"),
+            });
+        }
+    }
+
+    vtt
+}
+
+/// Formats milliseconds as an `HH:MM:SS.mmm` timestamp.
+pub fn format_time(ms: i64) -> String {
+    srt::format_time(ms).replace(',', ".")
+}
+
+fn parse_settings<'a>(items: impl Iterator<Item = &'a str>) -> CueSettings {
+    let mut settings = CueSettings::default();
+    let percent = |value: &str| value.strip_suffix('%')?.parse::<f64>().ok();
+    for item in items {
+        let (key, value) = match item.split_once(':') {
+            Some(setting) => setting,
+            None => continue,
+        };
+        let (value, anchor) = match value.split_once(',') {
+            Some((value, anchor)) => (value, parse_anchor(anchor)),
+            None => (value, None),
+        };
+        match key {
+            "line" => {
+                settings.line = match percent(value) {
+                    Some(percent) => Some(Line::Percent(percent)),
+                    None => value.parse().ok().map(Line::Number),
+                };
+                settings.line_align = anchor;
+            }
+            "position" => {
+                settings.position = percent(value);
+                settings.position_align = anchor;
+            }
+            "size" => settings.size = percent(value),
+            "align" => {
+                settings.align = match value {
+                    "start" => Some(Align::Start),
+                    "center" | "middle" => Some(Align::Center),
+                    "end" => Some(Align::End),
+                    "left" => Some(Align::Left),
+                    "right" => Some(Align::Right),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+    settings
+}
+
+fn parse_anchor(value: &str) -> Option<Anchor> {
+    match value {
+        "start" | "line-left" => Some(Anchor::Start),
+        "center" | "middle" => Some(Anchor::Center),
+        "end" | "line-right" => Some(Anchor::End),
+        _ => None,
+    }
+}
+
+fn parse_css(css: &str) -> Vec<Rule> {
+    let mut uncommented = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(open) = rest.find("/*") {
+        uncommented.push_str(&rest[..open]);
+        rest = rest[open + 2..]
+            .find("*/")
+            .map_or("", |close| &rest[open + 2 + close + 2..]);
+    }
+    uncommented.push_str(rest);
+
+    let mut rules = Vec::new();
+    let mut rest = uncommented.as_str();
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..].find('}').map_or(rest.len(), |i| open + i);
+        let declarations: Vec<_> = rest[open + 1..close]
+            .split(';')
+            .filter_map(|declaration| {
+                let (key, value) = declaration.split_once(':')?;
+                let value = value.trim().trim_end_matches("!important").trim_end();
+                Some((key.trim().to_ascii_lowercase(), value.to_string()))
+            })
+            .collect();
+        for selector in rest[..open].split(',') {
+            if let Some(class) = parse_selector(selector.trim()) {
+                rules.push(Rule {
+                    class,
+                    declarations: declarations.clone(),
+                });
+            }
+        }
+        rest = rest.get(close + 1..).unwrap_or("");
+    }
+    rules
+}
+
+// `::cue` is `Some(None)`, `::cue(.class)` is `Some(Some(class))`
+fn parse_selector(selector: &str) -> Option<Option<String>> {
+    let inner = selector.strip_prefix("::cue")?.trim();
+    if inner.is_empty() {
+        return Some(None);
+    }
+    let class = inner.strip_prefix("(.")?.strip_suffix(')')?.trim();
+    if is_class_name(class) {
+        Some(Some(class.to_string()))
+    } else {
+        None
+    }
+}
+
+fn is_class_name(class: &str) -> bool {
+    !class.is_empty()
+        && class
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+fn apply_declarations(style: &mut Style, declarations: &[(String, String)]) {
+    for (key, value) in declarations {
+        let value = value.as_str();
+        match key.as_str() {
+            "color" => {
+                if let Some(color) = css_color(value) {
+                    style.primary_color = color;
+                }
+            }
+            // an opaque box is drawn in the outline color
+            "background-color" | "background" => {
+                if let Some(color) = css_color(value) {
+                    style.outline_color = color;
+                    style.border_style = 3;
+                }
+            }
+            "font-family" => {
+                let family = value.split(',').next().unwrap_or("").trim();
+                let family = family.trim_matches(|c| c == '"' || c == '\'');
+                if let Ok(family) = CString::new(family) {
+                    style.font_name = family;
+                }
+            }
+            "font-size" => {
+                if let Some(size) = css_length(value, style.font_size) {
+                    style.font_size = size;
+                }
+            }
+            "font-weight" => {
+                style.bold = match value {
+                    "bold" | "bolder" => true,
+                    _ => value.parse::<u32>().is_ok_and(|weight| weight >= 600),
+                }
+            }
+            "font-style" => style.italic = value == "italic" || value == "oblique",
+            "text-decoration" | "text-decoration-line" => {
+                style.underline = value.contains("underline");
+                style.strikeout = value.contains("line-through");
+            }
+            _ => {}
+        }
+    }
+}
+
+// `px` are script pixels, `em` and `%` are relative to `base`
+fn css_length(value: &str, base: f64) -> Option<f64> {
+    let (number, scale) = if let Some(number) = value.strip_suffix("px") {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix("em") {
+        (number, base)
+    } else if let Some(number) = value.strip_suffix('%') {
+        (number, base / 100.0)
+    } else {
+        (value, 1.0)
+    };
+    let length = number.trim().parse::<f64>().ok()? * scale;
+    if length > 0.0 {
+        Some(length)
+    } else {
+        None
+    }
+}
+
+// Parses a CSS color into libass's RGBA with inverted alpha.
+fn css_color(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let (r, g, b, a) = if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+    {
+        let args: Vec<&str> = args.strip_suffix(')')?.split(',').map(str::trim).collect();
+        if args.len() < 3 {
+            return None;
+        }
+        let channel = |arg: &str| {
+            let value = match arg.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().ok()? * 2.55,
+                None => arg.parse::<f64>().ok()?,
+            };
+            Some(value.round().clamp(0.0, 255.0) as u8)
+        };
+        let alpha = match args.get(3) {
+            Some(alpha) => (alpha.parse::<f64>().ok()?.clamp(0.0, 1.0) * 255.0).round() as u8,
+            None => 255,
+        };
+        (
+            channel(args[0])?,
+            channel(args[1])?,
+            channel(args[2])?,
+            alpha,
+        )
+    } else if value.len() == 9 && value.starts_with('#') {
+        let [r, g, b, a] = u32::from_str_radix(&value[1..], 16).ok()?.to_be_bytes();
+        (r, g, b, a)
+    } else {
+        let (r, g, b) = srt::html_color(value)?;
+        (r, g, b, 255)
+    };
+    Some(u32::from_be_bytes([r, g, b, 255 - a]))
+}
+
+struct Text {
+    text: String,
+    voice: Option<String>,
+}
+
+// Converts a cue payload to ASS event text. `class_style` returns the name
+// of the style for a span's classes, if any.
+fn convert_text(text: &str, mut class_style: impl FnMut(&[&str]) -> Option<String>) -> Text {
+    let mut out = String::with_capacity(text.len());
+    let mut voice = None;
+    // the style of each open <c> or <v> span
+    let mut spans: Vec<Option<String>> = Vec::new();
+    // ruby text can't be placed above its base, so it is left out
+    let mut ruby_text = 0;
+    let mut rest = text;
+
+    while let Some(open) = rest.find('<') {
+        if ruby_text == 0 {
+            push_escaped_text(&mut out, &rest[..open]);
+        }
+        let close = match rest[open..].find('>') {
+            Some(i) => open + i,
+            None => {
+                rest = &rest[open..];
+                break;
+            }
+        };
+        let tag = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            match name.trim() {
+                name @ "b" | name @ "i" | name @ "u" => {
+                    out.push_str(&format!("{{\\{}0}}", name));
+                }
+                "c" | "v" => {
+                    // \r also drops any <b>, <i> or <u> opened inside the span
+                    if let Some(Some(_)) = spans.pop() {
+                        let outer = spans.iter().rev().flatten().next();
+                        out.push_str(&format!("{{\\r{}}}", outer.map_or("", |s| s)));
+                    }
+                }
+                "rt" => ruby_text = (ruby_text - 1).max(0),
+                _ => {}
+            }
+            continue;
+        }
+
+        // `c.class1.class2 annotation`
+        let (head, annotation) = match tag.find(char::is_whitespace) {
+            Some(i) => (&tag[..i], tag[i..].trim()),
+            None => (tag, ""),
+        };
+        let mut classes = head.split('.');
+        let name = classes.next().unwrap_or("");
+        let classes: Vec<&str> = classes.filter(|c| !c.is_empty()).collect();
+        match name {
+            "b" | "i" | "u" => out.push_str(&format!("{{\\{}1}}", name)),
+            "c" | "v" => {
+                if name == "v" && voice.is_none() && !annotation.is_empty() {
+                    let mut name = String::new();
+                    push_text(&mut name, annotation);
+                    voice = Some(name);
+                }
+                let style = if classes.is_empty() {
+                    None
+                } else {
+                    class_style(&classes)
+                };
+                if let Some(style) = &style {
+                    out.push_str(&format!("{{\\r{}}}", style));
+                }
+                spans.push(style);
+            }
+            "rt" => ruby_text += 1,
+            // <ruby>, <lang> and karaoke timestamps
+            _ => {}
+        }
+    }
+    if ruby_text == 0 {
+        push_escaped_text(&mut out, rest);
+    }
+
+    Text {
+        text: out.replace('\n', "\\N"),
+        voice,
+    }
+}
+
+// Appends `text` with character references decoded and with the
+// characters libass would read as overrides or escapes escaped.
+fn push_escaped_text(out: &mut String, text: &str) {
+    let mut decoded = String::with_capacity(text.len());
+    push_text(&mut decoded, text);
+    let mut chars = decoded.chars().peekable();
+    while let Some(c) = chars.next() {
+        // an override block may follow the text
+        let next = chars.peek().copied().or(Some('{'));
+        srt::push_escaped_char(out, c, next);
+    }
+}
+
+// Appends `text` with character references decoded.
+fn push_text(out: &mut String, text: &str) {
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest
+            .find(';')
+            .and_then(|end| Some((end, character_reference(&rest[1..end])?)));
+        match decoded {
+            Some((end, c)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+fn character_reference(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        "lrm" => Some('\u{200e}'),
+        "rlm" => Some('\u{200f}'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number
+                .strip_prefix('x')
+                .or_else(|| number.strip_prefix('X'))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            std::char::from_u32(code)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Layout {
+    tags: String,
+    margin_l: i32,
+    margin_r: i32,
+    margin_v: i32,
+}
+
+// Maps cue settings to `\an` and `\pos` or to event margins, the latter being
+// zero where the style's margins apply.
+fn layout(settings: &CueSettings, style: &Style) -> Layout {
+    let (width, height) = PLAY_RES;
+    let mut layout = Layout::default();
+
+    let numpad = legacy_to_numpad_alignment(style.alignment);
+    // left, center, right
+    let mut column = (numpad - 1) % 3;
+    // bottom, middle, top
+    let mut row = (numpad - 1) / 3;
+    if let Some(align) = settings.align {
+        column = match align {
+            Align::Start | Align::Left => 0,
+            Align::Center => 1,
+            Align::End | Align::Right => 2,
+        };
+    }
+    // WebVTT counts lines of text, this is close enough
+    let line_height = style.font_size.max(1.0);
+    let line_offset = |lines: i32| (lines as f64 * line_height).round() as i32;
+
+    let positioned = settings.position.is_some() || matches!(settings.line, Some(Line::Percent(_)));
+    if positioned {
+        let x = settings.position.unwrap_or(match column {
+            0 => 0.0,
+            1 => 50.0,
+            _ => 100.0,
+        }) * width as f64
+            / 100.0;
+        if let Some(anchor) = settings.position_align {
+            column = anchor_index(anchor);
+        }
+        let y = match settings.line {
+            Some(Line::Percent(percent)) => {
+                row = 2 - anchor_index(settings.line_align.unwrap_or(Anchor::Start));
+                percent * height as f64 / 100.0
+            }
+            Some(Line::Number(n)) if n >= 0 => {
+                row = 2;
+                (style.margin_v + line_offset(n)) as f64
+            }
+            Some(Line::Number(n)) => {
+                row = 0;
+                (height - style.margin_v - line_offset(-n - 1)) as f64
+            }
+            None => {
+                row = 0;
+                (height - style.margin_v) as f64
+            }
+        };
+        layout.tags = format!(
+            "{{\\an{}\\pos({},{})}}",
+            row * 3 + column + 1,
+            x.round() as i32,
+            y.round() as i32
+        );
+        return layout;
+    }
+
+    if let Some(Line::Number(n)) = settings.line {
+        row = if n >= 0 { 2 } else { 0 };
+        let lines = if n >= 0 { n } else { -n - 1 };
+        layout.margin_v = style.margin_v + line_offset(lines);
+    }
+    if let Some(size) = settings.size {
+        let free = ((100.0 - size.clamp(0.0, 100.0)) * width as f64 / 100.0).round() as i32;
+        match column {
+            0 => layout.margin_r = free,
+            1 => {
+                layout.margin_l = free / 2;
+                layout.margin_r = free / 2;
+            }
+            _ => layout.margin_l = free,
+        }
+    }
+    if settings.align.is_some() || settings.line.is_some() {
+        layout.tags = format!("{{\\an{}}}", row * 3 + column + 1);
+    }
+    layout
+}
+
+fn anchor_index(anchor: Anchor) -> i32 {
+    match anchor {
+        Anchor::Start => 0,
+        Anchor::Center => 1,
+        Anchor::End => 2,
+    }
+}
+
+impl Library {
+    /// Creates a track from WebVTT captions.
+    ///
+    /// A copy of `style`, adjusted by the `::cue` rules of `STYLE` blocks,
+    /// becomes the track's default style. `<c>` and `<v>` spans with classes
+    /// switch to a style named after them, built from the default color
+    /// classes and `::cue(.class)` rules, and the first voice of a cue
+    /// becomes its event's name.
+    ///
+    /// Cue settings become `\an` and `\pos` overrides or event margins. The
+    /// track has a 384x288 frame, which sizes and margins in `style` and
+    /// `px` lengths in `STYLE` blocks are relative to. Literal braces and
+    /// backslashes in cues are escaped as by [`srt::to_ass_text`].
+    pub fn new_track_from_webvtt(&self, data: &str, style: &Style) -> Result<Track<'_>> {
+        let vtt = parse(data);
+        let mut track = self.new_track()?;
+        {
+            let track = track.as_ass_track_mut();
+            track.PlayResX = PLAY_RES.0;
+            track.PlayResY = PLAY_RES.1;
+        }
+
+        let mut base = style.clone();
+        for rule in vtt.rules.iter().filter(|rule| rule.class.is_none()) {
+            apply_declarations(&mut base, &rule.declarations);
+        }
+        let default_style = track.add_style(&base);
+        track.as_ass_track_mut().default_style = default_style;
+
+        // whether a style exists for each combination of classes seen
+        let mut class_styles: HashMap<String, bool> = HashMap::new();
+        for cue in &vtt.cues {
+            let text = convert_text(&cue.text, |classes| {
+                if !classes.iter().all(|class| is_class_name(class)) {
+                    return None;
+                }
+                let name = classes.join(".");
+                let exists = *class_styles.entry(name.clone()).or_insert_with(|| {
+                    let mut style = base.clone();
+                    let mut matched = false;
+                    for &class in classes {
+                        if let Some(declaration) = default_declaration(class) {
+                            apply_declarations(&mut style, &[declaration]);
+                            matched = true;
+                        }
+                        for rule in &vtt.rules {
+                            if rule.class.as_deref() == Some(class) {
+                                apply_declarations(&mut style, &rule.declarations);
+                                matched = true;
+                            }
+                        }
+                    }
+                    match CString::new(name.as_str()) {
+                        Ok(name) if matched => {
+                            style.name = name;
+                            track.add_style(&style);
+                            true
+                        }
+                        _ => false,
+                    }
+                });
+                if exists {
+                    Some(name)
+                } else {
+                    None
+                }
+            });
+            let layout = layout(&cue.settings, &base);
+
+            let mut event = track.add_event();
+            event.set_start(cue.start);
+            event.set_duration(cue.end - cue.start);
+            event.set_style(default_style);
+            if let Some(voice) = &text.voice {
+                event.set_name(voice);
+            }
+            event.set_margin_l(layout.margin_l);
+            event.set_margin_r(layout.margin_r);
+            event.set_margin_v(layout.margin_v);
+            event.set_text(&(layout.tags + &text.text));
+        }
+
+        Ok(track)
+    }
+}
+
+fn default_declaration(class: &str) -> Option<(String, String)> {
+    if COLOR_CLASSES.contains(&class) {
+        return Some(("color".to_string(), class.to_string()));
+    }
+    let color = class.strip_prefix("bg_")?;
+    if COLOR_CLASSES.contains(&color) {
+        Some(("background-color".to_string(), color.to_string()))
+    } else {
+        None
+    }
+}
+
+impl<'library> Track<'library> {
+    /// Writes the events as WebVTT in time order.
+    ///
+    /// Line breaks, bold, italic, underline and alignment are kept, the last
+    /// as `line` and `align` cue settings. Everything else is left out and
+    /// listed in the returned report.
+    pub fn write_webvtt(&self, out: &mut impl io::Write) -> io::Result<ExportReport> {
+        let mut report = ExportReport::default();
+        writeln!(out, "WEBVTT")?;
+        for cue in export_cues(self, true, &mut report) {
+            let mut settings = String::new();
+            match (cue.alignment - 1) / 3 {
+                2 => settings.push_str(" line:0"),
+                1 => settings.push_str(" line:50%,center"),
+                _ => {}
+            }
+            match (cue.alignment - 1) % 3 {
+                0 => settings.push_str(" align:left"),
+                2 => settings.push_str(" align:right"),
+                _ => {}
+            }
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{} --> {}{}",
+                format_time(cue.start),
+                format_time(cue.end),
+                settings
+            )?;
+            writeln!(out, "{}", cue.text)?;
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,140 @@
+use libass::{ExportReport, Library, Loss, LossKind, Track};
+
+// a track with a regular style, since libass's default one has a weight
+fn new_track<'a>(library: &'a Library) -> Track<'a> {
+    let mut track = library.new_track().unwrap();
+    let mut style = track.style(0).unwrap();
+    style.bold = false;
+    track.add_style(&style);
+    track
+}
+
+fn add_event(track: &mut Track, start: i64, end: i64, effect: &str, text: &str) {
+    let style = track.style_count() as i32 - 1;
+    let mut event = track.add_event();
+    event.set_start(start);
+    event.set_duration(end - start);
+    event.set_style(style);
+    event.set_effect(effect);
+    event.set_text(text);
+}
+
+fn track<'a>(library: &'a Library) -> Track<'a> {
+    let mut track = new_track(library);
+    add_event(
+        &mut track,
+        1000,
+        2000,
+        "",
+        "{\\b1}Bold{\\b0} and {\\i1}italic\\N{\\u1}under{\\u0}lined",
+    );
+    add_event(&mut track, 500, 900, "", "{\\an8\\pos(10,10)}Top & <tag>");
+    add_event(&mut track, 3000, 4000, "", "{\\p1}m 0 0 l 10 10{\\p0}");
+    add_event(&mut track, 3000, 3000, "", "Never shown");
+    add_event(
+        &mut track,
+        5000,
+        6000,
+        "Banner;5",
+        "{\\an5\\fs20}Middle\\N\\NSecond \\{x\\} C:\\temp\\hend",
+    );
+    track
+}
+
+fn losses(report: &ExportReport) -> Vec<(i32, LossKind)> {
+    report
+        .losses
+        .iter()
+        .map(|Loss { event, kind }| (*event, kind.clone()))
+        .collect()
+}
+
+#[test]
+fn export_srt() {
+    let library = Library::new().unwrap();
+    let mut out = Vec::new();
+    let report = track(&library).write_srt(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "1
+00:00:00,500 --> 00:00:00,900
+{\\an8}Top & <tag>
+
+2
+00:00:01,000 --> 00:00:02,000
+<b>Bold</b> and <i>italic
+<u>under</u>lined</i>
+
+3
+00:00:05,000 --> 00:00:06,000
+Middle
+Second {x} C:\\temp\u{a0}end
+
+"
+    );
+    assert_eq!(
+        losses(&report),
+        [
+            (1, LossKind::Tag("pos".to_string())),
+            (2, LossKind::Drawing),
+            (4, LossKind::Effect("Banner;5".to_string())),
+            (4, LossKind::Tag("fs".to_string())),
+            (4, LossKind::Alignment(5)),
+        ]
+    );
+    assert!(!report.is_lossless());
+}
+
+#[test]
+fn export_webvtt() {
+    let library = Library::new().unwrap();
+    let mut out = Vec::new();
+    let report = track(&library).write_webvtt(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "WEBVTT
+
+00:00:00.500 --> 00:00:00.900 line:0
+Top &amp; &lt;tag&gt;
+
+00:00:01.000 --> 00:00:02.000
+<b>Bold</b> and <i>italic
+<u>under</u>lined</i>
+
+00:00:05.000 --> 00:00:06.000 line:50%,center
+Middle
+Second {x} C:\\temp\u{a0}end
+"
+    );
+    // alignments are all kept
+    assert_eq!(report.losses.len(), 4);
+}
+
+#[test]
+fn export_lossless() {
+    let library = Library::new().unwrap();
+    let mut track = new_track(&library);
+    add_event(&mut track, 0, 1000, "", "{\\i1}Plain{\\r} text");
+
+    let mut out = Vec::new();
+    let report = track.write_srt(&mut out).unwrap();
+    assert!(report.is_lossless());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "1\n00:00:00,000 --> 00:00:01,000\n<i>Plain</i> text\n\n"
+    );
+
+    // importing the export and exporting it again changes nothing
+    let mut out = Vec::new();
+    track
+        .event_mut(0)
+        .unwrap()
+        .set_text("{\\an8}{braces} \\and \\{escapes\\}");
+    track.write_srt(&mut out).unwrap();
+    let style = track.style(1).unwrap();
+    let srt = String::from_utf8(out).unwrap();
+    let copy = library.new_track_from_srt(&srt, &style).unwrap();
+    let mut again = Vec::new();
+    copy.write_srt(&mut again).unwrap();
+    assert_eq!(String::from_utf8(again).unwrap(), srt);
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::PathBuf;
+
+use libass::{FontRequest, FontSource, FontStatus, Library};
+
+fn fonts_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libass-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn font_report_skips_broken_files() {
+    let dir = fonts_dir("font-report");
+    fs::copy("tests/fonts/DejaVuSans.ttf", dir.join("DejaVuSans.ttf")).unwrap();
+    fs::write(dir.join("broken.ttf"), b"not a font").unwrap();
+
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    let mut event = track.add_event();
+    event.set_duration(1000);
+    event.set_text("{\\fnDejaVu Sans\\b400}Regular {\\b300}light {\\b600}semibold");
+    let report = track.font_report(&library, Some(&dir), false).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].path, dir.join("broken.ttf"));
+
+    let status = |family: &str, weight: u16| {
+        let request = FontRequest {
+            family: family.to_string(),
+            weight,
+            italic: false,
+        };
+        let check = report.fonts.iter().find(|f| f.request == request).unwrap();
+        check.status.clone()
+    };
+    let source = FontSource::Directory(dir.join("DejaVuSans.ttf"));
+    assert_eq!(
+        status("DejaVu Sans", 400),
+        FontStatus::Found(source.clone())
+    );
+    // a lighter weight is drawn with the regular face as is
+    assert_eq!(
+        status("DejaVu Sans", 300),
+        FontStatus::Found(source.clone())
+    );
+    // more than 150 heavier than the only face, so emulated
+    assert_eq!(status("DejaVu Sans", 600), FontStatus::Fallback(source));
+    assert_eq!(report.missing().count(), 1);
+    assert_eq!(report.unknown().count(), 0);
+}
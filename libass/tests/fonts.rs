@@ -0,0 +1,44 @@
+use std::fs;
+use std::path::PathBuf;
+
+use libass::Library;
+
+fn fonts_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libass-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(unix)]
+#[test]
+fn fonts_from_dir_with_symlink_loop() {
+    use std::os::unix::fs::symlink;
+
+    let dir = fonts_dir("symlink-loop");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::copy("tests/fonts/DejaVuSans.ttf", dir.join("sub/DejaVuSans.ttf")).unwrap();
+    fs::write(dir.join("broken.otf"), b"not a font").unwrap();
+    symlink(&dir, dir.join("sub/loop")).unwrap();
+    symlink(dir.join("sub/DejaVuSans.ttf"), dir.join("linked.ttf")).unwrap();
+
+    let library = Library::new().unwrap();
+    let report = library.add_fonts_from_dir(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let loaded: Vec<_> = report.loaded.iter().map(|font| &font.path).collect();
+    assert_eq!(
+        loaded,
+        [&dir.join("linked.ttf"), &dir.join("sub/DejaVuSans.ttf")]
+    );
+    assert_eq!(report.families(), ["DejaVu Sans"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].path, dir.join("broken.otf"));
+}
+
+#[test]
+fn fonts_from_missing_dir() {
+    let library = Library::new().unwrap();
+    let dir = fonts_dir("missing").join("missing");
+    assert!(library.add_fonts_from_dir(&dir).is_err());
+}
@@ -0,0 +1,102 @@
+use libass::tags::{EventText, KaraokeKind, Tag};
+use libass::{Library, Syllable};
+
+fn syllables(text: &str) -> Vec<Syllable> {
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    let mut event = track.add_event();
+    event.set_start(1000);
+    event.set_duration(5000);
+    event.set_text(text);
+    let id = event.id;
+    track.event(id).unwrap().syllables()
+}
+
+fn timing(syllables: &[Syllable]) -> Vec<(i64, i64, &str, KaraokeKind)> {
+    syllables
+        .iter()
+        .map(|s| (s.start, s.end, s.text.as_str(), s.kind))
+        .collect()
+}
+
+fn tags(text: &str) -> Vec<Tag> {
+    EventText::parse(text).tags().cloned().collect()
+}
+
+#[test]
+fn karaoke_syllables() {
+    let syllables = syllables("Intro {\\k20}Ka{\\k35}ra{\\k0}{\\k50}o\\Nke");
+    assert_eq!(
+        timing(&syllables),
+        [
+            (1000, 1200, "Ka", KaraokeKind::Instant),
+            (1200, 1550, "ra", KaraokeKind::Instant),
+            (1550, 1550, "", KaraokeKind::Instant),
+            (1550, 2050, "o\\Nke", KaraokeKind::Instant),
+        ]
+    );
+    assert!(syllables.iter().all(|s| s.tags.is_empty()));
+}
+
+#[test]
+fn karaoke_kinds() {
+    let syllables = syllables("{\\kf20}A{\\K30}B{\\ko40}C{\\k10}D");
+    assert_eq!(
+        timing(&syllables),
+        [
+            (1000, 1200, "A", KaraokeKind::Fill),
+            (1200, 1500, "B", KaraokeKind::Fill),
+            (1500, 1900, "C", KaraokeKind::Outline),
+            (1900, 2000, "D", KaraokeKind::Instant),
+        ]
+    );
+}
+
+#[test]
+fn karaoke_bare_tags() {
+    // a syllable lasts a second when the duration is left out
+    let syllables = syllables("{\\k}A{\\kf}B{\\k20}C");
+    assert_eq!(
+        timing(&syllables),
+        [
+            (1000, 2000, "A", KaraokeKind::Instant),
+            (2000, 3000, "B", KaraokeKind::Fill),
+            (3000, 3200, "C", KaraokeKind::Instant),
+        ]
+    );
+}
+
+#[test]
+fn karaoke_time() {
+    let syllables = syllables("{\\k20}A{\\kt100\\k30}B{\\k10}C");
+    assert_eq!(
+        timing(&syllables),
+        [
+            (1000, 1200, "A", KaraokeKind::Instant),
+            (2000, 2300, "B", KaraokeKind::Instant),
+            (2300, 2400, "C", KaraokeKind::Instant),
+        ]
+    );
+}
+
+#[test]
+fn karaoke_tags_before_karaoke_tag() {
+    let syllables = syllables("{\\1c&HFF&\\k20}A{\\k20\\b1}B{\\i1\\k20}C");
+    assert_eq!(syllables.len(), 3);
+    assert_eq!(syllables[0].tags, tags("{\\1c&HFF&}"));
+    assert_eq!(syllables[1].tags, tags("{\\1c&HFF&\\b1}"));
+    assert_eq!(syllables[2].tags, tags("{\\1c&HFF&\\b1\\i1}"));
+}
+
+#[test]
+fn karaoke_tags_in_text() {
+    let syllables = syllables("{\\k20}A{\\b1}B{\\i1}{\\k20}C{\\r\\k20}D");
+    assert_eq!(
+        timing(&syllables).iter().map(|s| s.2).collect::<Vec<_>>(),
+        ["AB", "C", "D"]
+    );
+    // tags after a syllable's text only apply to the next one
+    assert_eq!(syllables[0].tags, tags("{\\b1}"));
+    assert_eq!(syllables[1].tags, tags("{\\b1\\i1}"));
+    assert_eq!(syllables[2].tags, tags("{\\r}"));
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+use libass::{DefaultFontProvider, FontSettings, Library, Renderer, RendererConfig, Track};
+
+struct Player {
+    library: Library,
+    renderer: Renderer<'static>,
+    track: Track<'static>,
+}
+
+impl Player {
+    fn new() -> Self {
+        let library = Library::new().unwrap();
+        let font = fs::read("tests/fonts/DejaVuSans.ttf").unwrap();
+        library.add_font("DejaVuSans.ttf", &font);
+
+        let mut renderer = library.new_owned_renderer().unwrap();
+        renderer.set_config(&RendererConfig {
+            frame_size: (160, 90),
+            fonts: Some(FontSettings {
+                default_family: Some("DejaVu Sans".to_string()),
+                provider: DefaultFontProvider::None,
+                ..FontSettings::default()
+            }),
+            ..RendererConfig::default()
+        });
+        let script = fs::read_to_string("tests/scripts/basic.ass").unwrap();
+        let track = library
+            .new_track_from_memory(script.as_bytes(), "UTF-8")
+            .unwrap()
+            .into_owned();
+        Player {
+            library,
+            renderer,
+            track,
+        }
+    }
+
+    fn shown(&mut self, now: i64) -> usize {
+        let (image, _) = self.renderer.render_frame(&self.track, now);
+        image.map_or(0, Iterator::count)
+    }
+}
+
+#[test]
+fn library_owned_handles_drop_in_any_order() {
+    let orders = [
+        ["library", "renderer", "track"],
+        ["library", "track", "renderer"],
+        ["renderer", "library", "track"],
+        ["renderer", "track", "library"],
+        ["track", "library", "renderer"],
+        ["track", "renderer", "library"],
+    ];
+    for order in &orders {
+        let mut player = Player::new();
+        assert_eq!(player.shown(1600), 2);
+        let Player {
+            library,
+            renderer,
+            track,
+        } = player;
+        let (mut library, mut renderer, mut track) = (Some(library), Some(renderer), Some(track));
+        for name in order {
+            match *name {
+                "library" => drop(library.take()),
+                "renderer" => drop(renderer.take()),
+                _ => drop(track.take()),
+            }
+            if let (Some(renderer), Some(track)) = (&mut renderer, &track) {
+                let (image, _) = renderer.render_frame(track, 1000);
+                assert_eq!(image.map_or(0, Iterator::count), 1, "{:?}", order);
+            }
+        }
+    }
+}
+
+#[test]
+fn library_owned_track_outlives_library() {
+    let track = {
+        let library = Library::new().unwrap();
+        let mut track = library.new_owned_track().unwrap();
+        track.add_event().set_text("kept");
+        track
+    };
+    assert_eq!(track.event_count(), 1);
+}
+
+#[test]
+fn library_clear_fonts_waits_for_renderers() {
+    let mut player = Player::new();
+    let library = player.library.clone();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| library.clear_fonts()));
+    assert!(result.is_err());
+    assert_eq!(player.shown(1000), 1);
+
+    drop(player.renderer);
+    library.clear_fonts();
+}
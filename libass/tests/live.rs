@@ -0,0 +1,71 @@
+use std::thread;
+
+use libass::{Library, LiveTrack, Packet};
+
+const HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,48,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,2,20,20,30,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+fn packet(read_order: i32, start: i64, duration: i64, text: &str) -> Packet {
+    Packet {
+        start,
+        duration,
+        data: format!("{},0,Default,,0,0,0,,{}", read_order, text).into_bytes(),
+    }
+}
+
+fn texts(live: &LiveTrack) -> Vec<String> {
+    live.track()
+        .events()
+        .map(|e| e.text().into_owned())
+        .collect()
+}
+
+#[test]
+fn live_track() {
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    track.process_codec_private(&mut HEADER.as_bytes().to_vec());
+    let (mut live, sender) = LiveTrack::new(track);
+
+    assert_eq!(live.update(0), 0);
+    assert!(live.is_connected());
+
+    let producer = sender.clone();
+    thread::spawn(move || {
+        producer.send(packet(0, 1000, 2000, "First")).unwrap();
+        producer.send(packet(1, 2000, 2000, "Second")).unwrap();
+    })
+    .join()
+    .unwrap();
+    assert_eq!(live.update(500), 2);
+    assert_eq!(texts(&live), ["First", "Second"]);
+
+    // events that are over are dropped
+    assert_eq!(live.update(3000), 0);
+    assert_eq!(texts(&live), ["Second"]);
+
+    // packets that are over when they arrive are skipped
+    sender.send(packet(2, 1000, 500, "Late")).unwrap();
+    sender
+        .send(packet(3, 3500, i64::MAX, "Open ended"))
+        .unwrap();
+    assert_eq!(live.update(3500), 1);
+    assert_eq!(texts(&live), ["Second", "Open ended"]);
+
+    assert_eq!(live.update(4000), 0);
+    assert_eq!(texts(&live), ["Open ended"]);
+    assert!(live.is_connected());
+
+    drop(sender);
+    assert_eq!(live.update(5000), 0);
+    assert!(!live.is_connected());
+    assert_eq!(live.into_track().event_count(), 1);
+}
@@ -0,0 +1,328 @@
+#![cfg(feature = "matroska")]
+
+use std::io::{Cursor, Write};
+
+use libass::matroska::Matroska;
+use libass::Library;
+
+// an element with the id as written, including its length marker
+fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    if body.len() < 0x7F {
+        out.push(0x80 | body.len() as u8);
+    } else {
+        out.push(0x01);
+        out.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+    }
+    out.extend_from_slice(body);
+    out
+}
+
+fn uint(id: &[u8], value: u64) -> Vec<u8> {
+    element(id, &value.to_be_bytes())
+}
+
+// a block of `track` at `relative` to the cluster's timecode
+fn block(track: u8, relative: i16, data: &[u8]) -> Vec<u8> {
+    let mut body = vec![0x80 | track];
+    body.extend_from_slice(&relative.to_be_bytes());
+    body.push(0);
+    body.extend_from_slice(data);
+    body
+}
+
+const HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+Format: ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+fn file() -> Vec<u8> {
+    let tracks = [
+        // video
+        element(
+            &[0xAE],
+            &[
+                uint(&[0xD7], 1),
+                uint(&[0x83], 1),
+                element(&[0x86], b"V_VP9"),
+            ]
+            .concat(),
+        ),
+        element(
+            &[0xAE],
+            &[
+                uint(&[0xD7], 2),
+                uint(&[0x83], 0x11),
+                element(&[0x86], b"S_TEXT/ASS"),
+                element(&[0x63, 0xA2], HEADER.as_bytes()),
+            ]
+            .concat(),
+        ),
+        // header stripping of the blocks only
+        element(
+            &[0xAE],
+            &[
+                uint(&[0xD7], 3),
+                uint(&[0x83], 0x11),
+                element(&[0x86], b"S_TEXT/UTF8\0"),
+                element(&[0x53, 0x6E], b"Commentary"),
+                element(&[0x22, 0xB5, 0x9C], b"eng"),
+                uint(&[0x88], 0),
+                element(
+                    &[0x6D, 0x80],
+                    &element(
+                        &[0x62, 0x40],
+                        &element(
+                            &[0x50, 0x34],
+                            &[uint(&[0x42, 0x54], 3), element(&[0x42, 0x55], b"<i>")].concat(),
+                        ),
+                    ),
+                ),
+            ]
+            .concat(),
+        ),
+        // encrypted
+        element(
+            &[0xAE],
+            &[
+                uint(&[0xD7], 4),
+                uint(&[0x83], 0x11),
+                element(&[0x86], b"S_TEXT/UTF8"),
+                element(
+                    &[0x6D, 0x80],
+                    &element(&[0x62, 0x40], &uint(&[0x50, 0x33], 1)),
+                ),
+            ]
+            .concat(),
+        ),
+    ]
+    .concat();
+
+    // of unknown size, ended by the attachments
+    let mut cluster = vec![0x1F, 0x43, 0xB6, 0x75, 0xFF];
+    cluster.extend(
+        [
+            uint(&[0xE7], 500),
+            element(&[0xA3], &block(1, 0, &[0; 200])),
+            element(
+                &[0xA0],
+                &[
+                    element(&[0xA1], &block(2, 500, b"0,0,Default,,0,0,0,,Hello")),
+                    uint(&[0x9B], 1000),
+                ]
+                .concat(),
+            ),
+            element(&[0xA3], &block(3, 1000, b"Hi</i>")),
+            element(&[0xA3], &block(4, 0, b"secret")),
+            // out of order
+            element(&[0xA3], &block(3, 0, b"First</i>\r\n")),
+        ]
+        .concat(),
+    );
+
+    let attachments = [
+        element(
+            &[0x61, 0xA7],
+            &[
+                element(&[0x46, 0x6E], b"Font.TTF"),
+                element(&[0x46, 0x60], b"application/octet-stream"),
+                element(&[0x46, 0x5C], &[1, 2, 3]),
+            ]
+            .concat(),
+        ),
+        element(
+            &[0x61, 0xA7],
+            &[
+                element(&[0x46, 0x6E], b"cover.jpg"),
+                element(&[0x46, 0x60], b"image/jpeg"),
+                element(&[0x46, 0x5C], &[0; 300]),
+            ]
+            .concat(),
+        ),
+    ]
+    .concat();
+
+    let segment = [
+        element(
+            &[0x15, 0x49, 0xA9, 0x66],
+            &uint(&[0x2A, 0xD7, 0xB1], 2_000_000),
+        ),
+        element(&[0x16, 0x54, 0xAE, 0x6B], &tracks),
+        cluster,
+        element(&[0x19, 0x41, 0xA4, 0x69], &attachments),
+    ]
+    .concat();
+    matroska(&segment)
+}
+
+fn matroska(segment: &[u8]) -> Vec<u8> {
+    [
+        element(
+            &[0x1A, 0x45, 0xDF, 0xA3],
+            &element(&[0x42, 0x82], b"matroska"),
+        ),
+        element(&[0x18, 0x53, 0x80, 0x67], segment),
+    ]
+    .concat()
+}
+
+// content encodings with a single compression
+fn compression(scope: u64, algorithm: u64, settings: &[u8]) -> Vec<u8> {
+    element(
+        &[0x6D, 0x80],
+        &element(
+            &[0x62, 0x40],
+            &[
+                uint(&[0x50, 0x32], scope),
+                element(
+                    &[0x50, 0x34],
+                    &[
+                        uint(&[0x42, 0x54], algorithm),
+                        element(&[0x42, 0x55], settings),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        ),
+    )
+}
+
+#[test]
+fn matroska_read() {
+    let mkv = Matroska::read(Cursor::new(file())).unwrap();
+    assert_eq!(mkv.tracks.len(), 2);
+
+    let ass = &mkv.tracks[0];
+    assert_eq!(
+        (ass.number, ass.codec_id.as_str(), ass.is_ass(), ass.default),
+        (2, "S_TEXT/ASS", true, true)
+    );
+    assert_eq!(ass.codec_private, HEADER.as_bytes());
+    assert_eq!(ass.blocks.len(), 1);
+    // timecodes are in units of 2 ms
+    assert_eq!(
+        (ass.blocks[0].timecode, ass.blocks[0].duration),
+        (2000, Some(2000))
+    );
+
+    let srt = &mkv.tracks[1];
+    assert_eq!(srt.codec_id, "S_TEXT/UTF8");
+    assert!(!srt.is_ass() && !srt.default);
+    assert_eq!(srt.name.as_deref(), Some("Commentary"));
+    assert_eq!(srt.language.as_deref(), Some("eng"));
+    let blocks: Vec<_> = srt
+        .blocks
+        .iter()
+        .map(|b| (b.timecode, b.duration, &b.data[..]))
+        .collect();
+    assert_eq!(
+        blocks,
+        [
+            (1000, None, &b"<i>First</i>\r\n"[..]),
+            (3000, None, &b"<i>Hi</i>"[..]),
+        ]
+    );
+
+    let names: Vec<_> = mkv.attachments.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, ["Font.TTF", "cover.jpg"]);
+    assert_eq!(mkv.attachments[0].data, [1, 2, 3]);
+    assert!(mkv.attachments[0].is_font());
+    assert!(!mkv.attachments[1].is_font());
+}
+
+#[test]
+fn matroska_load() {
+    let library = Library::new().unwrap();
+    let mkv = Matroska::read(Cursor::new(file())).unwrap();
+    let events = |index: usize| -> Vec<_> {
+        let track = mkv.tracks[index].load(&library).unwrap();
+        track
+            .events()
+            .map(|e| (e.start(), e.duration(), e.text().into_owned()))
+            .collect()
+    };
+    assert_eq!(events(0), [(2000, 2000, "Hello".to_string())]);
+    // the last block without a duration doesn't last
+    assert_eq!(
+        events(1),
+        [
+            (1000, 2000, "{\\i1}First{\\i0}".to_string()),
+            (3000, 0, "{\\i1}Hi{\\i0}".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn matroska_compressed_codec_private() {
+    let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib.write_all(HEADER.as_bytes()).unwrap();
+    let zlib = zlib.finish().unwrap();
+    let stripped = &HEADER.as_bytes()["[Script Info]\n".len()..];
+
+    let track = |number: u64, private: &[u8], encodings: Vec<u8>| {
+        element(
+            &[0xAE],
+            &[
+                uint(&[0xD7], number),
+                uint(&[0x83], 0x11),
+                element(&[0x86], b"S_TEXT/ASS"),
+                element(&[0x63, 0xA2], private),
+                encodings,
+            ]
+            .concat(),
+        )
+    };
+    let tracks = [
+        track(1, &zlib, compression(2, 0, &[])),
+        track(2, stripped, compression(2, 3, b"[Script Info]\n")),
+        // both the codec private data and the blocks
+        track(3, stripped, compression(3, 3, b"[Script Info]\n")),
+    ]
+    .concat();
+    let cluster = [
+        uint(&[0xE7], 0),
+        element(&[0xA3], &block(1, 0, b"0,0,Default,,0,0,0,,zlib")),
+        element(&[0xA3], &block(2, 0, b"0,0,Default,,0,0,0,,stripped")),
+        element(&[0xA3], &block(3, 0, b"0,0,Default,,0,0,0,,both")),
+    ]
+    .concat();
+    let segment = [
+        element(&[0x16, 0x54, 0xAE, 0x6B], &tracks),
+        element(&[0x1F, 0x43, 0xB6, 0x75], &cluster),
+    ]
+    .concat();
+
+    let mkv = Matroska::read(Cursor::new(matroska(&segment))).unwrap();
+    assert_eq!(mkv.tracks.len(), 3);
+    for track in &mkv.tracks {
+        assert_eq!(track.codec_private, HEADER.as_bytes());
+    }
+    // the blocks are only decompressed when the scope includes them
+    assert_eq!(mkv.tracks[0].blocks[0].data, b"0,0,Default,,0,0,0,,zlib");
+    assert_eq!(
+        mkv.tracks[1].blocks[0].data,
+        b"0,0,Default,,0,0,0,,stripped"
+    );
+    assert_eq!(
+        mkv.tracks[2].blocks[0].data,
+        b"[Script Info]\n0,0,Default,,0,0,0,,both"
+    );
+}
+
+#[test]
+fn matroska_invalid() {
+    assert!(Matroska::read(Cursor::new(b"not a Matroska file".to_vec())).is_err());
+    assert!(Matroska::read(Cursor::new(Vec::new())).is_err());
+
+    // cut in the middle of the tracks
+    let mut file = file();
+    file.truncate(150);
+    assert!(Matroska::read(Cursor::new(file)).is_err());
+}
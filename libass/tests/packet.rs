@@ -0,0 +1,75 @@
+use libass::{Library, Track};
+
+const SCRIPT: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1280
+PlayResY: 720
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Main,Arial,48,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,2,20,20,30,1
+Style: Sign,Georgia,36,&H0000FFFF,&H000000FF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,0,0,8,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:00:05.00,0:00:07.50,Main,Alice,0,0,0,,Second, with a comma\\Nand a break
+Dialogue: 1,0:00:01.00,0:00:03.00,Sign,,15,25,40,Banner;20,{\\pos(640,100)\\b1}First
+Dialogue: 0,0:00:05.00,0:00:06.00,Main,Bob,0,0,0,,Same start
+";
+
+fn events(track: &Track) -> Vec<String> {
+    let mut events: Vec<_> = track
+        .events()
+        .map(|e| {
+            format!(
+                "{} {} {} {} {} {} {} {} {}",
+                e.start(),
+                e.end(),
+                e.layer(),
+                e.margin_l(),
+                e.margin_r(),
+                e.margin_v(),
+                e.name(),
+                e.effect(),
+                e.text()
+            )
+        })
+        .collect();
+    events.sort();
+    events
+}
+
+fn style_names(track: &Track) -> Vec<String> {
+    (0..track.style_count() as i32)
+        .map(|id| track.style(id).unwrap().name.into_string().unwrap())
+        .collect()
+}
+
+#[test]
+fn packets_round_trip() {
+    let library = Library::new().unwrap();
+    let original = library
+        .new_track_from_memory(SCRIPT.as_bytes(), "UTF-8")
+        .unwrap();
+    let packets = original.packetize();
+
+    assert_eq!(packets.packets.len(), 3);
+    assert!(packets.packets.windows(2).all(|w| w[0].start <= w[1].start));
+    assert_eq!(
+        packets.packets[0].data,
+        b"1,1,Sign,,15,25,40,Banner;20,{\\pos(640,100)\\b1}First".to_vec()
+    );
+    let header = String::from_utf8(packets.codec_private.clone()).unwrap();
+    assert!(header.contains("Style: Sign,"));
+    assert!(!header.contains("Dialogue:"));
+
+    let mut track = library.new_track().unwrap();
+    track.process_codec_private(&mut packets.codec_private.clone());
+    for packet in &packets.packets {
+        track.process_chunk(&mut packet.data.clone(), packet.start, packet.duration);
+    }
+
+    assert_eq!(style_names(&track), style_names(&original));
+    assert_eq!(events(&track), events(&original));
+    assert_eq!(track.packetize(), packets);
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+use libass::{DefaultFontProvider, FontSettings, Library, RenderPool, RendererConfig};
+
+fn config() -> RendererConfig {
+    RendererConfig {
+        frame_size: (160, 90),
+        fonts: Some(FontSettings {
+            default_family: Some("DejaVu Sans".to_string()),
+            provider: DefaultFontProvider::None,
+            ..FontSettings::default()
+        }),
+        ..RendererConfig::default()
+    }
+}
+
+fn library() -> Library {
+    let library = Library::new().unwrap();
+    let font = fs::read("tests/fonts/DejaVuSans.ttf").unwrap();
+    library.add_font("DejaVuSans.ttf", &font);
+    library
+}
+
+fn script() -> String {
+    fs::read_to_string("tests/scripts/basic.ass").unwrap()
+}
+
+#[test]
+fn pool_keeps_order() {
+    let library = library();
+    let track = library
+        .new_track_from_memory(script().as_bytes(), "UTF-8")
+        .unwrap();
+    let mut pool = RenderPool::new(&library, 3, &config()).unwrap();
+    assert_eq!(pool.threads(), 3);
+    // nothing to render before a track is set
+    let frames = pool.render(&[1000, 1600]);
+    assert!(frames.len() == 2 && frames.iter().all(Vec::is_empty));
+
+    pool.set_track(&track).unwrap();
+    let times = [1600, 0, 1000, 2500, 1800, 600, 100];
+    let frames = pool.render(&times);
+    // the rectangles shown, by their position
+    let shown: Vec<Vec<_>> = frames
+        .iter()
+        .map(|layers| layers.iter().map(|l| (l.x, l.y)).collect())
+        .collect();
+    let both = vec![(20, 10), (100, 50)];
+    let first = vec![(20, 10)];
+    assert_eq!(
+        shown,
+        [
+            both.clone(),
+            vec![],
+            first.clone(),
+            vec![],
+            both,
+            first,
+            vec![]
+        ]
+    );
+
+    let counts = pool.render_with(&times, |now, image| (now, image.map_or(0, Iterator::count)));
+    assert_eq!(
+        counts,
+        [
+            (1600, 2),
+            (0, 0),
+            (1000, 1),
+            (2500, 0),
+            (1800, 2),
+            (600, 1),
+            (100, 0)
+        ]
+    );
+    assert!(pool.render(&[]).is_empty());
+}
+
+#[test]
+fn pool_rejects_other_libraries() {
+    let library = library();
+    let other = Library::new().unwrap();
+    let track = other
+        .new_track_from_memory(script().as_bytes(), "UTF-8")
+        .unwrap();
+    let mut pool = RenderPool::new(&library, 2, &config()).unwrap();
+    assert!(pool.set_track(&track).is_err());
+    assert!(pool.render(&[1000])[0].is_empty());
+}
+
+#[test]
+fn pool_propagates_panics() {
+    let library = library();
+    let track = library
+        .new_track_from_memory(script().as_bytes(), "UTF-8")
+        .unwrap();
+    let mut pool = RenderPool::new(&library, 2, &config()).unwrap();
+    pool.set_track(&track).unwrap();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.render_with(&[0, 500, 1000, 1500], |now, _| {
+            if now == 1500 {
+                panic!("frame {}", now);
+            }
+            now
+        })
+    }));
+    let message = result.unwrap_err();
+    assert_eq!(message.downcast_ref::<String>().unwrap(), "frame 1500");
+
+    // the pool still works afterwards
+    assert_eq!(pool.render_with(&[0, 500], |now, _| now), [0, 500]);
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "serde")]
+
+use libass::{
+    CacheLimits, DefaultFontProvider, FontSettings, Hinting, Margins, RendererConfig, ShapingLevel,
+};
+
+#[test]
+fn renderer_config_round_trip() {
+    let config = RendererConfig {
+        frame_size: (1920, 1080),
+        storage_size: (1440, 1080),
+        fonts: Some(FontSettings {
+            default_font: Some("/usr/share/fonts/DejaVuSans.ttf".to_string()),
+            default_family: Some("DejaVu Sans".to_string()),
+            provider: DefaultFontProvider::Fontconfig,
+            fontconfig_config: None,
+            update_fontconfig_cache: false,
+        }),
+        margins: Margins {
+            top: 10,
+            bottom: 20,
+            left: 0,
+            right: -5,
+        },
+        use_margins: true,
+        pixel_aspect_ratio: 4.0 / 3.0,
+        font_scale: 1.25,
+        hinting: Hinting::Light,
+        line_spacing: 2.5,
+        line_position: 10.0,
+        cache_limits: CacheLimits {
+            glyph_max: 1000,
+            bitmap_max_size: 64,
+        },
+        shaper: ShapingLevel::Simple,
+    };
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<RendererConfig>(&json).unwrap(),
+        config
+    );
+
+    let default = RendererConfig::default();
+    let json = serde_json::to_string(&default).unwrap();
+    assert_eq!(
+        serde_json::from_str::<RendererConfig>(&json).unwrap(),
+        default
+    );
+}
+
+#[test]
+fn renderer_config_missing_fields() {
+    let config: RendererConfig =
+        serde_json::from_str(r#"{"font_scale": 2.0, "fonts": {"default_family": "Arial"}}"#)
+            .unwrap();
+    assert_eq!(
+        config,
+        RendererConfig {
+            font_scale: 2.0,
+            fonts: Some(FontSettings {
+                default_family: Some("Arial".to_string()),
+                ..FontSettings::default()
+            }),
+            ..RendererConfig::default()
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<RendererConfig>("{}").unwrap(),
+        RendererConfig::default()
+    );
+}
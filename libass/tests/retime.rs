@@ -0,0 +1,132 @@
+use libass::{Library, Timebase, Track};
+
+fn track<'a>(library: &'a Library, events: &[(i64, i64, &str)]) -> Track<'a> {
+    let mut track = library.new_track().unwrap();
+    for &(start, end, text) in events {
+        let mut event = track.add_event();
+        event.set_start(start);
+        event.set_duration(end - start);
+        event.set_text(text);
+    }
+    track
+}
+
+fn events(track: &Track) -> Vec<(i64, i64, String)> {
+    track
+        .events()
+        .map(|e| (e.start(), e.end(), e.text().into_owned()))
+        .collect()
+}
+
+#[test]
+fn retime_shift() {
+    let library = Library::new().unwrap();
+    let text = "{\\move(0,0,10,10,100,200)\\fad(100,200)}a";
+    let mut track = track(&library, &[(1000, 2000, text), (3000, 4000, "b")]);
+    track.shift(500);
+    // times relative to the event don't change
+    assert_eq!(
+        events(&track),
+        [
+            (1500, 2500, text.to_string()),
+            (3500, 4500, "b".to_string())
+        ]
+    );
+
+    track.shift_if(-1000, |e| e.text() == "b");
+    assert_eq!(events(&track)[1], (2500, 3500, "b".to_string()));
+}
+
+#[test]
+fn retime_animations() {
+    let library = Library::new().unwrap();
+    let mut track = track(
+        &library,
+        &[
+            (1000, 2000, "{\\move(0,0,10,10,100,200)\\fad(100,200)}a"),
+            (
+                1000,
+                2000,
+                "{\\t(0,500,\\fs20)\\fade(255,0,255,0,100,900,1000)}b",
+            ),
+        ],
+    );
+    track.rescale(2.0);
+    assert_eq!(
+        events(&track),
+        [
+            (
+                2000,
+                4000,
+                "{\\move(0,0,10,10,200,400)\\fad(200,400)}a".to_string()
+            ),
+            (
+                2000,
+                4000,
+                "{\\t(0,1000,\\fs20)\\fade(255,0,255,0,200,1800,2000)}b".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn retime_karaoke() {
+    let library = Library::new().unwrap();
+    let mut track = track(
+        &library,
+        &[
+            (1000, 2000, "{\\k10}a{\\k15}b{\\kf7}c"),
+            (1000, 2000, "{\\k10}a{\\kt50\\k10}b"),
+            (1000, 2000, "{\\k12.5}a{\\k12.5}b"),
+        ],
+    );
+    track.rescale(1.5);
+    // the syllables keep whole centiseconds and end where they would
+    // without rounding: 15 + 22.5 + 10.5 = 48, and 18.75 + 18.75 = 37.5
+    assert_eq!(
+        events(&track),
+        [
+            (1500, 3000, "{\\k15}a{\\k23}b{\\kf10}c".to_string()),
+            (1500, 3000, "{\\k15}a{\\kt75\\k15}b".to_string()),
+            (1500, 3000, "{\\k19}a{\\k19}b".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn retime_piecewise() {
+    let library = Library::new().unwrap();
+    let mut track = track(
+        &library,
+        &[(0, 1000, "{\\t(100,200,\\fs20)}a"), (2000, 3000, "b")],
+    );
+    // twice as slow up to 1000, then shifted
+    track.retime_piecewise(&[(1000, 2000), (0, 0), (2000, 3000)]);
+    assert_eq!(
+        events(&track),
+        [
+            (0, 2000, "{\\t(200,400,\\fs20)}a".to_string()),
+            (3000, 4000, "b".to_string()),
+        ]
+    );
+
+    track.retime_piecewise(&[(0, 100)]);
+    assert_eq!(events(&track)[1], (3100, 4100, "b".to_string()));
+    track.retime_piecewise(&[]);
+    assert_eq!(events(&track)[1], (3100, 4100, "b".to_string()));
+}
+
+#[test]
+fn retime_frames() {
+    let library = Library::new().unwrap();
+    let mut track = track(&library, &[(1000, 2000, "a")]);
+    let pal = Timebase::constant(25, 1).unwrap();
+    track.shift_frames(2, &pal);
+    assert_eq!(events(&track)[0], (1080, 2080, "a".to_string()));
+
+    // frame 24 and 48 at 23.976 fps are at 960 and 1920 ms at 25 fps
+    let mut track = self::track(&library, &[(1001, 2002, "a")]);
+    let film = Timebase::constant(24000, 1001).unwrap();
+    track.convert_timebase(&film, &pal);
+    assert_eq!(events(&track)[0], (960, 1920, "a".to_string()));
+}
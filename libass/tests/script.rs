@@ -0,0 +1,235 @@
+use libass::script::{Attachment, EventKind, InfoLine, Script, Section, Version};
+use libass::{Library, Track};
+
+const SCRIPT: &str = "\u{feff}; written by hand
+[Script Info]
+; Script generated by Aegisub
+Title:Example
+ScriptType: v4.00+
+PlayResX: 1280
+PlayResY: 720
+WrapStyle: 0
+ScaledBorderAndShadow: yes
+
+[Aegisub Project Garbage]
+Audio File: example.mkv
+Video Position: 120
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Main, Arial ,48,&H00FFFFFF,&H000000ff&,&H00000000,&H80000000,0,0,0,0,100.00,100.00,0,0,1,2,1,2,20,20,30,1
+Style: Sign,Georgia,36.5,&H0000FFFF,&H000000FF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,0,0,8,10,10,10,1
+
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Comment: 0,0:00:00.00,0:00:00.00,Main,,0,0,0,,Typesetting starts here
+Dialogue: 0,0:00:01.00,0:00:03.50,Main,Alice,0,0,0,, padded, with commas\\Nand a break
+Dialogue: 1,0:00:02.00,0:00:04.00,Sign,,15,25,40,Banner;20,{\\pos(640,100)\\b1}Sign
+Picture: 0,0:00:00.00,0:00:01.00,Main,,0,0,0,,logo.png
+
+[Fonts]
+fontname: font.ttf
+!!!!
+";
+
+fn parse(text: &str) -> Script {
+    Script::parse(text)
+}
+
+#[test]
+fn script_round_trip() {
+    let script = parse(SCRIPT);
+    let written = script.to_string();
+    assert_eq!(parse(&written), script);
+    // writing is stable once normalized
+    assert_eq!(parse(&written).to_string(), written);
+
+    assert_eq!(script.preamble, ["; written by hand"]);
+    assert_eq!(script.sections.len(), 5);
+    assert!(matches!(&script.sections[1], Section::Unknown(u) if u.lines.len() == 2));
+    let info = script.script_info().unwrap();
+    assert_eq!(
+        info.lines[0],
+        InfoLine::Comment(" Script generated by Aegisub".to_string())
+    );
+    assert_eq!(info.get("title"), Some("Example"));
+
+    let styles = script.styles().unwrap();
+    assert_eq!(styles.version, Version::V4Plus);
+    let main = styles.styles().next().unwrap();
+    assert_eq!(main.font_name, "Arial");
+    assert_eq!(main.secondary_colour.to_abgr(), 0x000000ff);
+
+    let events: Vec<_> = script.events().unwrap().events().collect();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].kind, EventKind::Comment);
+    assert_eq!(events[1].text, " padded, with commas\\Nand a break");
+    assert_eq!(events[1].end, 3500);
+    assert_eq!(script.fonts().unwrap().files[0].name, "font.ttf");
+}
+
+#[test]
+fn script_normalization() {
+    let written = parse(SCRIPT).to_string();
+    assert!(written.starts_with("; written by hand\n[Script Info]\n"));
+    assert!(written.contains("\nTitle: Example\n"));
+    assert!(written.contains("\nStyle: Main,Arial,48,&H00FFFFFF,&H000000FF,"));
+    assert!(written.contains(",100,100,0,0,1,2,1,2,20,20,30,1\n"));
+    assert!(written.contains("\nStyle: Sign,Georgia,36.5,"));
+    // one blank line between sections
+    assert!(written.contains(",10,10,10,1\n\n[Events]\n"));
+    assert!(written.contains("\nPicture: 0,0:00:00.00,0:00:01.00,Main,,0,0,0,,logo.png\n"));
+    assert!(written.contains(",Alice,0,0,0,, padded, with commas\\Nand a break\n"));
+}
+
+#[test]
+fn script_round_trip_ssa() {
+    let text = "[Script Info]
+ScriptType: v4.00
+
+[V4 Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, TertiaryColour, BackColour, Bold, Italic, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, AlphaLevel, Encoding
+Style: Default,Arial,20,16777215,65535,65535,-2147483640,-1,0,1,1,2,2,10,10,10,0,0
+
+[Events]
+Format: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0000,0000,0000,,Hello
+";
+    let script = parse(text);
+    assert!(script.is_ssa());
+    let written = script.to_string();
+    assert_eq!(parse(&written), script);
+    assert!(written.contains("\nStyle: Default,Arial,20,16777215,65535,65535,-2147483640,"));
+    assert!(written.contains("\nDialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hello\n"));
+}
+
+const TRACK_SCRIPT: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 640
+PlayResY: 360
+ScaledBorderAndShadow: yes
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Main,Arial,48,&H00FFFFFF,&H000000FF,&H00102030,&H80000000,-1,0,-1,0,100,100,0,0,1,2,1,2,20,20,30,1
+Style: Sign,Georgia,36.5,&H4000FFFF,&HFF0000FF,&H00000000,&H00000000,0,-1,0,-1,120,80,1.5,0,3,0,0,7,10,10,10,0
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:00:01.00,0:00:03.50,Main,Alice,0,0,0,,First{\\i1}line
+Dialogue: 2,0:01:02.03,1:00:00.99,Sign,,15,25,40,Banner;20,{\\pos(320,50)}Sign
+";
+
+fn track_script(track: &Track) -> String {
+    let mut out = Vec::new();
+    track.write_ass(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn track_write_ass_round_trip() {
+    let library = Library::new().unwrap();
+    let track = library
+        .new_track_from_memory(TRACK_SCRIPT.as_bytes(), "UTF-8")
+        .unwrap();
+    let written = track_script(&track);
+    assert!(written.contains("\nPlayResX: 640\nPlayResY: 360\n"));
+    assert!(written.contains("\nScaledBorderAndShadow: yes\n"));
+    assert!(written
+        .contains("\nStyle: Main,Arial,48,&H00FFFFFF,&H000000FF,&H00102030,&H80000000,-1,0,-1,0,"));
+    assert!(written.contains(
+        "\nStyle: Sign,Georgia,36.5,&H4000FFFF,&HFF0000FF,&H00000000,&H00000000,0,-1,0,-1,120,80,"
+    ));
+    assert!(written.contains(
+        "\nDialogue: 2,0:01:02.03,1:00:00.99,Sign,,15,25,40,Banner;20,{\\pos(320,50)}Sign\n"
+    ));
+
+    let reloaded = library
+        .new_track_from_memory(written.as_bytes(), "UTF-8")
+        .unwrap();
+    assert_eq!(reloaded.style_count(), track.style_count());
+    for id in 0..track.style_count() as i32 {
+        let (style, copy) = (track.style(id).unwrap(), reloaded.style(id).unwrap());
+        assert_eq!(copy.name, style.name);
+        assert_eq!(copy.font_name, style.font_name);
+        assert_eq!(copy.font_size, style.font_size);
+        assert_eq!(copy.primary_color, style.primary_color);
+        assert_eq!(copy.secondary_color, style.secondary_color);
+        assert_eq!(copy.outline_color, style.outline_color);
+        assert_eq!(copy.back_color, style.back_color);
+        assert_eq!(copy.bold, style.bold);
+        assert_eq!(copy.italic, style.italic);
+        assert_eq!(copy.underline, style.underline);
+        assert_eq!(copy.strikeout, style.strikeout);
+        assert_eq!(copy.scale_x, style.scale_x);
+        assert_eq!(copy.alignment, style.alignment);
+    }
+    let sign = reloaded.style(2).unwrap();
+    assert_eq!(sign.primary_color, 0xFFFF_0040);
+    assert!(sign.italic && sign.strikeout && !sign.bold);
+
+    let events: Vec<_> = track.events().collect();
+    let copies: Vec<_> = reloaded.events().collect();
+    assert_eq!(copies.len(), events.len());
+    for (event, copy) in events.iter().zip(&copies) {
+        assert_eq!(copy.start(), event.start());
+        assert_eq!(copy.duration(), event.duration());
+        assert_eq!(copy.layer(), event.layer());
+        assert_eq!(copy.style(), event.style());
+        assert_eq!(copy.name(), event.name());
+        assert_eq!(copy.effect(), event.effect());
+        assert_eq!(copy.text(), event.text());
+    }
+    assert_eq!((copies[1].start(), copies[1].end()), (62_030, 3_600_990));
+
+    // writing is stable
+    assert_eq!(track_script(&reloaded), written);
+}
+
+#[test]
+fn script_attachment_encoding() {
+    assert_eq!(Attachment::encode("a", &[0, 0, 0]).lines, ["!!!!"]);
+    // a partial group is written without padding
+    assert_eq!(Attachment::encode("a", &[0xff]).lines, ["`Q"]);
+    assert!(Attachment::encode("a", &[]).lines.is_empty());
+
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 + i / 256) as u8).collect();
+    for len in (0..=5).chain([80, 1000]) {
+        let attachment = Attachment::encode("a", &data[..len]);
+        assert!(attachment.lines.iter().all(|l| l.len() <= 80));
+        assert_eq!(attachment.decode(), &data[..len]);
+    }
+    assert_eq!(Attachment::encode("a", &data).lines.len(), 17);
+}
+
+#[test]
+fn script_embed_font() {
+    let data: Vec<u8> = (0..=255).collect();
+    let mut script = parse(
+        "[Script Info]
+ScriptType: v4.00+
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+",
+    );
+    script.embed_font("first.ttf", &data);
+    script.embed_font("second.ttf", &data[..100]);
+
+    let written = script.to_string();
+    assert!(written.find("[Fonts]").unwrap() < written.find("[Events]").unwrap());
+    let script = parse(&written);
+    let fonts = &script.fonts().unwrap().files;
+    let files: Vec<_> = fonts
+        .iter()
+        .map(|f| (f.name.as_str(), f.decode()))
+        .collect();
+    assert_eq!(
+        files,
+        [
+            ("first.ttf", data.clone()),
+            ("second.ttf", data[..100].to_vec())
+        ]
+    );
+}
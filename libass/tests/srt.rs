@@ -0,0 +1,121 @@
+use libass::srt::{self, Cue};
+use libass::Library;
+
+const SRT: &str = "\u{feff}1
+00:00:01,000 --> 00:00:04,000
+<i>First</i> line
+second line
+
+2
+00:00:05,500 --> 00:00:06,000 X1:10 X2:100 Y1:10 Y2:50
+Coordinates are ignored
+3
+00:01:00,000 --> 01:00:00,001
+Missing blank line before
+
+not a cue
+00:00:10 --> broken
+";
+
+#[test]
+fn srt_parse() {
+    assert_eq!(
+        srt::parse(SRT),
+        [
+            Cue {
+                start: 1000,
+                end: 4000,
+                text: "<i>First</i> line\nsecond line".to_string(),
+            },
+            Cue {
+                start: 5500,
+                end: 6000,
+                text: "Coordinates are ignored".to_string(),
+            },
+            Cue {
+                start: 60_000,
+                end: 3_600_001,
+                text: "Missing blank line before".to_string(),
+            },
+        ]
+    );
+    assert!(srt::parse("").is_empty());
+}
+
+#[test]
+fn srt_parse_time() {
+    assert_eq!(srt::parse_time("00:00:01,000"), Some(1000));
+    assert_eq!(srt::parse_time(" 01:02:03.456 "), Some(3_723_456));
+    assert_eq!(srt::parse_time("0:0:1,5"), Some(1500));
+    assert_eq!(srt::parse_time("00:00:01,05"), Some(1050));
+    assert_eq!(srt::parse_time("02:03"), Some(123_000));
+    assert_eq!(srt::parse_time("00:00:01,0001"), None);
+    assert_eq!(srt::parse_time("00:00:01,"), None);
+    assert_eq!(srt::parse_time("1:00:00:01,000"), None);
+    assert_eq!(srt::parse_time("aa:00:01,000"), None);
+    assert_eq!(srt::format_time(3_723_456), "01:02:03,456");
+    assert_eq!(srt::format_time(-5), "00:00:00,000");
+}
+
+#[test]
+fn srt_tags_to_ass() {
+    assert_eq!(
+        srt::to_ass_text("<b>Bold</b> <I>italic</I>\n<u>u</u> <s>s</s>"),
+        "{\\b1}Bold{\\b0} {\\i1}italic{\\i0}\\N{\\u1}u{\\u0} {\\s1}s{\\s0}"
+    );
+    assert_eq!(
+        srt::to_ass_text("<font color=\"#ff8000\" face='Comic Sans' size=30>A</font>B"),
+        "{\\c&H0080FF&\\fnComic Sans\\fs30}A{\\c\\fn\\fs}B"
+    );
+    assert_eq!(
+        srt::to_ass_text("<font color=red>R</font>"),
+        "{\\c&H0000FF&}R{\\c}"
+    );
+    assert_eq!(srt::to_ass_text("<ruby>unknown</ruby>"), "unknown");
+    assert_eq!(srt::to_ass_text("a\r\nb"), "a\\Nb");
+    // an unclosed tag is text
+    assert_eq!(srt::to_ass_text("1 < 2"), "1 < 2");
+}
+
+#[test]
+fn srt_escapes_ass_syntax() {
+    assert_eq!(
+        srt::to_ass_text("{not a tag} <i>{}</i>"),
+        "\\{not a tag\\} {\\i1}\\{\\}{\\i0}"
+    );
+    assert_eq!(
+        srt::to_ass_text("C:\\new\\Name \\h \\{x} \\s\\"),
+        "C:\\\u{2060}new\\\u{2060}Name \\\u{2060}h \\\u{2060}\\{x\\} \\s\\"
+    );
+    assert_eq!(srt::to_ass_text("\\<i>x</i>"), "\\\u{2060}{\\i1}x{\\i0}");
+    assert_eq!(
+        srt::to_ass_text("<font face=\"}{\\b1\">x</font>"),
+        "{\\fnb1}x{\\fn}"
+    );
+    // alignment as written by many players and by `Track::write_srt`
+    assert_eq!(srt::to_ass_text("{\\an8}Top"), "{\\an8}Top");
+    assert_eq!(srt::to_ass_text("{\\an0}x"), "\\{\\an0\\}x");
+}
+
+#[test]
+fn srt_track() {
+    let library = Library::new().unwrap();
+    let style = library.new_track().unwrap().style(0).unwrap();
+    let track = library.new_track_from_srt(SRT, &style).unwrap();
+    let events: Vec<_> = track
+        .events()
+        .map(|e| (e.start(), e.duration(), e.text().into_owned()))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            (
+                1000,
+                3000,
+                "{\\i1}First{\\i0} line\\Nsecond line".to_string()
+            ),
+            (5500, 500, "Coordinates are ignored".to_string()),
+            (60_000, 3_540_001, "Missing blank line before".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeSet;
+use std::convert::TryInto;
+use std::fs;
+use std::path::PathBuf;
+
+use libass::{subset_font, Library, Track};
+use ttf_parser::{name_id, Face, GlyphId, OutlineBuilder, Tag};
+
+const DEJAVU: &[u8] = include_bytes!("fonts/DejaVuSans.ttf");
+// a CID-keyed CFF font with one font DICT per glyph
+const FD_ARRAY: &[u8] = include_bytes!("fonts/FDArrayTest257.otf");
+
+struct Ignore;
+
+impl OutlineBuilder for Ignore {
+    fn move_to(&mut self, _: f32, _: f32) {}
+    fn line_to(&mut self, _: f32, _: f32) {}
+    fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) {}
+    fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {}
+    fn close(&mut self) {}
+}
+
+fn fonts_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libass-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// DejaVu Sans marked as bold, with its `maxp` table renamed so that it
+// can be found but not subset
+fn broken_bold() -> Vec<u8> {
+    let mut data = DEJAVU.to_vec();
+    let count = u16::from_be_bytes([data[4], data[5]]) as usize;
+    for record in (0..count).map(|i| 12 + 16 * i) {
+        let offset = u32::from_be_bytes(data[record + 8..record + 12].try_into().unwrap());
+        match &data[record..record + 4] {
+            b"OS/2" => {
+                let weight = offset as usize + 4;
+                data[weight..weight + 2].copy_from_slice(&700u16.to_be_bytes());
+            }
+            b"maxp" => data[record + 3] = b'q',
+            _ => {}
+        }
+    }
+    data
+}
+
+fn font_names(track: &Track) -> Vec<String> {
+    let style = track.style(1).unwrap().font_name;
+    let text = track.event(0).unwrap().text().into_owned();
+    vec![style.into_string().unwrap(), text]
+}
+
+fn has_outline(face: &Face, glyph: GlyphId) -> bool {
+    face.outline_glyph(glyph, &mut Ignore).is_some()
+}
+
+fn names(face: &Face, id: u16) -> Vec<String> {
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == id)
+        .filter_map(|name| name.to_string())
+        .collect()
+}
+
+// the names in the Name INDEX of a CFF table
+fn cff_names(cff: &[u8]) -> Vec<String> {
+    let at = cff[2] as usize;
+    let count = u16::from_be_bytes(cff[at..at + 2].try_into().unwrap()) as usize;
+    let offset_size = cff[at + 2] as usize;
+    let offset = |i: usize| {
+        let start = at + 3 + i * offset_size;
+        cff[start..start + offset_size]
+            .iter()
+            .fold(0, |value, &b| value << 8 | b as usize)
+    };
+    let data = at + 2 + (count + 1) * offset_size;
+    (0..count)
+        .map(|i| String::from_utf8(cff[data + offset(i)..data + offset(i + 1)].to_vec()).unwrap())
+        .collect()
+}
+
+#[test]
+fn subset_glyf_font() {
+    let chars: BTreeSet<char> = "Héllo, ﬁ!".chars().collect();
+    let subset = subset_font(DEJAVU, 0, &chars, "ABCDEF").unwrap();
+    let original = Face::parse(DEJAVU, 0).unwrap();
+    let face = Face::parse(&subset, 0).unwrap();
+    let glyf = |face: &Face| {
+        face.raw_face()
+            .table(Tag::from_bytes(b"glyf"))
+            .unwrap()
+            .len()
+    };
+    assert!(glyf(&face) < glyf(&original) / 20);
+    // glyph ids don't change
+    assert_eq!(face.number_of_glyphs(), original.number_of_glyphs());
+
+    for &c in &chars {
+        let glyph = face.glyph_index(c).unwrap();
+        assert_eq!(Some(glyph), original.glyph_index(c), "{:?}", c);
+        assert_eq!(
+            face.glyph_bounding_box(glyph),
+            original.glyph_bounding_box(glyph)
+        );
+    }
+    for c in "Zq€".chars() {
+        assert_eq!(face.glyph_index(c), None);
+        assert!(!has_outline(&face, original.glyph_index(c).unwrap()));
+    }
+    // é is a composite glyph, its components stay
+    assert!(has_outline(&face, face.glyph_index('é').unwrap()));
+    assert!(has_outline(&face, GlyphId(0)));
+
+    assert_eq!(names(&face, name_id::FAMILY), ["ABCDEF+DejaVu Sans"]);
+    assert_eq!(
+        names(&face, name_id::POST_SCRIPT_NAME),
+        ["ABCDEF+DejaVuSans"]
+    );
+}
+
+#[test]
+fn subset_glyf_font_keeps_substitutions() {
+    // the fi ligature is reachable from f and i through GSUB
+    let chars: BTreeSet<char> = "fi".chars().collect();
+    let subset = subset_font(DEJAVU, 0, &chars, "ABCDEF").unwrap();
+    let original = Face::parse(DEJAVU, 0).unwrap();
+    let face = Face::parse(&subset, 0).unwrap();
+
+    let ligature = original.glyph_index('ﬁ').unwrap();
+    assert_eq!(face.glyph_index('ﬁ'), None);
+    assert!(has_outline(&face, ligature));
+}
+
+#[test]
+fn subset_cff_font() {
+    let original = Face::parse(FD_ARRAY, 0).unwrap();
+    let mapped: Vec<char> = ('!'..='~')
+        .filter(|&c| original.glyph_index(c).is_some())
+        .collect();
+    assert!(mapped.len() > 10);
+    let (used, unused) = mapped.split_at(mapped.len() / 2);
+
+    let chars: BTreeSet<char> = used.iter().copied().collect();
+    let subset = subset_font(FD_ARRAY, 0, &chars, "ABCDEF").unwrap();
+    let face = Face::parse(&subset, 0).unwrap();
+    assert_eq!(face.number_of_glyphs(), original.number_of_glyphs());
+
+    for &c in used {
+        let glyph = face.glyph_index(c).unwrap();
+        assert_eq!(Some(glyph), original.glyph_index(c));
+        assert!(has_outline(&face, glyph));
+        assert_eq!(
+            face.glyph_bounding_box(glyph),
+            original.glyph_bounding_box(glyph)
+        );
+    }
+    for &c in unused {
+        assert_eq!(face.glyph_index(c), None);
+        assert!(!has_outline(&face, original.glyph_index(c).unwrap()));
+    }
+
+    let cff = face.raw_face().table(Tag::from_bytes(b"CFF ")).unwrap();
+    assert_eq!(cff_names(cff), ["ABCDEF+FDArrayTest257"]);
+    assert_eq!(
+        names(&face, name_id::POST_SCRIPT_NAME),
+        ["ABCDEF+FDArrayTest257"]
+    );
+}
+
+#[test]
+fn subset_rejects_broken_fonts() {
+    let chars: BTreeSet<char> = "a".chars().collect();
+    assert!(subset_font(b"not a font", 0, &chars, "ABCDEF").is_err());
+    assert!(subset_font(DEJAVU, 1, &chars, "ABCDEF").is_err());
+    assert!(subset_font(&DEJAVU[..DEJAVU.len() / 2], 0, &chars, "ABCDEF").is_err());
+
+    // a maxp table claiming no glyphs at all
+    let face = Face::parse(DEJAVU, 0).unwrap();
+    let maxp = face.raw_face().table(Tag::from_bytes(b"maxp")).unwrap();
+    let at = maxp.as_ptr() as usize - DEJAVU.as_ptr() as usize;
+    let mut font = DEJAVU.to_vec();
+    font[at + 4..at + 6].copy_from_slice(&[0, 0]);
+    assert!(subset_font(&font, 0, &chars, "ABCDEF").is_err());
+}
+
+#[test]
+fn subset_track_fonts() {
+    let dir = fonts_dir("subset");
+    fs::write(dir.join("DejaVuSans.ttf"), DEJAVU).unwrap();
+
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    let mut style = track.style(0).unwrap();
+    style.font_name = std::ffi::CString::new("DejaVu Sans").unwrap();
+    style.bold = false;
+    track.add_style(&style);
+    let mut event = track.add_event();
+    event.set_duration(1000);
+    event.set_style(1);
+    event.set_text("Regular {\\fn@DejaVu Sans}vertical");
+    let subsets = track.subset_fonts(&dir).unwrap();
+
+    assert!(subsets.failed.is_empty());
+    assert_eq!(subsets.fonts.len(), 1);
+    let font = &subsets.fonts[0];
+    let prefix = &font.family[..7];
+    assert_eq!(font.family, format!("{}DejaVu Sans", prefix));
+    assert_eq!(font.file_name, format!("{}DejaVuSans.ttf", prefix));
+    assert_eq!(
+        font_names(&track),
+        [
+            format!("{}DejaVu Sans", prefix),
+            format!("Regular {{\\fn@{}DejaVu Sans}}vertical", prefix)
+        ]
+    );
+
+    // the bold face can't be subset, so the family keeps its name
+    fs::write(dir.join("DejaVuSans-Bold.ttf"), broken_bold()).unwrap();
+    let mut track = library.new_track().unwrap();
+    track.add_style(&style);
+    let mut event = track.add_event();
+    event.set_duration(1000);
+    event.set_style(1);
+    event.set_text("Regular {\\b1}bold");
+    let subsets = track.subset_fonts(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(subsets.fonts.is_empty());
+    assert_eq!(subsets.failed.len(), 1);
+    assert_eq!(subsets.failed[0].path, dir.join("DejaVuSans-Bold.ttf"));
+    assert_eq!(font_names(&track), ["DejaVu Sans", "Regular {\\b1}bold"]);
+}
@@ -0,0 +1,246 @@
+use libass::script::Color;
+use libass::tags::{ClipShape, EventText, Item, KaraokeKind, Override, Part, Tag};
+
+fn tags(text: &str) -> Vec<Tag> {
+    EventText::parse(text).tags().cloned().collect()
+}
+
+fn unknown(name: &str, args: &str) -> Tag {
+    Tag::Unknown {
+        name: name.to_string(),
+        args: args.to_string(),
+    }
+}
+
+#[test]
+fn tags_tokenize() {
+    let text = EventText::parse("{comment\\fnArial Bold\\fscx120\\1c&HFF&\\xyz5\\b 1}Text");
+    assert_eq!(
+        text.parts,
+        [
+            Part::Block(vec![
+                Item::Comment("comment".to_string()),
+                Item::Tag(Tag::FontName("Arial Bold".to_string()).into()),
+                Item::Tag(unknown("fscx", "120").into()),
+                Item::Tag(
+                    Tag::Color {
+                        index: 1,
+                        color: Some(Color::from_abgr(0xff)),
+                    }
+                    .into()
+                ),
+                Item::Tag(unknown("xyz", "5").into()),
+                Item::Tag(Tag::Bold(Some(1)).into()),
+            ]),
+            Part::Text("Text".to_string()),
+        ]
+    );
+    // everything is written back as it was
+    assert_eq!(
+        text.to_string(),
+        "{comment\\fnArial Bold\\fscx120\\1c&HFF&\\xyz5\\b 1}Text"
+    );
+}
+
+#[test]
+fn tags_split_args() {
+    assert_eq!(
+        tags("{\\move( 1, 2 ,3,4)\\pos(1.5,-2)}"),
+        [
+            Tag::Move {
+                from: (1.0, 2.0),
+                to: (3.0, 4.0),
+                times: None,
+            },
+            Tag::Pos { x: 1.5, y: -2.0 },
+        ]
+    );
+    // commas inside nested tags don't split the arguments of \t
+    assert_eq!(
+        tags("{\\t(0,500,0.5,\\clip(0,0,10,10)\\fs20)}"),
+        [Tag::Transform {
+            times: Some((0, 500)),
+            accel: Some(0.5),
+            tags: vec![
+                Override::new(Tag::Clip {
+                    inverse: false,
+                    shape: ClipShape::Rect {
+                        x1: 0.0,
+                        y1: 0.0,
+                        x2: 10.0,
+                        y2: 10.0,
+                    },
+                }),
+                Override::new(Tag::FontSize(Some(20.0))),
+            ],
+        }]
+    );
+    assert_eq!(
+        tags("{\\t(\\alpha&HFF&)\\t(2,\\bord0)}"),
+        [
+            Tag::Transform {
+                times: None,
+                accel: None,
+                tags: vec![Override::new(Tag::Alpha {
+                    index: None,
+                    alpha: Some(0xff),
+                })],
+            },
+            Tag::Transform {
+                times: None,
+                accel: Some(2.0),
+                tags: vec![Override::new(Tag::Border(Some(0.0)))],
+            },
+        ]
+    );
+}
+
+#[test]
+fn tags_clips() {
+    let vector = |scale, commands: &str| ClipShape::Vector {
+        scale,
+        commands: commands.to_string(),
+    };
+    assert_eq!(
+        tags("{\\clip(1,2,3,4)\\iclip(m 0 0 l 10 0 10 10)\\clip(2,m 0 0 l 1 1)}"),
+        [
+            Tag::Clip {
+                inverse: false,
+                shape: ClipShape::Rect {
+                    x1: 1.0,
+                    y1: 2.0,
+                    x2: 3.0,
+                    y2: 4.0,
+                },
+            },
+            Tag::Clip {
+                inverse: true,
+                shape: vector(None, "m 0 0 l 10 0 10 10"),
+            },
+            Tag::Clip {
+                inverse: false,
+                shape: vector(Some(2), "m 0 0 l 1 1"),
+            },
+        ]
+    );
+    assert_eq!(
+        tags("{\\clip(1,2,3)\\clip(x,m 0 0)}"),
+        [unknown("clip", "(1,2,3)"), unknown("clip", "(x,m 0 0)")]
+    );
+
+    let mut text = EventText::parse("{\\iclip(2,m 0 0 l 1 1)}");
+    for tag in text.tags_mut() {
+        if let Tag::Clip { inverse, .. } = tag {
+            *inverse = false;
+        }
+    }
+    assert_eq!(text.to_string(), "{\\clip(2,m 0 0 l 1 1)}");
+}
+
+#[test]
+fn tags_karaoke_spelling() {
+    assert_eq!(
+        tags("{\\K20\\kf30}"),
+        [
+            Tag::Karaoke {
+                kind: KaraokeKind::Fill,
+                duration: 20.0,
+            },
+            Tag::Karaoke {
+                kind: KaraokeKind::Fill,
+                duration: 30.0,
+            },
+        ]
+    );
+
+    let mut text = EventText::parse("{\\K20}a{\\kf30}b{\\k10}c");
+    for tag in text.tags_mut() {
+        if let Tag::Karaoke { duration, .. } = tag {
+            *duration *= 2.0;
+        }
+    }
+    assert_eq!(text.to_string(), "{\\K40}a{\\kf60}b{\\k20}c");
+
+    // a different kind is written with its own name
+    let mut text = EventText::parse("{\\K20}a");
+    for tag in text.tags_mut() {
+        if let Tag::Karaoke { kind, .. } = tag {
+            *kind = KaraokeKind::Outline;
+        }
+    }
+    assert_eq!(text.to_string(), "{\\ko20}a");
+}
+
+#[test]
+fn tags_karaoke_default_duration() {
+    let karaoke = |kind| Tag::Karaoke {
+        kind,
+        duration: 100.0,
+    };
+    assert_eq!(
+        tags("{\\k\\K\\kf\\ko}"),
+        [
+            karaoke(KaraokeKind::Instant),
+            karaoke(KaraokeKind::Fill),
+            karaoke(KaraokeKind::Fill),
+            karaoke(KaraokeKind::Outline),
+        ]
+    );
+    assert_eq!(tags("{\\kt}"), [unknown("kt", "")]);
+    // the bare spelling is kept until the tag is edited
+    assert_eq!(EventText::parse("{\\k}a").to_string(), "{\\k}a");
+}
+
+#[test]
+fn tags_malformed() {
+    let cases = [
+        "{\\pos(1)}a",
+        "{\\move(1,2,3,4}b",
+        "{\\b}{\\bx}{\\fs}{\\fsabc}",
+        "{}{\\}{\\\\}",
+        "unclosed {\\b1",
+        "stray } brace",
+        "{\\t()\\t(1,2,3,4,5,\\b1)}",
+    ];
+    for text in &cases {
+        // kept as is
+        assert_eq!(EventText::parse(text).to_string(), *text);
+    }
+
+    assert_eq!(
+        tags("{\\pos(1)\\b\\bx\\fsabc}"),
+        [
+            unknown("pos", "(1)"),
+            Tag::Bold(None),
+            unknown("b", "x"),
+            unknown("fs", "abc"),
+        ]
+    );
+    // the missing parenthesis is implied
+    assert_eq!(
+        tags("{\\move(1,2,3,4}"),
+        [Tag::Move {
+            from: (1.0, 2.0),
+            to: (3.0, 4.0),
+            times: None,
+        }]
+    );
+    assert_eq!(
+        EventText::parse("unclosed {\\b1").parts,
+        [Part::Text("unclosed {\\b1".to_string())]
+    );
+}
+
+#[test]
+fn tags_escaped_braces() {
+    let text = EventText::parse("\\{not a block\\} {\\i1}x");
+    assert_eq!(
+        text.parts,
+        [
+            Part::Text("\\{not a block\\} ".to_string()),
+            Part::Block(vec![Item::Tag(Tag::Italic(Some(true)).into())]),
+            Part::Text("x".to_string()),
+        ]
+    );
+    assert_eq!(text.plain_text(), "{not a block} x");
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "testing")]
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+
+use libass::testing::{hermetic_library, Frame, Harness};
+use libass::{ImageKind, Layer};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libass-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn pixel(frame: &Frame, x: u32, y: u32) -> [u8; 4] {
+    let i = (y * frame.width + x) as usize * 4;
+    [
+        frame.data[i],
+        frame.data[i + 1],
+        frame.data[i + 2],
+        frame.data[i + 3],
+    ]
+}
+
+fn harness_script() -> (Vec<u8>, String) {
+    let font = fs::read("tests/fonts/DejaVuSans.ttf").unwrap();
+    let script = fs::read_to_string("tests/scripts/basic.ass").unwrap();
+    (font, script)
+}
+
+#[test]
+fn testing_blend() {
+    let mut frame = Frame::new(4, 3);
+    assert_eq!(pixel(&frame, 0, 0), [0, 0, 0, 255]);
+
+    // a half transparent green layer hanging off the top left corner
+    frame.blend(&Layer {
+        width: 2,
+        height: 2,
+        bitmap: vec![255, 128, 0, 255],
+        color: 0x00FF_0080,
+        x: -1,
+        y: 1,
+        kind: ImageKind::Character,
+    });
+    assert_eq!(pixel(&frame, 0, 1), [0, 63, 0, 255]);
+    assert_eq!(pixel(&frame, 0, 2), [0, 127, 0, 255]);
+    assert_eq!(pixel(&frame, 1, 1), [0, 0, 0, 255]);
+    assert_eq!(pixel(&frame, 0, 0), [0, 0, 0, 255]);
+}
+
+#[test]
+fn testing_compare() {
+    let black = Frame::new(3, 2);
+    let mut other = black.clone();
+    other.data[4] = 3;
+    other.data[8 + 2] = 10;
+
+    assert!(black.compare(&black, 0).is_none());
+    assert!(other.compare(&black, 10).is_none());
+    let mismatch = other.compare(&black, 3).unwrap();
+    assert_eq!((mismatch.pixels, mismatch.max_difference), (1, 10));
+    // differing pixels are red, the others are the actual frame dimmed
+    assert_eq!(pixel(&mismatch.diff, 2, 0), [255, 0, 0, 255]);
+    assert_eq!(pixel(&mismatch.diff, 1, 0), [0, 0, 0, 255]);
+
+    let mismatch = black.compare(&Frame::new(2, 3), 255).unwrap();
+    assert_eq!((mismatch.pixels, mismatch.max_difference), (6, 255));
+}
+
+#[test]
+fn testing_png_round_trip() {
+    let dir = temp_dir("png");
+    let mut frame = Frame::new(5, 4);
+    for (i, byte) in frame.data.iter_mut().enumerate() {
+        *byte = (i * 13) as u8;
+    }
+    let path = dir.join("frame.png");
+    frame.write_png(&path).unwrap();
+    assert_eq!(Frame::read_png(&path).unwrap(), frame);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn testing_golden() {
+    let (font, script) = harness_script();
+    let library = hermetic_library(&[("DejaVuSans.ttf", &font)]).unwrap();
+    let mut harness = Harness::new(&library, 160, 90, "DejaVu Sans").unwrap();
+    harness.set_tolerance(2);
+    harness.assert_golden(&script, 1000, "tests/golden/basic-1000.png");
+}
+
+#[test]
+fn testing_golden_mismatch() {
+    let dir = temp_dir("golden");
+    let golden = dir.join("basic.png");
+    fs::copy("tests/golden/basic-1000.png", &golden).unwrap();
+
+    let (font, script) = harness_script();
+    let library = hermetic_library(&[("DejaVuSans.ttf", &font)]).unwrap();
+    let mut harness = Harness::new(&library, 160, 90, "DejaVu Sans").unwrap();
+    // the second rectangle is on screen too
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        harness.assert_golden(&script, 1500, &golden);
+    }));
+    assert!(result.is_err());
+
+    let actual = Frame::read_png(dir.join("basic.actual.png")).unwrap();
+    assert_eq!(actual, harness.render(&script, 1500).unwrap());
+    let diff = Frame::read_png(dir.join("basic.diff.png")).unwrap();
+    assert_eq!(pixel(&diff, 110, 60), [255, 0, 0, 255]);
+    assert_eq!(pixel(&diff, 30, 20), [63, 0, 0, 255]);
+    fs::remove_dir_all(dir).unwrap();
+}
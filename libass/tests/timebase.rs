@@ -0,0 +1,130 @@
+use libass::{Library, Timebase};
+
+// 25 fps, except frames 25 to 49 at 50 fps
+const V1: &str = "# timecode format v1
+Assume 25
+# comment
+25,49,50
+";
+
+fn v1_times(frames: i64) -> Vec<f64> {
+    (0..frames)
+        .map(|frame| match frame {
+            0..=25 => frame as f64 * 40.0,
+            26..=50 => 1000.0 + (frame - 25) as f64 * 20.0,
+            _ => 1500.0 + (frame - 50) as f64 * 40.0,
+        })
+        .collect()
+}
+
+#[test]
+fn timebase_constant() {
+    let timebase = Timebase::constant(24000, 1001).unwrap();
+    assert_eq!(timebase.frame_time(24), 1001.0);
+    assert_eq!(timebase.timestamp(1), 42);
+    assert_eq!(timebase.frame_at(1000), 23);
+    assert_eq!(timebase.frame_at(1001), 24);
+    assert_eq!(timebase.frame_at(-1), -1);
+    assert!(Timebase::constant(0, 1).is_err());
+}
+
+#[test]
+fn timebase_v1() {
+    let timebase = Timebase::parse_timecodes(V1).unwrap();
+    for (frame, &time) in v1_times(100).iter().enumerate() {
+        assert!((timebase.frame_time(frame as i64) - time).abs() < 1e-9);
+    }
+    assert_eq!(timebase.frame_at(999), 24);
+    assert_eq!(timebase.frame_at(1000), 25);
+    assert_eq!(timebase.frame_at(1019), 25);
+    assert_eq!(timebase.frame_at(1020), 26);
+    assert_eq!(timebase.frame_at(1499), 49);
+    assert_eq!(timebase.frame_at(1500), 50);
+    assert_eq!(timebase.frame_at(1540), 51);
+    assert_eq!(timebase.frame_at(-40), -1);
+    assert_eq!(timebase.frame_time(-1), -40.0);
+}
+
+#[test]
+fn timebase_v1_long_ranges() {
+    // ranges are kept as they are instead of expanded to frames
+    let data = "# timecode format v1\nassume 25\n0,4000000000,50\n";
+    let timebase = Timebase::parse_timecodes(data).unwrap();
+    assert_eq!(timebase.frame_time(4_000_000_000), 80_000_000_000.0);
+    assert_eq!(timebase.frame_time(4_000_000_002), 80_000_000_060.0);
+    assert_eq!(timebase.frame_at(80_000_000_059), 4_000_000_001);
+}
+
+#[test]
+fn timebase_v1_errors() {
+    let parse = |data: &str| Timebase::parse_timecodes(data);
+    assert!(parse("# timecode format v1\n").is_err());
+    assert!(parse("# timecode format v1\nAssume 0\n").is_err());
+    assert!(parse("# timecode format v1\nAssume 25\n10,5,50\n").is_err());
+    assert!(parse("# timecode format v1\nAssume 25\n0,10,50\n10,20,30\n").is_err());
+    assert!(parse("# timecode format v1\nAssume 25\n0,10\n").is_err());
+    assert!(parse("# timecode format v1\nAssume 25\n0,10,inf\n").is_err());
+    assert!(parse("# timecode format v3\nAssume 25\n").is_err());
+    assert!(parse("# timecode format v1\nAssume 25\n20,29,50\n0,9,50\n").is_ok());
+}
+
+#[test]
+fn timebase_v2() {
+    let data = "# timecode format v2\n0\n41.708\n83.417\n\n125.125\n";
+    let timebase = Timebase::parse_timecodes(data).unwrap();
+    assert_eq!(timebase.frame_time(2), 83.417);
+    assert_eq!(timebase.timestamp(2), 84);
+    assert_eq!(timebase.frame_at(83), 1);
+    assert_eq!(timebase.frame_at(84), 2);
+    // the last interval continues
+    assert!((timebase.frame_time(5) - 208.541).abs() < 1e-9);
+    assert_eq!(timebase.frame_at(-42), -2);
+
+    assert!(Timebase::parse_timecodes("# timecode format v2\n0\n").is_err());
+    assert!(Timebase::parse_timecodes("# timecode format v2\n0\n10\n5\n").is_err());
+    assert!(Timebase::parse_timecodes("# timecode format v2\n0\nx\n").is_err());
+}
+
+#[test]
+fn timebase_v1_matches_v2() {
+    let v1 = Timebase::parse_timecodes(V1).unwrap();
+    let v2 = Timebase::from_timestamps(v1_times(60)).unwrap();
+    for frame in -5..100 {
+        assert!((v1.frame_time(frame) - v2.frame_time(frame)).abs() < 1e-9);
+    }
+    for ms in (-100..4000).step_by(7) {
+        assert_eq!(v1.frame_at(ms), v2.frame_at(ms), "{}", ms);
+    }
+}
+
+#[test]
+fn timeline_frames() {
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    for &(start, duration, text) in &[
+        (1000, 1000, "First"),
+        (3000, 1000, "Second"),
+        (5000, 200, "{\\move(0,0,100,100)}Moving"),
+    ] {
+        let mut event = track.add_event();
+        event.set_start(start);
+        event.set_duration(duration);
+        event.set_text(text);
+    }
+    let timeline = track.change_points();
+
+    let constant = Timebase::constant(25, 1).unwrap();
+    let frames: Vec<_> = timeline.frames(&constant, 0..200).collect();
+    let mut expected = vec![(0, 0), (25, 1000), (50, 2000), (75, 3000), (100, 4000)];
+    expected.extend((125..130).map(|frame| (frame, frame * 40)));
+    expected.push((130, 5200));
+    assert_eq!(frames, expected);
+
+    let v1 = Timebase::parse_timecodes(V1).unwrap();
+    let frames: Vec<_> = timeline.frames(&v1, 0..200).collect();
+    assert_eq!(frames[..3], [(0, 0), (25, 1000), (63, 2020)]);
+    for (frame, now) in timeline.frames(&v1, 0..200) {
+        assert_eq!(v1.frame_at(now), frame);
+    }
+    assert_eq!(timeline.frames(&v1, 10..10).count(), 0);
+}
@@ -0,0 +1,126 @@
+use libass::{ChangePoint, Library, Timeline};
+
+const HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+fn time(ms: i64) -> String {
+    let cs = ms / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+fn timeline(events: &[(i64, i64, &str)]) -> Timeline {
+    let mut script = HEADER.to_string();
+    for &(start, end, text) in events {
+        script += &format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            time(start),
+            time(end),
+            text
+        );
+    }
+    let library = Library::new().unwrap();
+    let track = library
+        .new_track_from_memory(script.as_bytes(), "UTF-8")
+        .unwrap();
+    track.change_points()
+}
+
+fn points(timeline: &Timeline) -> Vec<(i64, bool)> {
+    timeline
+        .points()
+        .iter()
+        .map(|&ChangePoint { time, animated }| (time, animated))
+        .collect()
+}
+
+#[test]
+fn timeline_static_events() {
+    let timeline = timeline(&[
+        (1500, 3000, "Second"),
+        (1000, 2000, "First"),
+        (1000, 1000, "Never shown"),
+        (4000, 3000, "Negative"),
+    ]);
+    assert_eq!(
+        points(&timeline),
+        [(1000, false), (1500, false), (2000, false), (3000, false)]
+    );
+    assert_eq!(timeline.next_change(0), Some(1000));
+    assert_eq!(timeline.next_change(1000), Some(1500));
+    assert_eq!(timeline.next_change(2999), Some(3000));
+    assert_eq!(timeline.next_change(3000), None);
+    assert!(!timeline.is_animated(1200));
+}
+
+#[test]
+fn timeline_animations() {
+    let timeline = timeline(&[(5000, 6000, "{\\move(0,0,10,10,100,300)}Moving")]);
+    assert_eq!(
+        points(&timeline),
+        [(5000, false), (5100, true), (5300, false), (6000, false)]
+    );
+    assert_eq!(timeline.next_change(5000), Some(5100));
+    assert_eq!(timeline.next_change(5100), Some(5101));
+    assert_eq!(timeline.next_change(5299), Some(5300));
+    assert_eq!(timeline.next_change(5300), Some(6000));
+    assert!(timeline.is_animated(5200));
+    assert!(!timeline.is_animated(5300));
+
+    // a \move without times lasts as long as the event
+    let timeline = self::timeline(&[(0, 1000, "{\\move(0,0,10,10)}Moving")]);
+    assert_eq!(points(&timeline), [(0, true), (1000, false)]);
+}
+
+#[test]
+fn timeline_merges_spans() {
+    // \fad gives 0-200 and 800-1000, \t gives 100-300
+    let timeline = timeline(&[
+        (0, 1000, "{\\fad(200,200)\\t(100,300,\\fs20)}Text"),
+        // starts while the text is being animated
+        (150, 2000, "Later"),
+    ]);
+    assert_eq!(
+        points(&timeline),
+        [
+            (0, true),
+            (300, false),
+            (800, true),
+            (1000, false),
+            (2000, false)
+        ]
+    );
+    assert_eq!(timeline.next_change(150), Some(151));
+    assert_eq!(timeline.next_change(300), Some(800));
+    assert_eq!(timeline.next_change(999), Some(1000));
+}
+
+#[test]
+fn timeline_karaoke() {
+    let timeline = timeline(&[(0, 5000, "{\\k50}a{\\kf100}b{\\k50}c")]);
+    assert_eq!(
+        points(&timeline),
+        [(0, false), (500, true), (1500, false), (5000, false)]
+    );
+}
+
+#[test]
+fn timeline_empty() {
+    let timeline = timeline(&[]);
+    assert!(timeline.points().is_empty());
+    assert_eq!(timeline.next_change(0), None);
+    assert!(!timeline.is_animated(0));
+}
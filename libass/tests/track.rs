@@ -0,0 +1,108 @@
+use libass::script::Script;
+use libass::{Library, RenderPool, RendererConfig, Track};
+
+const SCRIPT: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1280
+PlayResY: 720
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Main,Arial,48,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,2,20,20,30,1
+Style: Sign,Georgia,36,&H0000FFFF,&H000000FF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,0,0,8,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:00:01.00,0:00:03.00,Main,Alice,0,0,0,,First
+Dialogue: 1,0:00:02.00,0:00:04.00,Sign,,15,25,40,Banner;20,{\\pos(640,100)}Sign
+Dialogue: 0,0:00:05.00,0:00:06.00,Main,Bob,0,0,0,,Last
+";
+
+fn script(track: &Track) -> String {
+    Script::from_track(track).to_string()
+}
+
+#[test]
+fn try_clone_is_independent() {
+    let library = Library::new().unwrap();
+    let original = library
+        .new_track_from_memory(SCRIPT.as_bytes(), "UTF-8")
+        .unwrap();
+    let before = script(&original);
+    let styles = original.style_count();
+
+    let mut copy = original.try_clone().unwrap();
+    assert_eq!(script(&copy), before);
+
+    let mut event = copy.event_mut(0).unwrap();
+    event.set_text("Changed");
+    event.set_start(0);
+    copy.add_event().set_text("Added");
+    copy.retain_events(|e| e.text() != "Last");
+    let mut style = copy.style(0).unwrap();
+    style.font_size = 12.0;
+    copy.add_style(&style);
+    copy.process_data(&mut b"[Script Info]\nPlayResX: 1920\nPlayResY: 1080\n".to_vec());
+
+    let after = script(&copy);
+    assert_ne!(after, before);
+    assert!(after.contains("PlayResX: 1920"));
+    assert_eq!(script(&original), before);
+    assert_eq!(original.event_count(), 3);
+    assert_eq!(original.style_count(), styles);
+    assert_eq!(copy.style_count(), styles + 1);
+    assert_eq!(original.event(0).unwrap().text(), "First");
+
+    drop(copy);
+    assert_eq!(script(&original), before);
+}
+
+#[test]
+fn render_pool_rejects_other_libraries() {
+    let library = Library::new().unwrap();
+    let other = Library::new().unwrap();
+    let mut pool = RenderPool::new(&library, 2, &RendererConfig::default()).unwrap();
+
+    let track = library.new_track().unwrap();
+    assert!(pool.set_track(&track).is_ok());
+    let foreign = other.new_track().unwrap();
+    assert!(pool.set_track(&foreign).is_err());
+}
+
+fn texts(track: &Track) -> Vec<String> {
+    track.events().map(|e| e.text().into_owned()).collect()
+}
+
+#[test]
+fn retain_events() {
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    for (i, text) in ["A", "B", "C", "D", "E"].iter().enumerate() {
+        let mut event = track.add_event();
+        event.set_start(i as i64 * 1000);
+        event.set_duration(500);
+        event.set_text(text);
+    }
+
+    track.retain_events(|_| true);
+    assert_eq!(texts(&track), ["A", "B", "C", "D", "E"]);
+
+    track.retain_events(|e| e.text() != "B" && e.text() != "D");
+    assert_eq!(texts(&track), ["A", "C", "E"]);
+    // the remaining events are renumbered and keep their data
+    let starts: Vec<_> = track.events().map(|e| e.start()).collect();
+    assert_eq!(starts, [0, 2000, 4000]);
+    assert_eq!(track.event(2).unwrap().text(), "E");
+    assert!(track.event(3).is_none());
+
+    // new events go after the remaining ones
+    let id = track.add_event().id;
+    assert_eq!(id, 3);
+
+    track.retain_events(|e| e.start() >= 2000 && e.duration() > 0);
+    assert_eq!(texts(&track), ["C", "E"]);
+
+    track.retain_events(|_| false);
+    assert_eq!(track.event_count(), 0);
+    assert_eq!(script(&track), script(&library.new_track().unwrap()));
+}
@@ -0,0 +1,51 @@
+use libass::{Library, TranscriptLine};
+
+#[test]
+fn transcript() {
+    let library = Library::new().unwrap();
+    let mut track = library.new_track().unwrap();
+    let events: &[(i64, i64, &str, &str)] = &[
+        (3000, 4000, "", "{\\p1}m 0 0 l 10 10{\\p0}"),
+        (
+            1000,
+            3500,
+            "Alice",
+            "{\\i1}Two\\Nlines{\\i0}   and\\hspaces",
+        ),
+        (1000, 2000, "", "{a comment}Same start, added later"),
+        (0, 500, "Bob", "{\\pos(1,2)}First \\{literal\\} C:\\temp"),
+        (5000, 6000, "", "   "),
+    ];
+    for &(start, end, name, text) in events {
+        let mut event = track.add_event();
+        event.set_start(start);
+        event.set_duration(end - start);
+        event.set_name(name);
+        event.set_text(text);
+    }
+
+    let line = |start, end, name: &str, text: &str| TranscriptLine {
+        start,
+        end,
+        name: name.to_string(),
+        text: text.to_string(),
+    };
+    assert_eq!(
+        track.transcript(),
+        [
+            line(0, 500, "Bob", "First {literal} C:\\temp"),
+            line(1000, 3500, "Alice", "Two lines and spaces"),
+            line(1000, 2000, "", "Same start, added later"),
+        ]
+    );
+
+    let mut out = Vec::new();
+    track.write_transcript(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "[00:00:00.000 --> 00:00:00.500] Bob: First {literal} C:\\temp
+[00:00:01.000 --> 00:00:03.500] Alice: Two lines and spaces
+[00:00:01.000 --> 00:00:02.000] Same start, added later
+"
+    );
+}
@@ -0,0 +1,196 @@
+use libass::webvtt::{self, Align, Anchor, Cue, CueSettings, Line, Rule};
+use libass::{Library, Track};
+
+const VTT: &str = "\u{feff}WEBVTT - a header
+Kind: captions
+
+NOTE a comment
+spanning lines
+
+STYLE
+::cue { color: yellow; /* comment */ font-weight: bold }
+::cue(.loud), ::cue(#id), ::cue(.quiet) { font-size: 1.5em !important }
+
+REGION
+id:fred
+
+intro
+00:00:01.000 --> 00:00:04.000
+<v Alice>Hello</v>
+<i>world</i>
+
+00:05.500 --> 00:06.000 line:0 align:start position:10%,line-left size:50%
+Positioned
+
+3
+00:00:07.000 --> 00:00:08.000 line:-1,end align:middle bogus nokey:
+Last
+
+00:00:09.000 --> broken
+Skipped
+";
+
+fn track<'a>(library: &'a Library, vtt: &str) -> Track<'a> {
+    let mut style = library.new_track().unwrap().style(0).unwrap();
+    style.font_size = 20.0;
+    style.margin_l = 10;
+    style.margin_r = 10;
+    style.margin_v = 10;
+    // bottom center
+    style.alignment = 2;
+    library.new_track_from_webvtt(vtt, &style).unwrap()
+}
+
+fn events(track: &Track) -> Vec<(i64, i64, String, String, [i32; 3])> {
+    track
+        .events()
+        .map(|e| {
+            (
+                e.start(),
+                e.end(),
+                e.name().into_owned(),
+                e.text().into_owned(),
+                [e.margin_l(), e.margin_r(), e.margin_v()],
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn webvtt_parse() {
+    let vtt = webvtt::parse(VTT);
+    assert_eq!(
+        vtt.cues
+            .iter()
+            .map(|c| (c.id.as_deref(), c.start, c.end, c.text.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (
+                Some("intro"),
+                1000,
+                4000,
+                "<v Alice>Hello</v>\n<i>world</i>"
+            ),
+            (None, 5500, 6000, "Positioned"),
+            (Some("3"), 7000, 8000, "Last"),
+        ]
+    );
+    assert_eq!(
+        vtt.rules,
+        [
+            Rule {
+                class: None,
+                declarations: vec![
+                    ("color".to_string(), "yellow".to_string()),
+                    ("font-weight".to_string(), "bold".to_string()),
+                ],
+            },
+            Rule {
+                class: Some("loud".to_string()),
+                declarations: vec![("font-size".to_string(), "1.5em".to_string())],
+            },
+            Rule {
+                class: Some("quiet".to_string()),
+                declarations: vec![("font-size".to_string(), "1.5em".to_string())],
+            },
+        ]
+    );
+    assert_eq!(webvtt::parse("WEBVTT\n"), Default::default());
+}
+
+#[test]
+fn webvtt_settings() {
+    let cues: Vec<Cue> = webvtt::parse(VTT).cues;
+    assert_eq!(cues[0].settings, CueSettings::default());
+    assert_eq!(
+        cues[1].settings,
+        CueSettings {
+            line: Some(Line::Number(0)),
+            line_align: None,
+            position: Some(10.0),
+            position_align: Some(Anchor::Start),
+            size: Some(50.0),
+            align: Some(Align::Start),
+        }
+    );
+    assert_eq!(
+        cues[2].settings,
+        CueSettings {
+            line: Some(Line::Number(-1)),
+            line_align: Some(Anchor::End),
+            align: Some(Align::Center),
+            ..CueSettings::default()
+        }
+    );
+
+    let settings = |settings: &str| {
+        let vtt = format!("WEBVTT\n\n00:00.000 --> 00:01.000 {}\nx\n", settings);
+        webvtt::parse(&vtt).cues[0].settings.clone()
+    };
+    assert_eq!(settings("line:25.5%").line, Some(Line::Percent(25.5)));
+    assert_eq!(settings("line:x").line, None);
+    assert_eq!(settings("position:50").position, None);
+    assert_eq!(settings("align:right").align, Some(Align::Right));
+    assert_eq!(settings("align:justify").align, None);
+}
+
+#[test]
+fn webvtt_track() {
+    let library = Library::new().unwrap();
+    let track = track(&library, VTT);
+    let no_margins = [0, 0, 0];
+    assert_eq!(
+        events(&track),
+        [
+            (
+                1000,
+                4000,
+                "Alice".to_string(),
+                "Hello\\N{\\i1}world{\\i0}".to_string(),
+                no_margins
+            ),
+            (
+                5500,
+                6000,
+                String::new(),
+                "{\\an7\\pos(38,10)}Positioned".to_string(),
+                no_margins
+            ),
+            (
+                7000,
+                8000,
+                String::new(),
+                "{\\an2}Last".to_string(),
+                [0, 0, 10]
+            ),
+        ]
+    );
+
+    // the ::cue rule applies to the style of every event
+    let style = track.style(track.event(0).unwrap().style()).unwrap();
+    assert!(style.bold);
+    assert_eq!(style.primary_color, 0xffff_0000);
+}
+
+#[test]
+fn webvtt_classes_and_escapes() {
+    let vtt = "WEBVTT
+
+STYLE
+::cue(.loud) { font-size: 2em }
+
+00:00.000 --> 00:01.000
+<c.loud>{x}</c> <c.unknown>a\\b\\N</c> &lt;&#123;&gt;
+";
+    let library = Library::new().unwrap();
+    let track = track(&library, vtt);
+    assert_eq!(
+        track.event(0).unwrap().text(),
+        "{\\rloud}\\{x\\}{\\r} a\\b\\\u{2060}N <\\{>"
+    );
+    let loud = (0..track.style_count() as i32)
+        .filter_map(|id| track.style(id))
+        .find(|style| style.name.to_str() == Ok("loud"))
+        .unwrap();
+    assert_eq!(loud.font_size, 40.0);
+}